@@ -0,0 +1,98 @@
+use crate::{colour::Colour, filter::Filter, integrator::PathSample, vector::Vec3D};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Aov {
+    Depth,
+    Normal,
+    Albedo,
+    ObjectId,
+    Position,
+    Direct,
+    Indirect,
+}
+
+impl Aov {
+    pub const ALL: [Self; 7] = [
+        Self::Depth,
+        Self::Normal,
+        Self::Albedo,
+        Self::ObjectId,
+        Self::Position,
+        Self::Direct,
+        Self::Indirect,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Depth => "depth",
+            Self::Normal => "normal",
+            Self::Albedo => "albedo",
+            Self::ObjectId => "object_id",
+            Self::Position => "position",
+            Self::Direct => "direct",
+            Self::Indirect => "indirect",
+        }
+    }
+
+    // The light passes share the beauty filter so that direct + indirect reproduces the image.
+    pub fn filter(&self, beauty: Filter) -> Filter {
+        match self {
+            Self::Direct | Self::Indirect => beauty,
+            _ => Filter::default(),
+        }
+    }
+
+    // ID colours cannot be blended, so that pass keeps only the first sample of each pixel.
+    pub fn single_sample(&self) -> bool {
+        *self == Self::ObjectId
+    }
+
+    pub fn value(&self, path: &PathSample) -> Colour {
+        let vector = |vector: Vec3D| Colour::new(vector.x, vector.y, vector.z);
+        match self {
+            Self::Depth => Colour::gray(path.depth),
+            Self::Normal => vector(path.normal),
+            Self::Albedo => path.albedo,
+            Self::ObjectId => path.object_id.map_or(Colour::default(), id_colour),
+            Self::Position => vector(path.position),
+            Self::Direct => path.direct,
+            Self::Indirect => path.indirect,
+        }
+    }
+}
+
+pub fn id_colour(id: usize) -> Colour {
+    let hash = (id as u32 + 1).wrapping_mul(0x9e37_79b9);
+    let hash = (hash ^ hash >> 15).wrapping_mul(0x85eb_ca6b);
+    let channel = |shift: u32| ((hash >> shift) & 0xff) as f32 / 255. * 0.8 + 0.2;
+    Colour::new(channel(0), channel(8), channel(16))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_values() {
+        let path = PathSample {
+            direct: Colour::gray(0.25),
+            indirect: Colour::gray(0.5),
+            depth: 3.,
+            normal: Vec3D::Y,
+            albedo: Colour::new(0.1, 0.2, 0.3),
+            position: Vec3D::new(1., 2., 3.),
+            object_id: Some(4),
+        };
+        assert_eq!(Aov::Depth.value(&path), Colour::gray(3.));
+        assert_eq!(Aov::Normal.value(&path), Colour::new(0., 1., 0.));
+        assert_eq!(Aov::Position.value(&path), Colour::new(1., 2., 3.));
+        assert_eq!(Aov::Indirect.value(&path), Colour::gray(0.5));
+        assert_eq!(Aov::ObjectId.value(&path), id_colour(4));
+        assert_eq!(
+            Aov::ObjectId.value(&PathSample::default()),
+            Colour::default()
+        );
+        assert_ne!(id_colour(0), id_colour(1));
+        assert!(id_colour(0).luminance() > 0.);
+    }
+}
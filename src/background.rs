@@ -0,0 +1,122 @@
+use std::{f32::consts::PI, hash::Hasher};
+
+use crate::{
+    checkpoint::{Fingerprint, Fingerprinter},
+    colour::Colour,
+    environment::Environment,
+    sky::Sky,
+    vector::Vec3D,
+};
+
+#[derive(Debug, Clone)]
+pub enum Background {
+    Solid(Colour),
+    Gradient {
+        bottom: Colour,
+        top: Colour,
+    },
+    Checker {
+        even: Colour,
+        odd: Colour,
+        divisions: usize,
+    },
+    Map(Environment),
+    Sky(Sky),
+}
+
+impl Default for Background {
+    fn default() -> Self {
+        Self::Solid(Colour::default())
+    }
+}
+
+impl Background {
+    pub fn value(&self, direction: Vec3D) -> Colour {
+        let direction = direction.normalise();
+        match self {
+            Self::Solid(colour) => *colour,
+            Self::Gradient { bottom, top } => {
+                let t = 0.5 * (direction.y.clamp(-1., 1.) + 1.);
+                *bottom * (1. - t) + *top * t
+            }
+            Self::Checker {
+                even,
+                odd,
+                divisions,
+            } => {
+                let u = 0.5 + direction.x.atan2(-direction.z) / (2. * PI);
+                let v = 0.5 + direction.y.clamp(-1., 1.).asin() / PI;
+                let divisions = *divisions as f32;
+                let cell = (u * 2. * divisions).floor() as i64 + (v * divisions).floor() as i64;
+                if cell.rem_euclid(2) == 0 {
+                    *even
+                } else {
+                    *odd
+                }
+            }
+            Self::Map(environment) => environment.value(direction),
+            Self::Sky(sky) => sky.sky_radiance(direction),
+        }
+    }
+}
+
+impl Fingerprint for Background {
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        match self {
+            Self::Map(environment) => {
+                state.write(b"Map");
+                environment.fingerprint(state);
+            }
+            _ => state.debug(self),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::Image;
+
+    #[test]
+    fn test_solid_and_gradient() {
+        let solid = Background::Solid(Colour::new(0.2, 0.7, 0.8));
+        assert_eq!(solid.value(Vec3D::X), Colour::new(0.2, 0.7, 0.8));
+        assert_eq!(Background::default().value(Vec3D::Y), Colour::default());
+        let gradient = Background::Gradient {
+            bottom: Colour::gray(0.),
+            top: Colour::new(0., 0., 1.),
+        };
+        assert_eq!(gradient.value(Vec3D::Y), Colour::new(0., 0., 1.));
+        assert_eq!(gradient.value(-Vec3D::Y), Colour::default());
+        assert_eq!(gradient.value(Vec3D::X * 3.), Colour::new(0., 0., 0.5));
+    }
+
+    #[test]
+    fn test_checker_alternates() {
+        let checker = Background::Checker {
+            even: Colour::gray(1.),
+            odd: Colour::gray(0.),
+            divisions: 4,
+        };
+        let first = checker.value(Vec3D::new(0.1, 0.1, -1.));
+        let across = checker.value(Vec3D::new(-0.1, 0.1, -1.));
+        let below = checker.value(Vec3D::new(0.1, -0.1, -1.));
+        assert_ne!(first, across);
+        assert_ne!(first, below);
+        assert_eq!(across, below);
+    }
+
+    #[test]
+    fn test_map_and_sky() {
+        let environment = Environment::new(Image::new(4, 2, Colour::gray(0.5)), 0., 2.).unwrap();
+        assert_eq!(
+            Background::Map(environment).value(Vec3D::Z),
+            Colour::gray(1.)
+        );
+        let sky = Sky::new(0.6, 0., 3.);
+        assert_eq!(
+            Background::Sky(sky).value(Vec3D::Y),
+            sky.sky_radiance(Vec3D::Y)
+        );
+    }
+}
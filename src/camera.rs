@@ -1,4 +1,9 @@
-use crate::{quaternion::Quaternion, ray::Ray, vector::Vec3D};
+use crate::{
+    checkpoint::{Fingerprint, Fingerprinter},
+    quaternion::Quaternion,
+    ray::Ray,
+    vector::Vec3D,
+};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Camera {
@@ -23,3 +28,9 @@ impl Camera {
         )
     }
 }
+
+impl Fingerprint for Camera {
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        state.debug(self);
+    }
+}
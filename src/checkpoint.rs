@@ -0,0 +1,358 @@
+use std::{
+    fmt::{self, Debug},
+    fs::{self, File},
+    hash::Hasher,
+    io::{self, BufReader, BufWriter, ErrorKind, Read, Write},
+    path::Path,
+    sync::Arc,
+};
+
+use crate::{colour::Colour, renderer::PixelStatistics};
+
+const MAGIC: &[u8; 4] = b"TRCK";
+const VERSION: u32 = 3;
+
+// FNV-1a, so that hashes stay stable across runs and toolchains.
+#[derive(Debug, Clone, Copy)]
+pub struct Fingerprinter(u64);
+
+impl Default for Fingerprinter {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Fingerprinter {
+    // For small plain-data values whose Debug output already covers every field.
+    pub fn debug(&mut self, value: &impl Debug) {
+        fmt::Write::write_fmt(self, format_args!("{value:?}")).unwrap();
+    }
+
+    pub fn floats(&mut self, values: impl IntoIterator<Item = f32>) {
+        for value in values {
+            self.write_u32(value.to_bits());
+        }
+    }
+}
+
+impl Hasher for Fingerprinter {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = (self.0 ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+impl fmt::Write for Fingerprinter {
+    fn write_str(&mut self, text: &str) -> fmt::Result {
+        self.write(text.as_bytes());
+        Ok(())
+    }
+}
+
+pub trait Fingerprint {
+    fn fingerprint(&self, state: &mut Fingerprinter);
+}
+
+impl<T: Fingerprint + ?Sized> Fingerprint for &T {
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        (**self).fingerprint(state);
+    }
+}
+
+impl<T: Fingerprint + ?Sized> Fingerprint for Box<T> {
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        (**self).fingerprint(state);
+    }
+}
+
+impl<T: Fingerprint + ?Sized> Fingerprint for Arc<T> {
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        (**self).fingerprint(state);
+    }
+}
+
+impl<T: Fingerprint> Fingerprint for Option<T> {
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        state.write_u8(self.is_some().into());
+        if let Some(value) = self {
+            value.fingerprint(state);
+        }
+    }
+}
+
+impl<T: Fingerprint> Fingerprint for [T] {
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        state.write_usize(self.len());
+        for value in self {
+            value.fingerprint(state);
+        }
+    }
+}
+
+impl<T: Fingerprint> Fingerprint for Vec<T> {
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        self.as_slice().fingerprint(state);
+    }
+}
+
+impl<A: Fingerprint, B: Fingerprint> Fingerprint for (A, B) {
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        self.0.fingerprint(state);
+        self.1.fingerprint(state);
+    }
+}
+
+pub fn fingerprint(value: &impl Fingerprint) -> u64 {
+    let mut state = Fingerprinter::default();
+    value.fingerprint(&mut state);
+    state.finish()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Checkpoint {
+    pub scene_hash: u64,
+    pub settings_hash: u64,
+    pub width: usize,
+    pub height: usize,
+    pub sums: Vec<Colour>,
+    pub weights: Vec<f32>,
+    pub statistics: Vec<PixelStatistics>,
+    pub aovs: Vec<(Vec<Colour>, Vec<f32>)>,
+}
+
+impl Checkpoint {
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        let temporary = path.with_extension("partial");
+        let mut writer = BufWriter::new(File::create(&temporary)?);
+        self.write(&mut writer)?;
+        writer.flush()?;
+        drop(writer);
+        fs::rename(temporary, path)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::read(BufReader::new(File::open(path)?))
+    }
+
+    pub fn write(&self, mut writer: impl Write) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        for value in [
+            self.scene_hash,
+            self.settings_hash,
+            self.width as u64,
+            self.height as u64,
+            self.aovs.len() as u64,
+        ] {
+            writer.write_all(&value.to_le_bytes())?;
+        }
+        for (sums, weights) in [(&self.sums, &self.weights)]
+            .into_iter()
+            .chain(self.aovs.iter().map(|(sums, weights)| (sums, weights)))
+        {
+            for (sum, weight) in sums.iter().zip(weights) {
+                for value in [sum.red, sum.green, sum.blue, *weight] {
+                    writer.write_all(&value.to_le_bytes())?;
+                }
+            }
+        }
+        for statistics in &self.statistics {
+            writer.write_all(&(statistics.count as u64).to_le_bytes())?;
+            writer.write_all(&statistics.mean.to_le_bytes())?;
+            writer.write_all(&statistics.m2.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    pub fn read(mut reader: impl Read) -> io::Result<Self> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        let mut cursor = Cursor {
+            bytes: &bytes,
+            position: 0,
+        };
+        if cursor.take::<4>()? != *MAGIC {
+            return Err(invalid_data("not a render checkpoint".to_string()));
+        }
+        let version = u32::from_le_bytes(cursor.take()?);
+        if version != VERSION {
+            return Err(invalid_data(format!(
+                "unsupported checkpoint version {version}"
+            )));
+        }
+        let scene_hash = cursor.u64()?;
+        let settings_hash = cursor.u64()?;
+        let width = cursor.u64()? as usize;
+        let height = cursor.u64()? as usize;
+        let layers = cursor.u64()? as usize;
+        let too_large = || invalid_data(format!("checkpoint size {width}x{height} is too large"));
+        let count = width.checked_mul(height).ok_or_else(too_large)?;
+        let expected = layers
+            .checked_add(2)
+            .and_then(|buffers| buffers.checked_mul(16))
+            .and_then(|stride| stride.checked_mul(count))
+            .ok_or_else(too_large)?;
+        if bytes.len() - cursor.position != expected {
+            return Err(invalid_data(format!(
+                "expected {expected} bytes of checkpoint data, found {}",
+                bytes.len() - cursor.position
+            )));
+        }
+        let mut buffers = (0..=layers)
+            .map(|_| {
+                let mut sums = Vec::with_capacity(count);
+                let mut weights = Vec::with_capacity(count);
+                for _ in 0..count {
+                    sums.push(Colour::new(cursor.f32()?, cursor.f32()?, cursor.f32()?));
+                    weights.push(cursor.f32()?);
+                }
+                Ok((sums, weights))
+            })
+            .collect::<io::Result<Vec<(Vec<Colour>, Vec<f32>)>>>()?;
+        let (sums, weights) = buffers.remove(0);
+        let statistics = (0..count)
+            .map(|_| {
+                Ok(PixelStatistics {
+                    count: cursor.u64()? as usize,
+                    mean: cursor.f32()?,
+                    m2: cursor.f32()?,
+                })
+            })
+            .collect::<io::Result<Vec<PixelStatistics>>>()?;
+        Ok(Self {
+            scene_hash,
+            settings_hash,
+            width,
+            height,
+            sums,
+            weights,
+            statistics,
+            aovs: buffers,
+        })
+    }
+}
+
+struct Cursor<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl Cursor<'_> {
+    fn take<const N: usize>(&mut self) -> io::Result<[u8; N]> {
+        let bytes = self
+            .bytes
+            .get(self.position..self.position + N)
+            .ok_or_else(|| invalid_data("truncated checkpoint".to_string()))?;
+        self.position += N;
+        Ok(bytes.try_into().unwrap())
+    }
+
+    fn u64(&mut self) -> io::Result<u64> {
+        Ok(u64::from_le_bytes(self.take()?))
+    }
+
+    fn f32(&mut self) -> io::Result<f32> {
+        Ok(f32::from_le_bytes(self.take()?))
+    }
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        image::Image,
+        material::Material,
+        scene::Scene,
+        sphere::Sphere,
+        texture::{ImageTexture, ProceduralTexture, Texture, WrapMode},
+        vector::Vec3D,
+    };
+
+    fn checkpoint() -> Checkpoint {
+        Checkpoint {
+            scene_hash: 7,
+            settings_hash: 9,
+            width: 2,
+            height: 1,
+            sums: vec![Colour::new(1., 2., 3.), Colour::gray(0.5)],
+            weights: vec![4., 0.25],
+            statistics: vec![
+                PixelStatistics {
+                    count: 3,
+                    mean: 0.5,
+                    m2: 0.1,
+                },
+                PixelStatistics::default(),
+            ],
+            aovs: vec![(vec![Colour::gray(2.), Colour::default()], vec![1., 0.])],
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut bytes = Vec::new();
+        checkpoint().write(&mut bytes).unwrap();
+        assert_eq!(Checkpoint::read(bytes.as_slice()).unwrap(), checkpoint());
+        let error = Checkpoint::read(&bytes[..bytes.len() - 1]).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+        assert!(Checkpoint::read(&b"nope"[..]).is_err());
+    }
+
+    #[test]
+    fn test_rejects_overflowing_sizes() {
+        let mut bytes = Vec::new();
+        checkpoint().write(&mut bytes).unwrap();
+        bytes[24..32].copy_from_slice(&u64::MAX.to_le_bytes());
+        let error = Checkpoint::read(bytes.as_slice()).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+        bytes[24..32].copy_from_slice(&1_u64.to_le_bytes());
+        bytes[40..48].copy_from_slice(&(u64::MAX / 8).to_le_bytes());
+        assert!(Checkpoint::read(bytes.as_slice()).is_err());
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let scene = |texture: Arc<dyn Texture>| Scene {
+            objects: vec![Box::new(Sphere::new(
+                Vec3D::ZERO,
+                1.,
+                Material {
+                    base_colour: texture,
+                    ..Default::default()
+                },
+            ))],
+            ..Default::default()
+        };
+        let solid = scene(Colour::gray(0.5).into());
+        assert_eq!(
+            fingerprint(&solid),
+            fingerprint(&scene(Colour::gray(0.5).into()))
+        );
+        assert_ne!(
+            fingerprint(&solid),
+            fingerprint(&scene(Colour::gray(0.6).into()))
+        );
+        let first = scene(Arc::new(ProceduralTexture(|(u, _), _| Colour::gray(u))));
+        let second = scene(Arc::new(ProceduralTexture(|(_, v), _| Colour::gray(v))));
+        assert_ne!(fingerprint(&first), fingerprint(&second));
+        let mut image = Image::new(4, 4, Colour::gray(0.5));
+        let map = scene(Arc::new(
+            ImageTexture::new(image.clone(), WrapMode::Repeat).unwrap(),
+        ));
+        image.pixels[5].green = 0.25;
+        let edited = scene(Arc::new(
+            ImageTexture::new(image, WrapMode::Repeat).unwrap(),
+        ));
+        assert_ne!(fingerprint(&map), fingerprint(&edited));
+    }
+}
@@ -1,4 +1,4 @@
-use std::ops::{Add, Mul};
+use std::ops::{Add, Mul, Sub};
 
 #[derive(Debug, Clone, Copy, Default, PartialEq)]
 pub struct Colour {
@@ -33,6 +33,10 @@ impl Colour {
     pub fn gray(value: f32) -> Self {
         Self::new(value, value, value)
     }
+
+    pub fn luminance(self) -> f32 {
+        0.2126 * self.red + 0.7152 * self.green + 0.0722 * self.blue
+    }
 }
 
 impl Add for Colour {
@@ -46,6 +50,17 @@ impl Add for Colour {
     }
 }
 
+impl Sub for Colour {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self::new(
+            self.red - rhs.red,
+            self.green - rhs.green,
+            self.blue - rhs.blue,
+        )
+    }
+}
+
 impl Mul for Colour {
     type Output = Self;
     fn mul(self, rhs: Self) -> Self::Output {
@@ -95,4 +110,11 @@ mod tests {
         let colour = Colour::new(1., 1., 1.);
         assert_eq!(colour.as_rgb(), [1., 1., 1.]);
     }
+
+    #[test]
+    fn test_luminance() {
+        assert!((Colour::gray(1.).luminance() - 1.).abs() < 1e-6);
+        assert_eq!(Colour::default().luminance(), 0.);
+        assert!(Colour::new(0., 1., 0.).luminance() > Colour::new(1., 0., 0.).luminance());
+    }
 }
@@ -0,0 +1,229 @@
+use crate::{
+    checkpoint::{Fingerprint, Fingerprinter},
+    material::Material,
+    math::solve_quadratic,
+    physics::{Intersection, Object},
+    ray::Ray,
+    sphere::{spherical_tangent, spherical_uv},
+    vector::Vec3D,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Surface {
+    Side,
+    Base,
+}
+
+#[derive(Debug, Clone)]
+pub struct Cone {
+    pub centre: Vec3D,
+    pub radius: f32,
+    pub height: f32,
+    pub material: Material,
+}
+
+impl Cone {
+    pub fn new(centre: Vec3D, radius: f32, height: f32, material: Material) -> Self {
+        Self {
+            centre,
+            radius,
+            height,
+            material,
+        }
+    }
+
+    pub fn unit(material: Material) -> Self {
+        Self {
+            material,
+            ..Default::default()
+        }
+    }
+}
+
+impl Object for Cone {
+    fn intersections(&self, ray: &Ray) -> Vec<Intersection> {
+        let origin = ray.origin - self.centre;
+        let direction = ray.direction;
+        let half_height = self.height / 2.;
+        let slope = self.radius / self.height;
+        let slope_squared = slope * slope;
+        let below_apex = half_height - origin.y;
+        let mut distances = solve_quadratic(
+            direction.x * direction.x + direction.z * direction.z
+                - slope_squared * direction.y * direction.y,
+            2. * (origin.x * direction.x
+                + origin.z * direction.z
+                + slope_squared * below_apex * direction.y),
+            origin.x * origin.x + origin.z * origin.z - slope_squared * below_apex * below_apex,
+        )
+        .into_iter()
+        .filter(|distance| (origin.y + direction.y * distance).abs() <= half_height)
+        .map(|distance| (distance, Surface::Side))
+        .collect::<Vec<(f32, Surface)>>();
+        if direction.y != 0. {
+            let distance = (-half_height - origin.y) / direction.y;
+            let position = origin + direction * distance;
+            if position.x * position.x + position.z * position.z <= self.radius * self.radius {
+                distances.push((distance, Surface::Base));
+            }
+        }
+        distances.retain(|(distance, _)| *distance > 0.);
+        distances.sort_unstable_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+        let mut intersections = distances
+            .into_iter()
+            .map(|(distance, surface)| (ray.origin + ray.direction * distance, surface))
+            .collect::<Vec<(Vec3D, Surface)>>();
+        intersections.dedup_by(|a, b| a.0 == b.0);
+        intersections
+            .iter()
+            .map(|(intersection, surface)| {
+                let local_position = *intersection - self.centre;
+                let (normal, uv) = match surface {
+                    Surface::Base => (
+                        -Vec3D::Y,
+                        (
+                            local_position.x / (2. * self.radius) + 0.5,
+                            local_position.z / (2. * self.radius) + 0.5,
+                        ),
+                    ),
+                    Surface::Side => {
+                        let gradient = Vec3D::new(
+                            local_position.x,
+                            slope_squared * (half_height - local_position.y),
+                            local_position.z,
+                        );
+                        let normal = if gradient.length() > 0. {
+                            gradient.normalise()
+                        } else {
+                            Vec3D::Y
+                        };
+                        (
+                            normal,
+                            (spherical_uv(normal).0, local_position.y / self.height + 0.5),
+                        )
+                    }
+                };
+                Intersection {
+                    distance: (*intersection - ray.origin).length(),
+                    position: *intersection,
+                    local_position,
+                    normal,
+                    tangent: spherical_tangent(normal),
+                    uv,
+                    object: Box::new(self.clone()),
+                    ray: *ray,
+                }
+            })
+            .collect::<Vec<Intersection>>()
+    }
+
+    fn extent(&self) -> f32 {
+        (2. * self.radius).hypot(self.height)
+    }
+
+    fn centre(&self) -> Vec3D {
+        self.centre
+    }
+
+    fn material(&self) -> Material {
+        self.material.clone()
+    }
+}
+
+impl Default for Cone {
+    fn default() -> Self {
+        Self {
+            centre: Vec3D::default(),
+            radius: 1.,
+            height: 2.,
+            material: Material::default(),
+        }
+    }
+}
+
+impl Fingerprint for Cone {
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        state.debug(&(self.centre, self.radius, self.height));
+        self.material.fingerprint(state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cone_intersections() {
+        let cone = Cone::default();
+        let ray = Ray::new(Vec3D::new(0., 0., 5.), Vec3D::new(0., 0., -1.));
+        let intersections = cone.intersections(&ray);
+        assert_eq!(intersections.len(), 2);
+        assert_eq!(intersections[0].position, Vec3D::new(0., 0., 0.5));
+        assert_eq!(intersections[1].position, Vec3D::new(0., 0., -0.5));
+        assert_eq!(intersections[0].normal, Vec3D::new(0., 0.5, 1.).normalise());
+        assert_eq!(
+            intersections[1].normal,
+            Vec3D::new(0., 0.5, -1.).normalise()
+        );
+        assert_eq!(intersections[0].distance, 4.5);
+        assert_eq!(intersections[1].distance, 5.5);
+        assert_eq!(intersections[0].uv, (0.5, 0.5));
+    }
+
+    #[test]
+    fn test_cone_intersections_base() {
+        let cone = Cone::default();
+        let ray = Ray::new(Vec3D::new(0.25, 5., 0.), Vec3D::new(0., -1., 0.));
+        let intersections = cone.intersections(&ray);
+        assert_eq!(intersections.len(), 2);
+        assert_eq!(intersections[0].position, Vec3D::new(0.25, 0.5, 0.));
+        assert_eq!(intersections[1].position, Vec3D::new(0.25, -1., 0.));
+        assert_eq!(intersections[1].normal, -Vec3D::Y);
+        assert_eq!(intersections[1].uv, (0.625, 0.5));
+    }
+
+    #[test]
+    fn test_cone_intersections_tangent() {
+        let cone = Cone::default();
+        let ray = Ray::new(Vec3D::new(0.5, 0., 5.), Vec3D::new(0., 0., -1.));
+        let intersections = cone.intersections(&ray);
+        assert_eq!(intersections.len(), 1);
+        assert_eq!(intersections[0].position, Vec3D::new(0.5, 0., 0.));
+        assert_eq!(intersections[0].distance, 5.);
+        let ray = Ray::new(Vec3D::new(0., 5., 0.), Vec3D::new(0., -1., 0.));
+        let intersections = cone.intersections(&ray);
+        assert_eq!(intersections[0].position, Vec3D::new(0., 1., 0.));
+        assert_eq!(intersections[0].normal, Vec3D::Y);
+    }
+
+    #[test]
+    fn test_cone_intersections_miss() {
+        let cone = Cone::default();
+        let ray = Ray::new(Vec3D::new(0.75, 0., 5.), Vec3D::new(0., 0., -1.));
+        assert!(cone.intersections(&ray).is_empty());
+        let ray = Ray::new(Vec3D::new(0., 1.5, 5.), Vec3D::new(0., 0., -1.));
+        assert!(cone.intersections(&ray).is_empty());
+    }
+
+    #[test]
+    fn test_cone_intersections_inside() {
+        let cone = Cone::default();
+        let ray = Ray::new(Vec3D::new(0., 0., 0.), Vec3D::new(0., -1., 0.));
+        let intersections = cone.intersections(&ray);
+        assert_eq!(intersections.len(), 1);
+        assert_eq!(intersections[0].position, Vec3D::new(0., -1., 0.));
+        assert_eq!(intersections[0].normal, -Vec3D::Y);
+        assert_eq!(intersections[0].distance, 1.);
+    }
+
+    #[test]
+    fn test_cone_intersections_grazing() {
+        let cone = Cone::default();
+        let ray = Ray::new(Vec3D::new(0., 1., 0.), Vec3D::new(0.5, -1., 0.));
+        let intersections = cone.intersections(&ray);
+        assert_eq!(intersections.len(), 1);
+        assert_eq!(intersections[0].position, Vec3D::new(1., -1., 0.));
+        let ray = Ray::new(Vec3D::new(0., 1.1, 0.), Vec3D::new(0.5, -1., 0.));
+        assert!(cone.intersections(&ray).is_empty());
+    }
+}
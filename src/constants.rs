@@ -1 +1,3 @@
 pub const EPSILON: f32 = 0.00001;
+pub const RAY_OFFSET: f32 = 0.001;
+pub const ENVIRONMENT_DISTANCE: f32 = 1e6;
@@ -0,0 +1,327 @@
+use std::sync::Arc;
+
+use crate::{
+    checkpoint::{Fingerprint, Fingerprinter},
+    material::Material,
+    physics::{Intersection, Object},
+    ray::Ray,
+    vector::Vec3D,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    Union,
+    Intersection,
+    Difference,
+}
+
+impl Operation {
+    fn contains(self, inside_left: bool, inside_right: bool) -> bool {
+        match self {
+            Self::Union => inside_left || inside_right,
+            Self::Intersection => inside_left && inside_right,
+            Self::Difference => inside_left && !inside_right,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Csg {
+    pub operation: Operation,
+    pub left: Arc<dyn Object>,
+    pub right: Arc<dyn Object>,
+}
+
+impl Csg {
+    pub fn new(operation: Operation, left: Arc<dyn Object>, right: Arc<dyn Object>) -> Self {
+        Self {
+            operation,
+            left,
+            right,
+        }
+    }
+
+    pub fn union(left: Arc<dyn Object>, right: Arc<dyn Object>) -> Self {
+        Self::new(Operation::Union, left, right)
+    }
+
+    pub fn intersection(left: Arc<dyn Object>, right: Arc<dyn Object>) -> Self {
+        Self::new(Operation::Intersection, left, right)
+    }
+
+    pub fn difference(left: Arc<dyn Object>, right: Arc<dyn Object>) -> Self {
+        Self::new(Operation::Difference, left, right)
+    }
+}
+
+fn sorted_intersections(object: &dyn Object, ray: &Ray) -> Vec<Intersection> {
+    let mut intersections = object.intersections(ray);
+    intersections.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap());
+    // A tangent hit enters and leaves at the same point, either as one grazing hit or as a pair at
+    // equal distances. The span between them is empty, so it must not change the inside state.
+    let mut spans: Vec<Intersection> = Vec::with_capacity(intersections.len());
+    for intersection in intersections {
+        if intersection.normal.dot(intersection.ray.direction) == 0. {
+            continue;
+        }
+        if spans
+            .last()
+            .is_some_and(|last| last.distance == intersection.distance)
+        {
+            spans.pop();
+            continue;
+        }
+        spans.push(intersection);
+    }
+    spans
+}
+
+fn exits(intersection: &Intersection) -> bool {
+    intersection.normal.dot(intersection.ray.direction) > 0.
+}
+
+impl Object for Csg {
+    fn intersections(&self, ray: &Ray) -> Vec<Intersection> {
+        let left = sorted_intersections(self.left.as_ref(), ray);
+        let right = sorted_intersections(self.right.as_ref(), ray);
+        let mut inside_left = left.first().is_some_and(exits);
+        let mut inside_right = right.first().is_some_and(exits);
+        let mut inside = self.operation.contains(inside_left, inside_right);
+        let mut left = left.into_iter().peekable();
+        let mut right = right.into_iter().peekable();
+        let mut intersections = Vec::new();
+        loop {
+            let from_left = match (left.peek(), right.peek()) {
+                (Some(a), Some(b)) => a.distance <= b.distance,
+                (Some(_), None) => true,
+                (None, Some(_)) => false,
+                (None, None) => break,
+            };
+            let mut intersection = if from_left {
+                let intersection = left.next().unwrap();
+                inside_left = !exits(&intersection);
+                intersection
+            } else {
+                let intersection = right.next().unwrap();
+                inside_right = !exits(&intersection);
+                intersection
+            };
+            let now_inside = self.operation.contains(inside_left, inside_right);
+            if now_inside != inside {
+                // Turn the whole tangent frame over, so that a normal map tilting the subtracted
+                // surface along its tangent tilts the carved surface the opposite way.
+                if !from_left && self.operation == Operation::Difference {
+                    intersection.normal = -intersection.normal;
+                    intersection.tangent = -intersection.tangent;
+                }
+                intersections.push(intersection);
+                inside = now_inside;
+            }
+        }
+        intersections
+    }
+
+    fn extent(&self) -> f32 {
+        match self.operation {
+            Operation::Union => {
+                let (_, radius) = enclosing_sphere(
+                    (self.left.centre(), self.left.extent() / 2.),
+                    (self.right.centre(), self.right.extent() / 2.),
+                );
+                radius * 2.
+            }
+            Operation::Intersection => self.left.extent().min(self.right.extent()),
+            Operation::Difference => self.left.extent(),
+        }
+    }
+
+    fn centre(&self) -> Vec3D {
+        match self.operation {
+            Operation::Union => {
+                enclosing_sphere(
+                    (self.left.centre(), self.left.extent() / 2.),
+                    (self.right.centre(), self.right.extent() / 2.),
+                )
+                .0
+            }
+            Operation::Intersection if self.right.extent() < self.left.extent() => {
+                self.right.centre()
+            }
+            Operation::Intersection | Operation::Difference => self.left.centre(),
+        }
+    }
+
+    fn material(&self) -> Material {
+        self.left.material()
+    }
+}
+
+fn enclosing_sphere(first: (Vec3D, f32), second: (Vec3D, f32)) -> (Vec3D, f32) {
+    let ((first_centre, first_radius), (second_centre, second_radius)) = (first, second);
+    let distance = (second_centre - first_centre).length();
+    if distance + second_radius <= first_radius {
+        return first;
+    }
+    if distance + first_radius <= second_radius {
+        return second;
+    }
+    let radius = (distance + first_radius + second_radius) / 2.;
+    (
+        first_centre + (second_centre - first_centre) * ((radius - first_radius) / distance),
+        radius,
+    )
+}
+
+impl Fingerprint for Csg {
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        state.debug(&self.operation);
+        self.left.fingerprint(state);
+        self.right.fingerprint(state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{colour::Colour, cuboid::Cuboid, cylinder::Cylinder, sphere::Sphere};
+
+    fn spheres() -> (Arc<dyn Object>, Arc<dyn Object>) {
+        (
+            Arc::new(Sphere::new(
+                Vec3D::new(-0.5, 0., 0.),
+                1.,
+                Material::default(),
+            )),
+            Arc::new(Sphere::new(
+                Vec3D::new(0.5, 0., 0.),
+                1.,
+                Material::default(),
+            )),
+        )
+    }
+
+    fn ray() -> Ray {
+        Ray::new(Vec3D::new(-5., 0., 0.), Vec3D::X)
+    }
+
+    fn positions(intersections: &[Intersection]) -> Vec<f32> {
+        intersections
+            .iter()
+            .map(|intersection| intersection.position.x)
+            .collect()
+    }
+
+    #[test]
+    fn test_union() {
+        let (left, right) = spheres();
+        let intersections = Csg::union(left, right).intersections(&ray());
+        assert_eq!(positions(&intersections), vec![-1.5, 1.5]);
+        assert_eq!(intersections[0].normal, -Vec3D::X);
+        assert_eq!(intersections[1].normal, Vec3D::X);
+    }
+
+    #[test]
+    fn test_intersection() {
+        let (left, right) = spheres();
+        let intersections = Csg::intersection(left, right).intersections(&ray());
+        assert_eq!(positions(&intersections), vec![-0.5, 0.5]);
+        assert_eq!(intersections[0].normal, -Vec3D::X);
+        assert_eq!(intersections[1].normal, Vec3D::X);
+    }
+
+    #[test]
+    fn test_difference() {
+        let (left, right) = spheres();
+        let intersections = Csg::difference(left, right).intersections(&ray());
+        assert_eq!(positions(&intersections), vec![-1.5, -0.5]);
+        assert_eq!(intersections[0].normal, -Vec3D::X);
+        assert_eq!(intersections[1].normal, Vec3D::X);
+    }
+
+    #[test]
+    fn test_normal_mapped_difference() {
+        let (left, _) = spheres();
+        let right: Arc<dyn Object> = Arc::new(Sphere::new(
+            Vec3D::new(0.5, 0., 0.),
+            1.,
+            Material {
+                normal_map: Some(Colour::new(1., 0.5, 1.).into()),
+                ..Default::default()
+            },
+        ));
+        let carved = &Csg::difference(left, right.clone()).intersections(&ray())[1];
+        let original = &right.intersections(&ray())[0];
+        assert_eq!(carved.tangent, -original.tangent);
+        let material = carved.object.material();
+        let shading = material.shading_normal(carved);
+        assert!(shading.dot(carved.normal) > 0.);
+        assert!((shading + material.shading_normal(original)).length() < 1e-6);
+    }
+
+    #[test]
+    fn test_ray_starting_inside() {
+        let (left, right) = spheres();
+        let ray = Ray::new(Vec3D::new(-1., 0., 0.), Vec3D::X);
+        let intersections = Csg::difference(left.clone(), right.clone()).intersections(&ray);
+        assert_eq!(positions(&intersections), vec![-0.5]);
+        assert_eq!(intersections[0].normal, Vec3D::X);
+        let ray = Ray::new(Vec3D::ZERO, Vec3D::X);
+        let intersections = Csg::union(left, right).intersections(&ray);
+        assert_eq!(positions(&intersections), vec![1.5]);
+    }
+
+    #[test]
+    fn test_tangent_hits() {
+        let (left, right) = spheres();
+        let ray = Ray::new(Vec3D::new(-5., 1., 0.), Vec3D::X);
+        assert!(Csg::union(left.clone(), right.clone())
+            .intersections(&ray)
+            .is_empty());
+        let core: Arc<dyn Object> = Arc::new(Sphere::new(Vec3D::ZERO, 0.5, Material::default()));
+        let ray = Ray::new(Vec3D::new(-5., 0.5, 0.), Vec3D::X);
+        let intersections =
+            Csg::difference(Arc::new(Csg::union(left, right)), core).intersections(&ray);
+        assert_eq!(intersections.len(), 2);
+        assert!(intersections[0].position.x < -1. && intersections[1].position.x > 1.);
+    }
+
+    #[test]
+    fn test_nested() {
+        let (left, right) = spheres();
+        let lens: Arc<dyn Object> = Arc::new(Csg::intersection(left, right));
+        let core: Arc<dyn Object> = Arc::new(Sphere::new(Vec3D::ZERO, 0.25, Material::default()));
+        let hollow = Csg::difference(lens, core);
+        let intersections = hollow.intersections(&ray());
+        assert_eq!(positions(&intersections), vec![-0.5, -0.25, 0.25, 0.5]);
+        assert_eq!(intersections[1].normal, Vec3D::X);
+        assert_eq!(intersections[2].normal, -Vec3D::X);
+    }
+
+    #[test]
+    fn test_drilled_cuboid() {
+        let drilled = Csg::difference(
+            Arc::new(Cuboid::default()),
+            Arc::new(Cylinder::new(Vec3D::ZERO, 0.5, 4., Material::default())),
+        );
+        let ray = Ray::new(Vec3D::new(0., 5., 0.), -Vec3D::Y);
+        assert!(drilled.intersections(&ray).is_empty());
+        let ray = Ray::new(Vec3D::new(0.75, 5., 0.), -Vec3D::Y);
+        assert_eq!(drilled.intersections(&ray).len(), 2);
+        let ray = Ray::new(Vec3D::new(-5., 0., 0.), Vec3D::X);
+        let intersections = drilled.intersections(&ray);
+        assert_eq!(positions(&intersections), vec![-1., -0.5, 0.5, 1.]);
+        assert_eq!(intersections[1].normal, Vec3D::X);
+        assert_eq!(intersections[2].normal, -Vec3D::X);
+    }
+
+    #[test]
+    fn test_bounds() {
+        let (left, right) = spheres();
+        let union = Csg::union(left.clone(), right.clone());
+        assert_eq!(union.centre(), Vec3D::ZERO);
+        assert_eq!(union.extent(), 3.);
+        let difference = Csg::difference(left, right);
+        assert_eq!(difference.centre(), Vec3D::new(-0.5, 0., 0.));
+        assert_eq!(difference.extent(), 2.);
+    }
+}
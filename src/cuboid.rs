@@ -0,0 +1,246 @@
+use crate::{
+    checkpoint::{Fingerprint, Fingerprinter},
+    material::Material,
+    physics::{Intersection, Object},
+    ray::Ray,
+    vector::Vec3D,
+};
+
+#[derive(Debug, Clone)]
+pub struct Cuboid {
+    pub minimum: Vec3D,
+    pub maximum: Vec3D,
+    pub material: Material,
+}
+
+impl Cuboid {
+    pub fn new(minimum: Vec3D, maximum: Vec3D, material: Material) -> Self {
+        Self {
+            minimum,
+            maximum,
+            material,
+        }
+    }
+
+    pub fn unit(material: Material) -> Self {
+        Self {
+            material,
+            ..Default::default()
+        }
+    }
+
+    pub fn size(&self) -> Vec3D {
+        self.maximum - self.minimum
+    }
+
+    fn face(&self, local_position: Vec3D) -> (Vec3D, Vec3D) {
+        let half_size = self.size() * 0.5;
+        let relative = [
+            local_position.x / half_size.x,
+            local_position.y / half_size.y,
+            local_position.z / half_size.z,
+        ];
+        let axis = (0..3)
+            .max_by(|a, b| relative[*a].abs().partial_cmp(&relative[*b].abs()).unwrap())
+            .unwrap();
+        let sign = 1_f32.copysign(relative[axis]);
+        match axis {
+            0 => (Vec3D::X * sign, -Vec3D::Z * sign),
+            1 => (Vec3D::Y * sign, Vec3D::X),
+            _ => (Vec3D::Z * sign, Vec3D::X * sign),
+        }
+    }
+}
+
+impl Object for Cuboid {
+    fn intersections(&self, ray: &Ray) -> Vec<Intersection> {
+        let mut near = f32::NEG_INFINITY;
+        let mut far = f32::INFINITY;
+        for (origin, direction, minimum, maximum) in [
+            (
+                ray.origin.x,
+                ray.direction.x,
+                self.minimum.x,
+                self.maximum.x,
+            ),
+            (
+                ray.origin.y,
+                ray.direction.y,
+                self.minimum.y,
+                self.maximum.y,
+            ),
+            (
+                ray.origin.z,
+                ray.direction.z,
+                self.minimum.z,
+                self.maximum.z,
+            ),
+        ] {
+            if direction == 0. {
+                if origin < minimum || origin > maximum {
+                    return Vec::new();
+                }
+                continue;
+            }
+            let first = (minimum - origin) / direction;
+            let second = (maximum - origin) / direction;
+            near = near.max(first.min(second));
+            far = far.min(first.max(second));
+        }
+        if near > far {
+            return Vec::new();
+        }
+        let mut intersections = [near, far]
+            .into_iter()
+            .filter(|distance| *distance > 0.)
+            .map(|distance| ray.origin + ray.direction * distance)
+            .collect::<Vec<Vec3D>>();
+        intersections.dedup();
+        intersections
+            .iter()
+            .map(|intersection| {
+                let local_position = *intersection - self.centre();
+                let (normal, tangent) = self.face(local_position);
+                let bitangent = normal.cross(tangent);
+                let size = self.size();
+                let span = |direction: Vec3D| {
+                    (direction.x * size.x).abs()
+                        + (direction.y * size.y).abs()
+                        + (direction.z * size.z).abs()
+                };
+                Intersection {
+                    distance: (*intersection - ray.origin).length(),
+                    position: *intersection,
+                    local_position,
+                    normal,
+                    tangent,
+                    uv: (
+                        local_position.dot(tangent) / span(tangent) + 0.5,
+                        local_position.dot(bitangent) / span(bitangent) + 0.5,
+                    ),
+                    object: Box::new(self.clone()),
+                    ray: *ray,
+                }
+            })
+            .collect::<Vec<Intersection>>()
+    }
+
+    fn extent(&self) -> f32 {
+        self.size().length()
+    }
+
+    fn centre(&self) -> Vec3D {
+        (self.minimum + self.maximum) * 0.5
+    }
+
+    fn material(&self) -> Material {
+        self.material.clone()
+    }
+}
+
+impl Default for Cuboid {
+    fn default() -> Self {
+        Self {
+            minimum: Vec3D::triple(-1.),
+            maximum: Vec3D::triple(1.),
+            material: Material::default(),
+        }
+    }
+}
+
+impl Fingerprint for Cuboid {
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        state.debug(&(self.minimum, self.maximum));
+        self.material.fingerprint(state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cuboid_intersections() {
+        let cuboid = Cuboid::default();
+        let ray = Ray::new(Vec3D::new(0.5, 0.5, 5.), Vec3D::new(0., 0., -1.));
+        let intersections = cuboid.intersections(&ray);
+        assert_eq!(intersections.len(), 2);
+        assert_eq!(intersections[0].position, Vec3D::new(0.5, 0.5, 1.));
+        assert_eq!(intersections[1].position, Vec3D::new(0.5, 0.5, -1.));
+        assert_eq!(intersections[0].normal, Vec3D::new(0., 0., 1.));
+        assert_eq!(intersections[1].normal, Vec3D::new(0., 0., -1.));
+        assert_eq!(intersections[0].distance, 4.);
+        assert_eq!(intersections[1].distance, 6.);
+        assert_eq!(intersections[0].uv, (0.75, 0.75));
+        assert_eq!(intersections[1].uv, (0.25, 0.75));
+    }
+
+    #[test]
+    fn test_cuboid_intersections_tangent() {
+        let cuboid = Cuboid::default();
+        let ray = Ray::new(Vec3D::new(3., 0., -1.), Vec3D::new(-1., 0., 1.));
+        let intersections = cuboid.intersections(&ray);
+        assert_eq!(intersections.len(), 1);
+        assert_eq!(intersections[0].position, Vec3D::new(1., 0., 1.));
+        assert_eq!(intersections[0].distance, 2_f32.sqrt() * 2.);
+    }
+
+    #[test]
+    fn test_cuboid_intersections_miss() {
+        let cuboid = Cuboid::default();
+        let ray = Ray::new(Vec3D::new(0., 0., 2.), Vec3D::new(0., 1., 0.));
+        assert!(cuboid.intersections(&ray).is_empty());
+        let ray = Ray::new(Vec3D::new(0., 0., 5.), Vec3D::new(0., 0., 1.));
+        assert!(cuboid.intersections(&ray).is_empty());
+    }
+
+    #[test]
+    fn test_cuboid_intersections_inside() {
+        let cuboid = Cuboid::default();
+        let ray = Ray::new(Vec3D::new(0., 0., 0.), Vec3D::new(0., -1., 0.));
+        let intersections = cuboid.intersections(&ray);
+        assert_eq!(intersections.len(), 1);
+        assert_eq!(intersections[0].position, Vec3D::new(0., -1., 0.));
+        assert_eq!(intersections[0].normal, Vec3D::new(0., -1., 0.));
+        assert_eq!(intersections[0].distance, 1.);
+    }
+
+    #[test]
+    fn test_cuboid_intersections_grazing() {
+        let cuboid = Cuboid::default();
+        let ray = Ray::new(Vec3D::new(-3., 1., 0.), Vec3D::new(1., 0., 0.));
+        let intersections = cuboid.intersections(&ray);
+        assert_eq!(intersections.len(), 2);
+        assert_eq!(intersections[0].position, Vec3D::new(-1., 1., 0.));
+        assert_eq!(intersections[1].position, Vec3D::new(1., 1., 0.));
+        let ray = Ray::new(Vec3D::new(-3., 1.001, 0.), Vec3D::new(1., 0., 0.));
+        assert!(cuboid.intersections(&ray).is_empty());
+    }
+
+    #[test]
+    fn test_cuboid_tangent_frames() {
+        let cuboid = Cuboid::new(
+            Vec3D::new(-1., 0., -3.),
+            Vec3D::new(3., 2., 1.),
+            Material::default(),
+        );
+        for direction in [
+            Vec3D::X,
+            -Vec3D::X,
+            Vec3D::Y,
+            -Vec3D::Y,
+            Vec3D::Z,
+            -Vec3D::Z,
+        ] {
+            let ray = Ray::new(cuboid.centre() - direction * 10., direction);
+            let intersections = cuboid.intersections(&ray);
+            assert_eq!(intersections.len(), 2);
+            assert_eq!(intersections[0].normal, -direction);
+            assert_eq!(intersections[1].normal, direction);
+            for intersection in intersections {
+                assert_eq!(intersection.tangent.dot(intersection.normal), 0.);
+                assert_eq!(intersection.uv, (0.5, 0.5));
+            }
+        }
+    }
+}
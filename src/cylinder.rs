@@ -0,0 +1,236 @@
+use crate::{
+    checkpoint::{Fingerprint, Fingerprinter},
+    material::Material,
+    math::solve_quadratic,
+    physics::{Intersection, Object},
+    ray::Ray,
+    sphere::{spherical_tangent, spherical_uv},
+    vector::Vec3D,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Surface {
+    Side,
+    Cap,
+}
+
+#[derive(Debug, Clone)]
+pub struct Cylinder {
+    pub centre: Vec3D,
+    pub radius: f32,
+    pub height: f32,
+    pub material: Material,
+}
+
+impl Cylinder {
+    pub fn new(centre: Vec3D, radius: f32, height: f32, material: Material) -> Self {
+        Self {
+            centre,
+            radius,
+            height,
+            material,
+        }
+    }
+
+    pub fn unit(material: Material) -> Self {
+        Self {
+            material,
+            ..Default::default()
+        }
+    }
+}
+
+impl Object for Cylinder {
+    fn intersections(&self, ray: &Ray) -> Vec<Intersection> {
+        let origin = ray.origin - self.centre;
+        let direction = ray.direction;
+        let half_height = self.height / 2.;
+        let mut distances = solve_quadratic(
+            direction.x * direction.x + direction.z * direction.z,
+            2. * (origin.x * direction.x + origin.z * direction.z),
+            origin.x * origin.x + origin.z * origin.z - self.radius * self.radius,
+        )
+        .into_iter()
+        .filter(|distance| (origin.y + direction.y * distance).abs() <= half_height)
+        .map(|distance| (distance, Surface::Side))
+        .collect::<Vec<(f32, Surface)>>();
+        if direction.y != 0. {
+            distances.extend(
+                [-half_height, half_height]
+                    .map(|cap| (cap - origin.y) / direction.y)
+                    .into_iter()
+                    .filter(|distance| {
+                        let position = origin + direction * *distance;
+                        position.x * position.x + position.z * position.z
+                            <= self.radius * self.radius
+                    })
+                    .map(|distance| (distance, Surface::Cap)),
+            );
+        }
+        distances.retain(|(distance, _)| *distance > 0.);
+        distances.sort_unstable_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+        let mut intersections = distances
+            .into_iter()
+            .map(|(distance, surface)| (ray.origin + ray.direction * distance, surface))
+            .collect::<Vec<(Vec3D, Surface)>>();
+        intersections.dedup_by(|a, b| a.0 == b.0);
+        intersections
+            .iter()
+            .map(|(intersection, surface)| {
+                let local_position = *intersection - self.centre;
+                let (normal, tangent, uv) = match surface {
+                    Surface::Cap => {
+                        let normal = Vec3D::Y * 1_f32.copysign(local_position.y);
+                        let bitangent = normal.cross(Vec3D::X);
+                        (
+                            normal,
+                            Vec3D::X,
+                            (
+                                local_position.x / (2. * self.radius) + 0.5,
+                                local_position.dot(bitangent) / (2. * self.radius) + 0.5,
+                            ),
+                        )
+                    }
+                    Surface::Side => {
+                        let normal = Vec3D::new(local_position.x, 0., local_position.z).normalise();
+                        (
+                            normal,
+                            spherical_tangent(normal),
+                            (spherical_uv(normal).0, local_position.y / self.height + 0.5),
+                        )
+                    }
+                };
+                Intersection {
+                    distance: (*intersection - ray.origin).length(),
+                    position: *intersection,
+                    local_position,
+                    normal,
+                    tangent,
+                    uv,
+                    object: Box::new(self.clone()),
+                    ray: *ray,
+                }
+            })
+            .collect::<Vec<Intersection>>()
+    }
+
+    fn extent(&self) -> f32 {
+        (2. * self.radius).hypot(self.height)
+    }
+
+    fn centre(&self) -> Vec3D {
+        self.centre
+    }
+
+    fn material(&self) -> Material {
+        self.material.clone()
+    }
+}
+
+impl Default for Cylinder {
+    fn default() -> Self {
+        Self {
+            centre: Vec3D::default(),
+            radius: 1.,
+            height: 2.,
+            material: Material::default(),
+        }
+    }
+}
+
+impl Fingerprint for Cylinder {
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        state.debug(&(self.centre, self.radius, self.height));
+        self.material.fingerprint(state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cylinder_intersections() {
+        let cylinder = Cylinder::default();
+        let ray = Ray::new(Vec3D::new(0., 0.5, 5.), Vec3D::new(0., 0., -1.));
+        let intersections = cylinder.intersections(&ray);
+        assert_eq!(intersections.len(), 2);
+        assert_eq!(intersections[0].position, Vec3D::new(0., 0.5, 1.));
+        assert_eq!(intersections[1].position, Vec3D::new(0., 0.5, -1.));
+        assert_eq!(intersections[0].normal, Vec3D::new(0., 0., 1.));
+        assert_eq!(intersections[1].normal, Vec3D::new(0., 0., -1.));
+        assert_eq!(intersections[0].distance, 4.);
+        assert_eq!(intersections[1].distance, 6.);
+        assert_eq!(intersections[0].uv, (0.5, 0.75));
+    }
+
+    #[test]
+    fn test_cylinder_intersections_caps() {
+        let cylinder = Cylinder::default();
+        let ray = Ray::new(Vec3D::new(0.5, 5., 0.), Vec3D::new(0., -1., 0.));
+        let intersections = cylinder.intersections(&ray);
+        assert_eq!(intersections.len(), 2);
+        assert_eq!(intersections[0].position, Vec3D::new(0.5, 1., 0.));
+        assert_eq!(intersections[1].position, Vec3D::new(0.5, -1., 0.));
+        assert_eq!(intersections[0].normal, Vec3D::Y);
+        assert_eq!(intersections[1].normal, -Vec3D::Y);
+        assert_eq!(intersections[0].uv, (0.75, 0.5));
+        let ray = Ray::new(Vec3D::new(0., 2.5, 3.), Vec3D::new(0., -1., -1.));
+        let intersections = cylinder.intersections(&ray);
+        assert_eq!(intersections.len(), 2);
+        assert_eq!(intersections[0].position, Vec3D::new(0., 0.5, 1.));
+        assert_eq!(intersections[0].normal, Vec3D::Z);
+        assert_eq!(intersections[1].position, Vec3D::new(0., -1., -0.5));
+        assert_eq!(intersections[1].normal, -Vec3D::Y);
+    }
+
+    #[test]
+    fn test_cylinder_intersections_tangent() {
+        let cylinder = Cylinder::default();
+        let ray = Ray::new(Vec3D::new(1., 0., 5.), Vec3D::new(0., 0., -1.));
+        let intersections = cylinder.intersections(&ray);
+        assert_eq!(intersections.len(), 1);
+        assert_eq!(intersections[0].position, Vec3D::new(1., 0., 0.));
+        assert_eq!(intersections[0].normal, Vec3D::X);
+        assert_eq!(intersections[0].distance, 5.);
+    }
+
+    #[test]
+    fn test_cylinder_intersections_miss() {
+        let cylinder = Cylinder::default();
+        let ray = Ray::new(Vec3D::new(0., 1.5, 5.), Vec3D::new(0., 0., -1.));
+        assert!(cylinder.intersections(&ray).is_empty());
+        let ray = Ray::new(Vec3D::new(2., 5., 0.), Vec3D::new(0., -1., 0.));
+        assert!(cylinder.intersections(&ray).is_empty());
+    }
+
+    #[test]
+    fn test_cylinder_intersections_inside() {
+        let cylinder = Cylinder::default();
+        let ray = Ray::new(Vec3D::new(0., 0., 0.), Vec3D::new(-1., 0., 0.));
+        let intersections = cylinder.intersections(&ray);
+        assert_eq!(intersections.len(), 1);
+        assert_eq!(intersections[0].position, Vec3D::new(-1., 0., 0.));
+        assert_eq!(intersections[0].normal, -Vec3D::X);
+        assert_eq!(intersections[0].distance, 1.);
+        let ray = Ray::new(Vec3D::new(0., 0., 0.), Vec3D::new(0., 1., 0.));
+        let intersections = cylinder.intersections(&ray);
+        assert_eq!(intersections.len(), 1);
+        assert_eq!(intersections[0].normal, Vec3D::Y);
+    }
+
+    #[test]
+    fn test_cylinder_intersections_grazing() {
+        let cylinder = Cylinder::default();
+        let ray = Ray::new(Vec3D::new(1., 5., 0.), Vec3D::new(0., -1., 0.));
+        let intersections = cylinder.intersections(&ray);
+        assert_eq!(intersections.len(), 2);
+        assert_eq!(intersections[0].position, Vec3D::new(1., 1., 0.));
+        assert_eq!(intersections[1].position, Vec3D::new(1., -1., 0.));
+        let ray = Ray::new(Vec3D::new(-5., 1., 0.), Vec3D::new(1., 0., 0.));
+        let intersections = cylinder.intersections(&ray);
+        assert_eq!(intersections.len(), 2);
+        assert_eq!(intersections[0].position, Vec3D::new(-1., 1., 0.));
+        assert_eq!(intersections[1].position, Vec3D::new(1., 1., 0.));
+    }
+}
@@ -0,0 +1,305 @@
+use std::io;
+
+use crate::{colour::Colour, image::Image};
+
+const KERNEL: [f32; 5] = [1. / 16., 1. / 4., 3. / 8., 1. / 4., 1. / 16.];
+
+#[derive(Debug, Clone, Copy)]
+pub struct Guides<'a> {
+    pub normal: &'a Image,
+    pub albedo: &'a Image,
+    pub depth: &'a Image,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Denoiser {
+    pub iterations: usize,
+    pub colour_sigma: f32,
+    pub normal_sigma: f32,
+    pub albedo_sigma: f32,
+    pub depth_sigma: f32,
+}
+
+impl Default for Denoiser {
+    fn default() -> Self {
+        Self {
+            iterations: 5,
+            colour_sigma: 0.5,
+            normal_sigma: 0.3,
+            albedo_sigma: 0.1,
+            depth_sigma: 0.05,
+        }
+    }
+}
+
+impl Denoiser {
+    pub fn denoise(&self, image: &Image, guides: Guides) -> io::Result<Image> {
+        let (width, height) = (image.width, image.height);
+        for guide in [image, guides.normal, guides.albedo, guides.depth] {
+            if (guide.width, guide.height) != (width, height)
+                || guide.pixels.len() != width * height
+            {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "guide buffers must match the image size".to_string(),
+                ));
+            }
+        }
+        // A zero sigma disables that term instead of dividing by zero.
+        let inverse = |sigma: f32| {
+            if sigma > 0. {
+                1. / (sigma * sigma)
+            } else {
+                0.
+            }
+        };
+        // Non-finite values would turn every weight they touch into NaN, so they count as black.
+        let finite = |colour: Colour| {
+            let clean = |value: f32| if value.is_finite() { value } else { 0. };
+            Colour::new(clean(colour.red), clean(colour.green), clean(colour.blue))
+        };
+        let modulation = guides
+            .albedo
+            .pixels
+            .iter()
+            .map(|albedo| {
+                let albedo = finite(*albedo);
+                if albedo.luminance() > 1e-3 {
+                    Colour::new(
+                        albedo.red.max(1e-3),
+                        albedo.green.max(1e-3),
+                        albedo.blue.max(1e-3),
+                    )
+                } else {
+                    Colour::gray(1.)
+                }
+            })
+            .collect::<Vec<Colour>>();
+        let mut current = image
+            .pixels
+            .iter()
+            .zip(&modulation)
+            .map(|(pixel, albedo)| {
+                let pixel = finite(*pixel);
+                Colour::new(
+                    pixel.red / albedo.red,
+                    pixel.green / albedo.green,
+                    pixel.blue / albedo.blue,
+                )
+            })
+            .collect::<Vec<Colour>>();
+        let term = |distance: f32, weight: f32| {
+            if weight == 0. {
+                0.
+            } else {
+                distance * weight
+            }
+        };
+        let distance = |a: Colour, b: Colour| {
+            let difference = finite(a) - finite(b);
+            difference.red.powi(2) + difference.green.powi(2) + difference.blue.powi(2)
+        };
+        for iteration in 0..self.iterations {
+            // Once the step reaches the image size every tap but the centre falls outside it, so
+            // further iterations would change nothing.
+            let Some(step) = u32::try_from(iteration)
+                .ok()
+                .and_then(|iteration| 1_usize.checked_shl(iteration))
+                .filter(|step| *step < width.max(height))
+            else {
+                break;
+            };
+            let step = step as isize;
+            let colour_weight = inverse(self.colour_sigma * 0.5_f32.powi(iteration as i32));
+            let (normal_weight, albedo_weight, depth_weight) = (
+                inverse(self.normal_sigma),
+                inverse(self.albedo_sigma),
+                inverse(self.depth_sigma),
+            );
+            let mut next = Vec::with_capacity(current.len());
+            for y in 0..height {
+                for x in 0..width {
+                    let centre = y * width + x;
+                    let depth = finite(guides.depth.pixels[centre]).red;
+                    let mut sum = Colour::default();
+                    let mut total = 0.;
+                    for (dy, ky) in KERNEL.iter().enumerate() {
+                        let Some(qy) = (y as isize + (dy as isize - 2) * step)
+                            .try_into()
+                            .ok()
+                            .filter(|qy: &usize| *qy < height)
+                        else {
+                            continue;
+                        };
+                        for (dx, kx) in KERNEL.iter().enumerate() {
+                            let Some(qx) = (x as isize + (dx as isize - 2) * step)
+                                .try_into()
+                                .ok()
+                                .filter(|qx: &usize| *qx < width)
+                            else {
+                                continue;
+                            };
+                            let other = qy * width + qx;
+                            let depth_difference = (depth - finite(guides.depth.pixels[other]).red)
+                                / depth.abs().max(1e-3);
+                            let exponent =
+                                term(distance(current[centre], current[other]), colour_weight)
+                                    + term(
+                                        distance(
+                                            guides.normal.pixels[centre],
+                                            guides.normal.pixels[other],
+                                        ),
+                                        normal_weight,
+                                    )
+                                    + term(
+                                        distance(
+                                            guides.albedo.pixels[centre],
+                                            guides.albedo.pixels[other],
+                                        ),
+                                        albedo_weight,
+                                    )
+                                    + term(depth_difference.powi(2), depth_weight);
+                            let weight = kx * ky * (-exponent).exp();
+                            sum = sum + current[other] * weight;
+                            total += weight;
+                        }
+                    }
+                    next.push(sum * (1. / total));
+                }
+            }
+            current = next;
+        }
+        Ok(Image {
+            width,
+            height,
+            pixels: current
+                .into_iter()
+                .zip(&modulation)
+                .map(|(pixel, albedo)| pixel * *albedo)
+                .collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::random::Rng;
+
+    fn noisy(width: usize, height: usize, value: impl Fn(usize) -> f32) -> Image {
+        let mut rng = Rng::new(9, 0);
+        Image {
+            width,
+            height,
+            pixels: (0..width * height)
+                .map(|index| Colour::gray(value(index % width) * 2. * rng.next_f32()))
+                .collect(),
+        }
+    }
+
+    fn error(image: &Image, value: impl Fn(usize) -> f32) -> f32 {
+        image
+            .pixels
+            .iter()
+            .enumerate()
+            .map(|(index, pixel)| (pixel.red - value(index % image.width)).powi(2))
+            .sum::<f32>()
+            / image.pixels.len() as f32
+    }
+
+    #[test]
+    fn test_reduces_noise_deterministically() {
+        let flat = Image::new(24, 24, Colour::gray(1.));
+        let guides = Guides {
+            normal: &flat,
+            albedo: &Image::new(24, 24, Colour::gray(0.5)),
+            depth: &Image::new(24, 24, Colour::gray(10.)),
+        };
+        let image = noisy(24, 24, |_| 0.4);
+        let denoiser = Denoiser {
+            colour_sigma: 2.,
+            ..Default::default()
+        };
+        let denoised = denoiser.denoise(&image, guides).unwrap();
+        assert!(error(&denoised, |_| 0.4) < error(&image, |_| 0.4) * 0.2);
+        assert_eq!(denoised, denoiser.denoise(&image, guides).unwrap());
+    }
+
+    #[test]
+    fn test_preserves_guide_edges() {
+        let value = |x: usize| if x < 12 { 0.2 } else { 0.8 };
+        let mut normal = Image::new(24, 24, Colour::new(0., 0., 1.));
+        let mut depth = Image::new(24, 24, Colour::gray(5.));
+        for y in 0..24 {
+            for x in 12..24 {
+                normal.pixels[y * 24 + x] = Colour::new(1., 0., 0.);
+                depth.pixels[y * 24 + x] = Colour::gray(9.);
+            }
+        }
+        let guides = Guides {
+            normal: &normal,
+            albedo: &Image::new(24, 24, Colour::gray(1.)),
+            depth: &depth,
+        };
+        let image = noisy(24, 24, value);
+        let denoised = Denoiser {
+            colour_sigma: 2.,
+            ..Default::default()
+        }
+        .denoise(&image, guides)
+        .unwrap();
+        assert!(error(&denoised, value) < error(&image, value) * 0.2);
+        for y in 0..24 {
+            assert!(denoised.pixel(11, y).red < 0.4);
+            assert!(denoised.pixel(12, y).red > 0.6);
+        }
+    }
+
+    #[test]
+    fn test_stops_once_steps_pass_the_image() {
+        let flat = Image::new(8, 8, Colour::gray(1.));
+        let guides = Guides {
+            normal: &flat,
+            albedo: &flat,
+            depth: &flat,
+        };
+        let image = noisy(8, 8, |_| 0.5);
+        let denoise = |iterations| {
+            Denoiser {
+                iterations,
+                ..Default::default()
+            }
+            .denoise(&image, guides)
+            .unwrap()
+        };
+        assert_eq!(denoise(100), denoise(3));
+        assert_eq!(denoise(usize::MAX), denoise(3));
+    }
+
+    #[test]
+    fn test_rejects_bad_input() {
+        let flat = Image::new(8, 8, Colour::gray(1.));
+        let guides = Guides {
+            normal: &flat,
+            albedo: &flat,
+            depth: &flat,
+        };
+        let small = Image::new(4, 8, Colour::gray(1.));
+        let error = Denoiser::default().denoise(&small, guides).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+
+        let mut image = noisy(8, 8, |_| 0.5);
+        image.pixels[9] = Colour::new(f32::NAN, f32::INFINITY, 0.5);
+        let denoised = Denoiser {
+            colour_sigma: 0.,
+            depth_sigma: 0.,
+            ..Default::default()
+        }
+        .denoise(&image, guides)
+        .unwrap();
+        assert!(denoised
+            .pixels
+            .iter()
+            .all(|pixel| pixel.as_rgb().iter().all(|value| value.is_finite())));
+    }
+}
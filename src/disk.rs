@@ -0,0 +1,173 @@
+use crate::{
+    checkpoint::{Fingerprint, Fingerprinter},
+    material::Material,
+    physics::{Intersection, Object},
+    ray::Ray,
+    vector::Vec3D,
+};
+
+#[derive(Debug, Clone)]
+pub struct Disk {
+    pub centre: Vec3D,
+    pub normal: Vec3D,
+    pub radius: f32,
+    pub material: Material,
+}
+
+impl Disk {
+    pub fn new(centre: Vec3D, normal: Vec3D, radius: f32, material: Material) -> Self {
+        Self {
+            centre,
+            normal: normal.normalise(),
+            radius,
+            material,
+        }
+    }
+
+    pub fn unit(material: Material) -> Self {
+        Self {
+            material,
+            ..Default::default()
+        }
+    }
+}
+
+impl Object for Disk {
+    fn intersections(&self, ray: &Ray) -> Vec<Intersection> {
+        let facing = ray.direction.dot(self.normal);
+        if facing == 0. {
+            return Vec::new();
+        }
+        let distance = (self.centre - ray.origin).dot(self.normal) / facing;
+        let intersection = ray.origin + ray.direction * distance;
+        let local_position = intersection - self.centre;
+        if distance <= 0. || local_position.length() > self.radius {
+            return Vec::new();
+        }
+        let (tangent, bitangent) = self.normal.basis();
+        vec![Intersection {
+            distance: (intersection - ray.origin).length(),
+            position: intersection,
+            local_position,
+            normal: self.normal,
+            tangent,
+            uv: (
+                local_position.dot(tangent) / (2. * self.radius) + 0.5,
+                local_position.dot(bitangent) / (2. * self.radius) + 0.5,
+            ),
+            object: Box::new(self.clone()),
+            ray: *ray,
+        }]
+    }
+
+    fn extent(&self) -> f32 {
+        self.radius * 2.
+    }
+
+    fn centre(&self) -> Vec3D {
+        self.centre
+    }
+
+    fn material(&self) -> Material {
+        self.material.clone()
+    }
+}
+
+impl Default for Disk {
+    fn default() -> Self {
+        Self {
+            centre: Vec3D::default(),
+            normal: Vec3D::Y,
+            radius: 1.,
+            material: Material::default(),
+        }
+    }
+}
+
+impl Fingerprint for Disk {
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        state.debug(&(self.centre, self.normal, self.radius));
+        self.material.fingerprint(state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disk_intersections() {
+        let disk = Disk::default();
+        let ray = Ray::new(Vec3D::new(0.5, 2., -0.5), Vec3D::new(0., -1., 0.));
+        let intersections = disk.intersections(&ray);
+        assert_eq!(intersections.len(), 1);
+        assert_eq!(intersections[0].position, Vec3D::new(0.5, 0., -0.5));
+        assert_eq!(intersections[0].normal, Vec3D::Y);
+        assert_eq!(intersections[0].distance, 2.);
+        assert_eq!(intersections[0].uv, (0.75, 0.75));
+    }
+
+    #[test]
+    fn test_tilted_disk_intersections() {
+        let disk = Disk::new(
+            Vec3D::new(0., 0., -2.),
+            Vec3D::new(0., 0., 2.),
+            1.,
+            Material::default(),
+        );
+        let ray = Ray::new(Vec3D::new(0.5, 0.5, 3.), -Vec3D::Z);
+        let intersections = disk.intersections(&ray);
+        assert_eq!(intersections.len(), 1);
+        assert_eq!(intersections[0].position, Vec3D::new(0.5, 0.5, -2.));
+        assert_eq!(intersections[0].normal, Vec3D::Z);
+        assert_eq!(intersections[0].distance, 5.);
+        assert_eq!(intersections[0].tangent.dot(Vec3D::Z), 0.);
+        let ray = Ray::new(Vec3D::new(0.5, 2., 0.), -Vec3D::Y);
+        assert!(disk.intersections(&ray).is_empty());
+    }
+
+    #[test]
+    fn test_disk_intersections_below() {
+        let disk = Disk::default();
+        let ray = Ray::new(Vec3D::new(0., -3., 0.), Vec3D::new(0., 1., 0.));
+        let intersections = disk.intersections(&ray);
+        assert_eq!(intersections.len(), 1);
+        assert_eq!(intersections[0].normal, Vec3D::Y);
+        assert_eq!(intersections[0].distance, 3.);
+    }
+
+    #[test]
+    fn test_disk_intersections_tangent() {
+        let disk = Disk::default();
+        let ray = Ray::new(Vec3D::new(1., 1., 0.), Vec3D::new(0., -1., 0.));
+        let intersections = disk.intersections(&ray);
+        assert_eq!(intersections.len(), 1);
+        assert_eq!(intersections[0].position, Vec3D::new(1., 0., 0.));
+    }
+
+    #[test]
+    fn test_disk_intersections_miss() {
+        let disk = Disk::default();
+        let ray = Ray::new(Vec3D::new(1.5, 1., 0.), Vec3D::new(0., -1., 0.));
+        assert!(disk.intersections(&ray).is_empty());
+        let ray = Ray::new(Vec3D::new(0., 1., 0.), Vec3D::new(0., 1., 0.));
+        assert!(disk.intersections(&ray).is_empty());
+    }
+
+    #[test]
+    fn test_disk_intersections_inside() {
+        let disk = Disk::default();
+        let ray = Ray::new(Vec3D::new(0., 0., 0.), Vec3D::new(0., -1., 0.));
+        assert!(disk.intersections(&ray).is_empty());
+    }
+
+    #[test]
+    fn test_disk_intersections_grazing() {
+        let disk = Disk::default();
+        let ray = Ray::new(Vec3D::new(-5., 0., 0.), Vec3D::new(1., 0., 0.));
+        assert!(disk.intersections(&ray).is_empty());
+        let ray = Ray::new(Vec3D::new(-5., 1., 0.), Vec3D::new(5., -1.00001, 0.));
+        let intersections = disk.intersections(&ray);
+        assert_eq!(intersections.len(), 1);
+    }
+}
@@ -0,0 +1,146 @@
+#[derive(Debug, Clone, PartialEq)]
+pub struct Distribution1D {
+    pub function: Vec<f32>,
+    cdf: Vec<f32>,
+    integral: f32,
+}
+
+impl Distribution1D {
+    pub fn new(function: Vec<f32>) -> Self {
+        let count = function.len() as f32;
+        let mut cdf = Vec::with_capacity(function.len() + 1);
+        cdf.push(0.);
+        for value in &function {
+            cdf.push(cdf.last().unwrap() + value.max(0.) / count);
+        }
+        let integral = *cdf.last().unwrap();
+        if integral > 0. {
+            cdf.iter_mut().for_each(|value| *value /= integral);
+        } else {
+            cdf.iter_mut()
+                .enumerate()
+                .for_each(|(i, value)| *value = i as f32 / count);
+        }
+        Self {
+            function,
+            cdf,
+            integral,
+        }
+    }
+
+    pub fn integral(&self) -> f32 {
+        self.integral
+    }
+
+    pub fn len(&self) -> usize {
+        self.function.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.function.is_empty()
+    }
+
+    pub fn sample(&self, u: f32) -> (f32, f32, usize) {
+        let index = self
+            .cdf
+            .partition_point(|value| *value <= u)
+            .clamp(1, self.len())
+            - 1;
+        let width = self.cdf[index + 1] - self.cdf[index];
+        let offset = if width > 0. {
+            (u - self.cdf[index]) / width
+        } else {
+            0.
+        };
+        (
+            ((index as f32 + offset.clamp(0., 1.)) / self.len() as f32).min(1. - f32::EPSILON),
+            self.pdf(index),
+            index,
+        )
+    }
+
+    pub fn pdf(&self, index: usize) -> f32 {
+        if self.integral > 0. {
+            self.function[index].max(0.) / self.integral
+        } else {
+            1.
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Distribution2D {
+    conditionals: Vec<Distribution1D>,
+    marginal: Distribution1D,
+}
+
+impl Distribution2D {
+    pub fn new(function: &[f32], width: usize, height: usize) -> Self {
+        let conditionals = function
+            .chunks_exact(width)
+            .take(height)
+            .map(|row| Distribution1D::new(row.to_vec()))
+            .collect::<Vec<Distribution1D>>();
+        let marginal =
+            Distribution1D::new(conditionals.iter().map(Distribution1D::integral).collect());
+        Self {
+            conditionals,
+            marginal,
+        }
+    }
+
+    pub fn sample(&self, (u, v): (f32, f32)) -> ((f32, f32), f32) {
+        let (y, marginal_pdf, row) = self.marginal.sample(v);
+        let (x, conditional_pdf, _) = self.conditionals[row].sample(u);
+        ((x, y), marginal_pdf * conditional_pdf)
+    }
+
+    pub fn pdf(&self, (x, y): (f32, f32)) -> f32 {
+        let row = ((y * self.marginal.len() as f32) as usize).min(self.marginal.len() - 1);
+        let conditional = &self.conditionals[row];
+        let column = ((x * conditional.len() as f32) as usize).min(conditional.len() - 1);
+        self.marginal.pdf(row) * conditional.pdf(column)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::random::Rng;
+
+    #[test]
+    fn test_sampling_follows_function() {
+        let distribution = Distribution1D::new(vec![1., 0., 3.]);
+        assert_eq!(distribution.integral(), 4. / 3.);
+        let mut rng = Rng::new(1, 0);
+        let mut counts = [0; 3];
+        for _ in 0..30000 {
+            let (value, pdf, index) = distribution.sample(rng.next_f32());
+            assert_eq!((value * 3.) as usize, index);
+            assert_eq!(pdf, distribution.pdf(index));
+            counts[index] += 1;
+        }
+        assert_eq!(counts[1], 0);
+        assert!((counts[2] as f32 / counts[0] as f32 - 3.).abs() < 0.15);
+    }
+
+    #[test]
+    fn test_zero_function_is_uniform() {
+        let distribution = Distribution1D::new(vec![0.; 4]);
+        assert_eq!(distribution.sample(0.6), (0.6, 1., 2));
+    }
+
+    #[test]
+    fn test_2d_pdf_matches_samples() {
+        let function = [0., 1., 2., 3., 4., 5.];
+        let distribution = Distribution2D::new(&function, 3, 2);
+        let mut rng = Rng::new(2, 0);
+        for _ in 0..1000 {
+            let (point, pdf) = distribution.sample(rng.next_pair());
+            assert!((pdf - distribution.pdf(point)).abs() < 1e-5);
+        }
+        let total = function.iter().sum::<f32>() / 6.;
+        assert!((distribution.pdf((0.9, 0.9)) - 5. / total).abs() < 1e-5);
+        assert_eq!(distribution.pdf((0.1, 0.1)), 0.);
+    }
+}
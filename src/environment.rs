@@ -0,0 +1,218 @@
+use std::{
+    f32::consts::{FRAC_PI_2, PI},
+    io,
+    path::Path,
+};
+
+use crate::{
+    checkpoint::{Fingerprint, Fingerprinter},
+    colour::Colour,
+    distribution::Distribution2D,
+    image::Image,
+    vector::Vec3D,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EnvironmentSample {
+    pub direction: Vec3D,
+    pub radiance: Colour,
+    pub pdf: f32,
+}
+
+#[derive(Debug, Clone)]
+pub struct Environment {
+    pub image: Image,
+    pub rotation: f32,
+    pub intensity: f32,
+    distribution: Distribution2D,
+}
+
+impl Environment {
+    pub fn new(image: Image, rotation: f32, intensity: f32) -> io::Result<Self> {
+        if image.width == 0 || image.height == 0 || image.pixels.len() != image.width * image.height
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid {}x{} environment map", image.width, image.height),
+            ));
+        }
+        let weights = image
+            .pixels
+            .chunks_exact(image.width)
+            .enumerate()
+            .flat_map(|(row, pixels)| {
+                let latitude = FRAC_PI_2 - PI * (row as f32 + 0.5) / image.height as f32;
+                pixels
+                    .iter()
+                    .map(move |pixel| pixel.luminance().max(0.) * latitude.cos())
+            })
+            .collect::<Vec<f32>>();
+        let distribution = Distribution2D::new(&weights, image.width, image.height);
+        Ok(Self {
+            image,
+            rotation,
+            intensity,
+            distribution,
+        })
+    }
+
+    pub fn load(path: impl AsRef<Path>, rotation: f32, intensity: f32) -> io::Result<Self> {
+        Self::new(Image::load(path)?, rotation, intensity)
+    }
+
+    fn map_position(&self, direction: Vec3D) -> (f32, f32) {
+        let direction = direction.normalise();
+        let (sin, cos) = self.rotation.sin_cos();
+        let x = cos * direction.x + sin * direction.z;
+        let z = -sin * direction.x + cos * direction.z;
+        (
+            (0.5 + x.atan2(-z) / (2. * PI)).rem_euclid(1.),
+            0.5 - direction.y.clamp(-1., 1.).asin() / PI,
+        )
+    }
+
+    fn direction(&self, (x, y): (f32, f32)) -> Vec3D {
+        let longitude = (x - 0.5) * 2. * PI + self.rotation;
+        let latitude = (0.5 - y) * PI;
+        Vec3D::new(
+            latitude.cos() * longitude.sin(),
+            latitude.sin(),
+            -latitude.cos() * longitude.cos(),
+        )
+    }
+
+    fn jacobian(y: f32) -> f32 {
+        2. * PI * PI * ((0.5 - y) * PI).cos()
+    }
+
+    pub fn value(&self, direction: Vec3D) -> Colour {
+        let (x, y) = self.map_position(direction);
+        let column = ((x * self.image.width as f32) as usize).min(self.image.width - 1);
+        let row = ((y * self.image.height as f32) as usize).min(self.image.height - 1);
+        self.image.pixel(column, row) * self.intensity
+    }
+
+    pub fn pdf(&self, direction: Vec3D) -> f32 {
+        let (x, y) = self.map_position(direction);
+        let jacobian = Self::jacobian(y);
+        if jacobian <= 0. {
+            return 0.;
+        }
+        self.distribution.pdf((x, y)) / jacobian
+    }
+
+    pub fn sample(&self, uv: (f32, f32)) -> Option<EnvironmentSample> {
+        let (point, pdf) = self.distribution.sample(uv);
+        let jacobian = Self::jacobian(point.1);
+        if pdf <= 0. || jacobian <= 0. {
+            return None;
+        }
+        let direction = self.direction(point);
+        Some(EnvironmentSample {
+            direction,
+            radiance: self.value(direction),
+            pdf: pdf / jacobian,
+        })
+    }
+}
+
+impl Fingerprint for Environment {
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        self.image.fingerprint(state);
+        state.floats([self.rotation, self.intensity]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::random::Rng;
+
+    fn sun() -> Image {
+        let mut image = Image::new(16, 8, Colour::gray(0.1));
+        image.pixels[2 * 16 + 11] = Colour::gray(50.);
+        image
+    }
+
+    #[test]
+    fn test_mapping_round_trip() {
+        for rotation in [0., 1.3] {
+            let environment = Environment::new(sun(), rotation, 1.).unwrap();
+            for direction in [
+                Vec3D::new(0.3, 0.5, -0.8),
+                Vec3D::new(-0.9, -0.1, 0.2),
+                Vec3D::new(0.1, 0.9, 0.4),
+            ] {
+                let direction = direction.normalise();
+                let map = environment.map_position(direction);
+                assert_eq!(environment.direction(map), direction);
+            }
+        }
+        let environment = Environment::new(sun(), 0., 1.).unwrap();
+        assert_eq!(environment.map_position(-Vec3D::Z), (0.5, 0.5));
+        assert_eq!(environment.map_position(Vec3D::X).0, 0.75);
+    }
+
+    #[test]
+    fn test_rejects_empty_maps() {
+        for image in [
+            Image::new(0, 4, Colour::default()),
+            Image::new(4, 0, Colour::default()),
+        ] {
+            let error = Environment::new(image, 0., 1.).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+
+    #[test]
+    fn test_rotation_and_intensity() {
+        let environment = Environment::new(sun(), 0., 2.).unwrap();
+        let bright = environment.direction((11.5 / 16., 2.5 / 8.));
+        assert_eq!(environment.value(bright), Colour::gray(100.));
+        let rotated = Environment::new(sun(), FRAC_PI_2, 2.).unwrap();
+        assert_eq!(rotated.value(bright), Colour::gray(0.2));
+        let (sin, cos) = FRAC_PI_2.sin_cos();
+        let turned = Vec3D::new(
+            cos * bright.x - sin * bright.z,
+            bright.y,
+            sin * bright.x + cos * bright.z,
+        );
+        assert_eq!(rotated.value(turned), Colour::gray(100.));
+    }
+
+    #[test]
+    fn test_importance_sampling() {
+        let environment = Environment::new(sun(), 0.4, 1.).unwrap();
+        let mut rng = Rng::new(3, 0);
+        let count = 20000;
+        let mut estimate = Colour::default();
+        let mut bright = 0;
+        for _ in 0..count {
+            let sample = environment.sample(rng.next_pair()).unwrap();
+            assert!((sample.pdf - environment.pdf(sample.direction)).abs() < 1e-2 * sample.pdf);
+            estimate = estimate + sample.radiance * (1. / sample.pdf);
+            if sample.radiance.red > 1. {
+                bright += 1;
+            }
+        }
+        assert!(bright > count / 2);
+        let estimate = estimate.red / count as f32;
+        let steps = 400;
+        let mut integral = 0.;
+        for i in 0..steps {
+            for j in 0..steps {
+                let point = (
+                    (i as f32 + 0.5) / steps as f32,
+                    (j as f32 + 0.5) / steps as f32,
+                );
+                let direction = environment.direction(point);
+                integral += environment.value(direction).red * Environment::jacobian(point.1)
+                    / (steps * steps) as f32;
+            }
+        }
+        assert!(
+            (estimate - integral).abs() < 0.01 * integral,
+            "{estimate} {integral}"
+        );
+    }
+}
@@ -0,0 +1,292 @@
+use crate::{colour::Colour, filter::Filter, image::Image};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilmTile {
+    pub origin: (usize, usize),
+    pub width: usize,
+    pub height: usize,
+    pub filter: Filter,
+    sums: Vec<Colour>,
+    weights: Vec<f32>,
+}
+
+impl FilmTile {
+    pub fn add_sample(&mut self, position: (f32, f32), colour: Colour) {
+        splat(
+            self.filter,
+            (self.origin, self.width, self.height),
+            (&mut self.sums, &mut self.weights),
+            position,
+            colour,
+        );
+    }
+}
+
+fn splat(
+    filter: Filter,
+    (origin, width, height): ((usize, usize), usize, usize),
+    (sums, weights): (&mut [Colour], &mut [f32]),
+    (x, y): (f32, f32),
+    colour: Colour,
+) {
+    let radius = filter.radius();
+    let (x, y) = (x - 0.5, y - 0.5);
+    // Pixels take samples from the half-open interval [centre - radius, centre + radius), so a
+    // sample on a shared edge lands in exactly one pixel of a box filter.
+    let bound = |centre: f32, start: usize, size: usize| {
+        let first = ((centre - radius).floor() + 1.).max(start as f32) as usize;
+        let last = ((centre + radius).floor() + 1.).clamp(0., (start + size) as f32) as usize;
+        first..last
+    };
+    for row in bound(y, origin.1, height) {
+        for column in bound(x, origin.0, width) {
+            let weight = filter.evaluate(column as f32 - x, row as f32 - y);
+            if weight == 0. {
+                continue;
+            }
+            let index = (row - origin.1) * width + column - origin.0;
+            sums[index] = sums[index] + colour * weight;
+            weights[index] += weight;
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Film {
+    pub width: usize,
+    pub height: usize,
+    pub filter: Filter,
+    sums: Vec<Colour>,
+    weights: Vec<f32>,
+}
+
+impl Film {
+    pub fn new(width: usize, height: usize, filter: Filter) -> Self {
+        Self {
+            width,
+            height,
+            filter,
+            sums: vec![Colour::default(); width * height],
+            weights: vec![0.; width * height],
+        }
+    }
+
+    pub fn from_buffers(
+        width: usize,
+        height: usize,
+        filter: Filter,
+        sums: Vec<Colour>,
+        weights: Vec<f32>,
+    ) -> Option<Self> {
+        if sums.len() != width * height || weights.len() != width * height {
+            return None;
+        }
+        Some(Self {
+            width,
+            height,
+            filter,
+            sums,
+            weights,
+        })
+    }
+
+    pub fn buffers(&self) -> (&[Colour], &[f32]) {
+        (&self.sums, &self.weights)
+    }
+
+    pub fn tile(&self, (x, y): (usize, usize), (width, height): (usize, usize)) -> FilmTile {
+        let padding = self.filter.radius().ceil() as usize;
+        let origin = (
+            x.saturating_sub(padding).min(self.width),
+            y.saturating_sub(padding).min(self.height),
+        );
+        let width = (x + width + padding).min(self.width) - origin.0;
+        let height = (y + height + padding).min(self.height) - origin.1;
+        FilmTile {
+            origin,
+            width,
+            height,
+            filter: self.filter,
+            sums: vec![Colour::default(); width * height],
+            weights: vec![0.; width * height],
+        }
+    }
+
+    pub fn merge(&mut self, tile: &FilmTile) {
+        for row in 0..tile.height {
+            for column in 0..tile.width {
+                let source = row * tile.width + column;
+                let target = (row + tile.origin.1) * self.width + column + tile.origin.0;
+                self.sums[target] = self.sums[target] + tile.sums[source];
+                self.weights[target] += tile.weights[source];
+            }
+        }
+    }
+
+    pub fn add_sample(&mut self, position: (f32, f32), colour: Colour) {
+        splat(
+            self.filter,
+            ((0, 0), self.width, self.height),
+            (&mut self.sums, &mut self.weights),
+            position,
+            colour,
+        );
+    }
+
+    // Filters each pixel's own mean instead of its samples, so a pixel contributes to its
+    // neighbours by the filter weight alone, however many samples it has taken.
+    pub fn resolve(&self, filter: Filter) -> Image {
+        let means = self.image();
+        let reach = filter.radius().ceil() as isize;
+        let mut pixels = Vec::with_capacity(self.width * self.height);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let mut sum = Colour::default();
+                let mut total = 0.;
+                for dy in -reach..=reach {
+                    for dx in -reach..=reach {
+                        let (Ok(column), Ok(row)) = (
+                            usize::try_from(x as isize + dx),
+                            usize::try_from(y as isize + dy),
+                        ) else {
+                            continue;
+                        };
+                        if column >= self.width
+                            || row >= self.height
+                            || self.weights[row * self.width + column] == 0.
+                        {
+                            continue;
+                        }
+                        let weight = filter.evaluate(dx as f32, dy as f32);
+                        sum = sum + means.pixel(column, row) * weight;
+                        total += weight;
+                    }
+                }
+                pixels.push(if total > 0. {
+                    sum * (1. / total)
+                } else {
+                    Colour::default()
+                });
+            }
+        }
+        Image {
+            width: self.width,
+            height: self.height,
+            pixels,
+        }
+    }
+
+    pub fn image(&self) -> Image {
+        Image {
+            width: self.width,
+            height: self.height,
+            pixels: self
+                .sums
+                .iter()
+                .zip(&self.weights)
+                .map(|(sum, weight)| {
+                    if *weight == 0. {
+                        Colour::default()
+                    } else {
+                        *sum * (1. / weight)
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::random::Rng;
+
+    fn samples() -> Vec<((f32, f32), Colour)> {
+        let mut rng = Rng::new(5, 0);
+        (0..400)
+            .map(|_| {
+                let (x, y) = rng.next_pair();
+                let colour = Colour::new(rng.next_f32(), rng.next_f32(), rng.next_f32());
+                ((x * 8., y * 6.), colour)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_box_filter_averages_pixels() {
+        let mut film = Film::new(2, 1, Filter::default());
+        film.add_sample((0.2, 0.5), Colour::gray(1.));
+        film.add_sample((0.7, 0.1), Colour::gray(0.));
+        film.add_sample((1.5, 0.5), Colour::gray(0.4));
+        let image = film.image();
+        assert_eq!(image.pixel(0, 0), Colour::gray(0.5));
+        assert_eq!(image.pixel(1, 0), Colour::gray(0.4));
+        let mut film = Film::new(2, 2, Filter::default());
+        film.add_sample((1., 1.), Colour::gray(1.));
+        let weights = film.buffers().1;
+        assert_eq!(weights, [0., 0., 0., 1.]);
+    }
+
+    #[test]
+    fn test_resolve_weighs_pixels_not_samples() {
+        let mut film = Film::new(2, 1, Filter::default());
+        film.add_sample((0.5, 0.5), Colour::gray(1.));
+        for _ in 0..63 {
+            film.add_sample((1.5, 0.5), Colour::gray(0.));
+        }
+        let image = film.resolve(Filter::Tent { radius: 1.5 });
+        assert_eq!(image.pixel(0, 0), Colour::gray(0.75));
+        assert_eq!(image.pixel(1, 0), Colour::gray(0.25));
+        assert_eq!(film.resolve(Filter::default()), film.image());
+    }
+
+    #[test]
+    fn test_tiles_outside_the_film_are_empty() {
+        let film = Film::new(4, 3, Filter::Tent { radius: 1.5 });
+        let tile = film.tile((8, 7), (2, 2));
+        assert_eq!((tile.width, tile.height), (0, 0));
+        let mut merged = film.clone();
+        merged.merge(&tile);
+        assert_eq!(merged, film);
+    }
+
+    #[test]
+    fn test_tiles_match_whole_film() {
+        let filter = Filter::Mitchell {
+            radius: 2.,
+            b: 1. / 3.,
+            c: 1. / 3.,
+        };
+        let mut whole = Film::new(8, 6, filter);
+        let mut tiled = Film::new(8, 6, filter);
+        for (position, colour) in samples() {
+            whole.add_sample(position, colour);
+        }
+        for (x, y) in [(0, 0), (4, 0), (0, 3), (4, 3)] {
+            let mut tile = tiled.tile((x, y), (4, 3));
+            for (position, colour) in samples() {
+                if (x..x + 4).contains(&(position.0 as usize))
+                    && (y..y + 3).contains(&(position.1 as usize))
+                {
+                    tile.add_sample(position, colour);
+                }
+            }
+            tiled.merge(&tile);
+        }
+        for (a, b) in whole.image().pixels.iter().zip(&tiled.image().pixels) {
+            assert!((*a - *b).luminance().abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn test_splats_cross_tile_boundary() {
+        let mut film = Film::new(4, 1, Filter::Tent { radius: 1.5 });
+        let mut tile = film.tile((0, 0), (2, 1));
+        assert_eq!(tile.width, 4);
+        tile.add_sample((1.9, 0.5), Colour::gray(1.));
+        film.merge(&tile);
+        let image = film.image();
+        assert_eq!(image.pixel(2, 0), Colour::gray(1.));
+        assert_eq!(image.pixel(3, 0), Colour::default());
+    }
+}
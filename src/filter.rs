@@ -0,0 +1,118 @@
+use std::f32::consts::PI;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Filter {
+    Box { radius: f32 },
+    Tent { radius: f32 },
+    Gaussian { radius: f32, alpha: f32 },
+    Mitchell { radius: f32, b: f32, c: f32 },
+    Lanczos { radius: f32 },
+}
+
+impl Default for Filter {
+    fn default() -> Self {
+        Self::Box { radius: 0.5 }
+    }
+}
+
+impl Filter {
+    pub fn radius(&self) -> f32 {
+        match *self {
+            Self::Box { radius }
+            | Self::Tent { radius }
+            | Self::Gaussian { radius, .. }
+            | Self::Mitchell { radius, .. }
+            | Self::Lanczos { radius } => radius,
+        }
+    }
+
+    pub fn evaluate(&self, x: f32, y: f32) -> f32 {
+        self.evaluate_1d(x) * self.evaluate_1d(y)
+    }
+
+    fn evaluate_1d(&self, x: f32) -> f32 {
+        let radius = self.radius();
+        let x = x.abs();
+        if x > radius {
+            return 0.;
+        }
+        match *self {
+            Self::Box { .. } => 1.,
+            Self::Tent { .. } => radius - x,
+            Self::Gaussian { alpha, .. } => {
+                ((-alpha * x * x).exp() - (-alpha * radius * radius).exp()).max(0.)
+            }
+            Self::Mitchell { b, c, .. } => {
+                let x = 2. * x / radius;
+                if x < 1. {
+                    ((12. - 9. * b - 6. * c) * x.powi(3)
+                        + (-18. + 12. * b + 6. * c) * x.powi(2)
+                        + (6. - 2. * b))
+                        / 6.
+                } else {
+                    ((-b - 6. * c) * x.powi(3)
+                        + (6. * b + 30. * c) * x.powi(2)
+                        + (-12. * b - 48. * c) * x
+                        + (8. * b + 24. * c))
+                        / 6.
+                }
+            }
+            Self::Lanczos { .. } => sinc(x) * sinc(x / radius),
+        }
+    }
+}
+
+fn sinc(x: f32) -> f32 {
+    if x.abs() < 1e-5 {
+        return 1.;
+    }
+    (PI * x).sin() / (PI * x)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_support() {
+        for filter in [
+            Filter::Box { radius: 0.5 },
+            Filter::Tent { radius: 1. },
+            Filter::Gaussian {
+                radius: 1.5,
+                alpha: 2.,
+            },
+            Filter::Mitchell {
+                radius: 2.,
+                b: 1. / 3.,
+                c: 1. / 3.,
+            },
+            Filter::Lanczos { radius: 3. },
+        ] {
+            let radius = filter.radius();
+            assert_eq!(filter.evaluate(radius + 0.01, 0.), 0.);
+            assert_eq!(filter.evaluate(0., -radius - 0.01), 0.);
+            assert!(filter.evaluate(0., 0.) > 0.);
+            assert_eq!(filter.evaluate(0.3, -0.2), filter.evaluate(-0.3, 0.2));
+            assert!(
+                filter.evaluate_1d(radius).abs() < 1e-5 || matches!(filter, Filter::Box { .. })
+            );
+        }
+    }
+
+    #[test]
+    fn test_shapes() {
+        let mitchell = Filter::Mitchell {
+            radius: 2.,
+            b: 1. / 3.,
+            c: 1. / 3.,
+        };
+        assert!((mitchell.evaluate_1d(0.) - 8. / 9.).abs() < 1e-6);
+        assert!(mitchell.evaluate_1d(1.5) < 0.);
+        let lanczos = Filter::Lanczos { radius: 3. };
+        assert!(lanczos.evaluate_1d(1.).abs() < 1e-6);
+        assert!(lanczos.evaluate_1d(1.5) < 0.);
+        let tent = Filter::Tent { radius: 1. };
+        assert_eq!(tent.evaluate(0.5, 0.5), 0.25);
+    }
+}
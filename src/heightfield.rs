@@ -0,0 +1,495 @@
+use std::{io, path::Path, sync::Arc};
+
+use crate::{
+    checkpoint::{Fingerprint, Fingerprinter},
+    constants::EPSILON,
+    image::Image,
+    material::Material,
+    noise::NoiseTexture,
+    physics::{Intersection, Object},
+    ray::Ray,
+    vector::Vec3D,
+};
+
+// The grids sit behind Arcs because every intersection carries a clone of the object.
+#[derive(Debug, Clone)]
+pub struct Heightfield {
+    pub heights: Arc<[f32]>,
+    pub resolution: (usize, usize),
+    pub centre: Vec3D,
+    pub scale: Vec3D,
+    pub material: Material,
+    normals: Arc<[Vec3D]>,
+    range: (f32, f32),
+}
+
+impl Heightfield {
+    pub fn new(
+        heights: Vec<f32>,
+        resolution: (usize, usize),
+        centre: Vec3D,
+        scale: Vec3D,
+        material: Material,
+    ) -> io::Result<Self> {
+        let (columns, rows) = resolution;
+        if columns < 2 || rows < 2 {
+            return Err(invalid_data(format!(
+                "heightfield needs at least 2x2 samples, found {columns}x{rows}"
+            )));
+        }
+        if columns.checked_mul(rows) != Some(heights.len()) {
+            return Err(invalid_data(format!(
+                "expected {columns}x{rows} heights, found {}",
+                heights.len()
+            )));
+        }
+        let range = heights
+            .iter()
+            .fold((f32::INFINITY, f32::NEG_INFINITY), |(low, high), height| {
+                (low.min(*height), high.max(*height))
+            });
+        let mut heightfield = Self {
+            heights: heights.into(),
+            resolution,
+            centre,
+            scale,
+            material,
+            normals: Arc::from([]),
+            range,
+        };
+        heightfield.normals = (0..rows)
+            .flat_map(|row| (0..columns).map(move |column| (column, row)))
+            .map(|(column, row)| heightfield.vertex_normal(column, row))
+            .collect();
+        Ok(heightfield)
+    }
+
+    pub fn from_image(
+        image: &Image,
+        centre: Vec3D,
+        scale: Vec3D,
+        material: Material,
+    ) -> io::Result<Self> {
+        Self::new(
+            image.pixels.iter().map(|pixel| pixel.luminance()).collect(),
+            (image.width, image.height),
+            centre,
+            scale,
+            material,
+        )
+    }
+
+    pub fn load(
+        path: impl AsRef<Path>,
+        centre: Vec3D,
+        scale: Vec3D,
+        material: Material,
+    ) -> io::Result<Self> {
+        Self::from_image(&Image::load(path)?, centre, scale, material)
+    }
+
+    pub fn from_noise(
+        noise: &NoiseTexture,
+        resolution: (usize, usize),
+        centre: Vec3D,
+        scale: Vec3D,
+        material: Material,
+    ) -> io::Result<Self> {
+        let (columns, rows) = resolution;
+        if columns < 2 || rows < 2 {
+            return Err(invalid_data(format!(
+                "heightfield needs at least 2x2 samples, found {columns}x{rows}"
+            )));
+        }
+        let heights = (0..rows)
+            .flat_map(|row| (0..columns).map(move |column| (column, row)))
+            .map(|(column, row)| {
+                let u = column as f32 / (columns - 1) as f32 - 0.5;
+                let v = row as f32 / (rows - 1) as f32 - 0.5;
+                noise.amount(Vec3D::new(u * scale.x, 0., v * scale.z))
+            })
+            .collect();
+        Self::new(heights, resolution, centre, scale, material)
+    }
+
+    pub fn height(&self, column: usize, row: usize) -> f32 {
+        self.heights[row * self.resolution.0 + column]
+    }
+
+    fn spacing(&self) -> (f32, f32) {
+        (
+            self.scale.x / (self.resolution.0 - 1) as f32,
+            self.scale.z / (self.resolution.1 - 1) as f32,
+        )
+    }
+
+    fn vertex_normal(&self, column: usize, row: usize) -> Vec3D {
+        let (columns, rows) = self.resolution;
+        let (dx, dz) = self.spacing();
+        let (left, right) = (column.saturating_sub(1), (column + 1).min(columns - 1));
+        let (back, front) = (row.saturating_sub(1), (row + 1).min(rows - 1));
+        let slope_x = (self.height(right, row) - self.height(left, row)) * self.scale.y
+            / ((right - left) as f32 * dx);
+        let slope_z = (self.height(column, front) - self.height(column, back)) * self.scale.y
+            / ((front - back) as f32 * dz);
+        Vec3D::new(-slope_x, 1., -slope_z).normalise()
+    }
+
+    fn to_grid(&self, position: Vec3D) -> Vec3D {
+        let local = position - self.centre;
+        Vec3D::new(
+            (local.x / self.scale.x + 0.5) * (self.resolution.0 - 1) as f32,
+            local.y / self.scale.y,
+            (local.z / self.scale.z + 0.5) * (self.resolution.1 - 1) as f32,
+        )
+    }
+
+    fn grid_direction(&self, direction: Vec3D) -> Vec3D {
+        Vec3D::new(
+            direction.x / self.scale.x * (self.resolution.0 - 1) as f32,
+            direction.y / self.scale.y,
+            direction.z / self.scale.z * (self.resolution.1 - 1) as f32,
+        )
+    }
+
+    fn bounds(&self, origin: Vec3D, direction: Vec3D) -> Option<(f32, f32)> {
+        let mut near = 0_f32;
+        let mut far = f32::INFINITY;
+        for (origin, direction, minimum, maximum) in [
+            (origin.x, direction.x, 0., (self.resolution.0 - 1) as f32),
+            (origin.y, direction.y, self.range.0, self.range.1),
+            (origin.z, direction.z, 0., (self.resolution.1 - 1) as f32),
+        ] {
+            if direction == 0. {
+                if origin < minimum || origin > maximum {
+                    return None;
+                }
+                continue;
+            }
+            let first = (minimum - origin) / direction;
+            let second = (maximum - origin) / direction;
+            near = near.max(first.min(second));
+            far = far.min(first.max(second));
+        }
+        (near <= far).then_some((near, far))
+    }
+
+    fn cell_hits(&self, origin: Vec3D, direction: Vec3D, column: usize, row: usize) -> Vec<Hit> {
+        let corner = |i: usize, j: usize| {
+            Vec3D::new(
+                (column + i) as f32,
+                self.height(column + i, row + j),
+                (row + j) as f32,
+            )
+        };
+        let normal = |i: usize, j: usize| self.normals[(row + j) * self.resolution.0 + column + i];
+        [[(0, 0), (1, 0), (1, 1)], [(0, 0), (1, 1), (0, 1)]]
+            .into_iter()
+            .filter_map(|triangle| {
+                let [a, b, c] = triangle.map(|(i, j)| corner(i, j));
+                let (distance, u, v) = intersect_triangle(origin, direction, a, b, c)?;
+                let [na, nb, nc] = triangle.map(|(i, j)| normal(i, j));
+                Some(Hit {
+                    distance,
+                    normal: (na * (1. - u - v) + nb * u + nc * v).normalise(),
+                })
+            })
+            .collect()
+    }
+}
+
+struct Hit {
+    distance: f32,
+    normal: Vec3D,
+}
+
+fn intersect_triangle(
+    origin: Vec3D,
+    direction: Vec3D,
+    a: Vec3D,
+    b: Vec3D,
+    c: Vec3D,
+) -> Option<(f32, f32, f32)> {
+    let (edge_1, edge_2) = (b - a, c - a);
+    let p = direction.cross(edge_2);
+    let determinant = edge_1.dot(p);
+    if determinant.abs() < EPSILON {
+        return None;
+    }
+    let inverse = 1. / determinant;
+    let s = origin - a;
+    let u = s.dot(p) * inverse;
+    if !(-EPSILON..=1. + EPSILON).contains(&u) {
+        return None;
+    }
+    let q = s.cross(edge_1);
+    let v = direction.dot(q) * inverse;
+    if v < -EPSILON || u + v > 1. + EPSILON {
+        return None;
+    }
+    let distance = edge_2.dot(q) * inverse;
+    (distance > 0.).then_some((distance, u, v))
+}
+
+impl Object for Heightfield {
+    fn intersections(&self, ray: &Ray) -> Vec<Intersection> {
+        let origin = self.to_grid(ray.origin);
+        let direction = self.grid_direction(ray.direction);
+        let Some((near, far)) = self.bounds(origin, direction) else {
+            return Vec::new();
+        };
+        let (columns, rows) = (self.resolution.0 - 1, self.resolution.1 - 1);
+        let entry = origin + direction * near;
+        let mut cell = (
+            (entry.x.floor().max(0.) as usize).min(columns - 1),
+            (entry.z.floor().max(0.) as usize).min(rows - 1),
+        );
+        let axis = |origin: f32, direction: f32, cell: usize| {
+            if direction > 0. {
+                (
+                    1_isize,
+                    ((cell + 1) as f32 - origin) / direction,
+                    1. / direction,
+                )
+            } else if direction < 0. {
+                (-1, (cell as f32 - origin) / direction, -1. / direction)
+            } else {
+                (0, f32::INFINITY, f32::INFINITY)
+            }
+        };
+        let (step_x, mut next_x, delta_x) = axis(origin.x, direction.x, cell.0);
+        let (step_z, mut next_z, delta_z) = axis(origin.z, direction.z, cell.1);
+        let mut hits: Vec<Hit> = Vec::new();
+        loop {
+            for hit in self.cell_hits(origin, direction, cell.0, cell.1) {
+                if !hits
+                    .iter()
+                    .any(|other| (other.distance - hit.distance).abs() < EPSILON)
+                {
+                    hits.push(hit);
+                }
+            }
+            let (column, row) = if next_x < next_z {
+                if next_x > far {
+                    break;
+                }
+                next_x += delta_x;
+                (cell.0 as isize + step_x, cell.1 as isize)
+            } else {
+                if next_z > far {
+                    break;
+                }
+                next_z += delta_z;
+                (cell.0 as isize, cell.1 as isize + step_z)
+            };
+            if column < 0 || row < 0 || column >= columns as isize || row >= rows as isize {
+                break;
+            }
+            cell = (column as usize, row as usize);
+        }
+        hits.sort_unstable_by(|a, b| a.distance.partial_cmp(&b.distance).unwrap());
+        hits.into_iter()
+            .map(|hit| {
+                let position = ray.origin + ray.direction * hit.distance;
+                let local_position = position - self.centre;
+                let grid = self.to_grid(position);
+                let tangent = (Vec3D::X - hit.normal * hit.normal.x).normalise();
+                Intersection {
+                    distance: (position - ray.origin).length(),
+                    position,
+                    local_position,
+                    normal: hit.normal,
+                    tangent,
+                    uv: (grid.x / columns as f32, 1. - grid.z / rows as f32),
+                    object: Box::new(self.clone()),
+                    ray: *ray,
+                }
+            })
+            .collect()
+    }
+
+    fn extent(&self) -> f32 {
+        Vec3D::new(
+            self.scale.x,
+            (self.range.1 - self.range.0) * self.scale.y,
+            self.scale.z,
+        )
+        .length()
+    }
+
+    fn centre(&self) -> Vec3D {
+        self.centre + Vec3D::Y * ((self.range.0 + self.range.1) / 2. * self.scale.y)
+    }
+
+    fn material(&self) -> Material {
+        self.material.clone()
+    }
+}
+
+impl Fingerprint for Heightfield {
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        state.debug(&(self.resolution, self.centre, self.scale));
+        state.floats(self.heights.iter().copied());
+        self.material.fingerprint(state);
+    }
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{colour::Colour, noise::NoisePattern};
+
+    fn ramp() -> Heightfield {
+        let resolution = (9, 5);
+        let heights = (0..resolution.1)
+            .flat_map(|_| (0..resolution.0).map(|column| column as f32 / 8.))
+            .collect();
+        Heightfield::new(
+            heights,
+            resolution,
+            Vec3D::ZERO,
+            Vec3D::new(4., 2., 2.),
+            Material::default(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_rejects_invalid_grids() {
+        let tiny = Image::new(1, 3, Colour::default());
+        let error = Heightfield::from_image(&tiny, Vec3D::ZERO, Vec3D::ONE, Material::default())
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        let short = Heightfield::new(
+            vec![0.; 5],
+            (3, 2),
+            Vec3D::ZERO,
+            Vec3D::ONE,
+            Material::default(),
+        );
+        assert!(short.is_err());
+    }
+
+    #[test]
+    fn test_flat_field() {
+        let flat = Heightfield::new(
+            vec![0.5; 16],
+            (4, 4),
+            Vec3D::new(0., -1., 0.),
+            Vec3D::new(3., 2., 3.),
+            Material::default(),
+        )
+        .unwrap();
+        let ray = Ray::new(Vec3D::new(0.2, 5., -0.7), -Vec3D::Y);
+        let intersections = flat.intersections(&ray);
+        assert_eq!(intersections.len(), 1);
+        assert_eq!(intersections[0].position, Vec3D::new(0.2, 0., -0.7));
+        assert_eq!(intersections[0].normal, Vec3D::Y);
+        assert_eq!(intersections[0].distance, 5.);
+        let ray = Ray::new(Vec3D::new(0.2, -5., -0.7), Vec3D::Y);
+        assert_eq!(flat.intersections(&ray).len(), 1);
+        let ray = Ray::new(Vec3D::new(2., 5., 0.), -Vec3D::Y);
+        assert!(flat.intersections(&ray).is_empty());
+    }
+
+    #[test]
+    fn test_ramp_traversal() {
+        let ramp = ramp();
+        let ray = Ray::new(Vec3D::new(-5., 1., 0.5), Vec3D::X);
+        let intersections = ramp.intersections(&ray);
+        assert_eq!(intersections.len(), 1);
+        assert_eq!(intersections[0].position, Vec3D::new(0., 1., 0.5));
+        let expected = Vec3D::new(-0.5, 1., 0.).normalise();
+        assert_eq!(intersections[0].normal, expected);
+        assert!(intersections[0].tangent.dot(expected).abs() < 1e-6);
+        assert_eq!(intersections[0].uv, (0.5, 0.25));
+        let direction = Vec3D::new(1., -0.6, 0.35).normalise();
+        let ray = Ray::new(Vec3D::new(-2., 1.8, -0.9), direction);
+        let intersections = ramp.intersections(&ray);
+        assert_eq!(intersections.len(), 1);
+        let position = intersections[0].position;
+        assert!((position.y - (position.x / 4. + 0.5) * 2.).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_smooth_normals() {
+        let resolution = (17, 17);
+        let heights = (0..resolution.1)
+            .flat_map(|row| {
+                (0..resolution.0).map(move |column| {
+                    let x = column as f32 / 8. - 1.;
+                    let z = row as f32 / 8. - 1.;
+                    1. - (x * x + z * z) / 2.
+                })
+            })
+            .collect();
+        let hill = Heightfield::new(
+            heights,
+            resolution,
+            Vec3D::ZERO,
+            Vec3D::new(2., 1., 2.),
+            Material::default(),
+        )
+        .unwrap();
+        let normal_at =
+            |x: f32| hill.intersections(&Ray::new(Vec3D::new(x, 5., 0.), -Vec3D::Y))[0].normal;
+        let (a, b) = (normal_at(0.5), normal_at(0.5 + 1. / 16.));
+        let (c, d) = (normal_at(0.5 + 1. / 32.), (a + b).normalise());
+        assert!((c - d).length() < 0.01);
+        assert!(a.x > 0. && a.x < b.x);
+    }
+
+    #[test]
+    fn test_from_image_and_noise() {
+        let image = Image {
+            width: 2,
+            height: 2,
+            pixels: vec![
+                Colour::gray(0.),
+                Colour::gray(1.),
+                Colour::gray(0.),
+                Colour::gray(1.),
+            ],
+        };
+        let heightfield =
+            Heightfield::from_image(&image, Vec3D::ZERO, Vec3D::ONE, Material::default()).unwrap();
+        assert_eq!(heightfield.height(1, 0), 1.);
+        assert_eq!(heightfield.centre(), Vec3D::new(0., 0.5, 0.));
+        let noise = NoiseTexture::new(
+            4,
+            NoisePattern::Fbm { octaves: 4 },
+            0.5,
+            (Colour::gray(0.), Colour::gray(1.)),
+        );
+        let terrain = Heightfield::from_noise(
+            &noise,
+            (33, 33),
+            Vec3D::ZERO,
+            Vec3D::new(10., 2., 10.),
+            Material::default(),
+        )
+        .unwrap();
+        let again = Heightfield::from_noise(
+            &noise,
+            (33, 33),
+            Vec3D::ZERO,
+            Vec3D::new(10., 2., 10.),
+            Material::default(),
+        )
+        .unwrap();
+        assert_eq!(terrain.heights, again.heights);
+        assert!(Arc::ptr_eq(&terrain.heights, &terrain.clone().heights));
+        assert!(terrain
+            .heights
+            .iter()
+            .all(|height| (0. ..=1.).contains(height)));
+        for x in [-4.5, -1., 2.3] {
+            let ray = Ray::new(Vec3D::new(x, 10., 1.7), -Vec3D::Y);
+            assert_eq!(terrain.intersections(&ray).len(), 1);
+        }
+    }
+}
@@ -1,8 +1,18 @@
-use std::fmt::Display;
+use std::{
+    ffi::OsStr,
+    fmt::Display,
+    fs::File,
+    hash::Hasher,
+    io::{self, BufReader, ErrorKind, Read, Write},
+    path::Path,
+};
 
-use crate::colour::Colour;
+use crate::{
+    checkpoint::{Fingerprint, Fingerprinter},
+    colour::Colour,
+};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Image {
     pub width: usize,
     pub height: usize,
@@ -17,6 +27,328 @@ impl Image {
             pixels: vec![fill; width * height],
         }
     }
+
+    pub fn pixel(&self, x: usize, y: usize) -> Colour {
+        self.pixels[y * self.width + x]
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let reader = BufReader::new(File::open(path)?);
+        match path
+            .extension()
+            .and_then(OsStr::to_str)
+            .map(str::to_ascii_lowercase)
+            .as_deref()
+        {
+            Some("png") => Self::read_png(reader),
+            Some("ppm" | "pnm") => Self::read_ppm(reader),
+            Some("hdr" | "pic") => Self::read_hdr(reader),
+            Some("pfm") => Self::read_pfm(reader),
+            _ => Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                format!("unsupported image format: {}", path.display()),
+            )),
+        }
+    }
+
+    pub fn read_ppm(mut reader: impl Read) -> io::Result<Self> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        let mut cursor = 0;
+        let magic = next_token(&bytes, &mut cursor)?;
+        let binary = match magic.as_str() {
+            "P6" => true,
+            "P3" => false,
+            _ => return Err(invalid_data(format!("unsupported PPM type {magic}"))),
+        };
+        let width: usize = parse_token(&bytes, &mut cursor)?;
+        let height: usize = parse_token(&bytes, &mut cursor)?;
+        let max_value: usize = parse_token(&bytes, &mut cursor)?;
+        if max_value == 0 || max_value > 65535 {
+            return Err(invalid_data(format!(
+                "invalid PPM maximum value {max_value}"
+            )));
+        }
+        let stride = if max_value > 255 { 2 } else { 1 };
+        let max_value = max_value as f32;
+        let samples = width
+            .checked_mul(height)
+            .and_then(|count| count.checked_mul(3))
+            .ok_or_else(|| invalid_data(format!("PPM size {width}x{height} is too large")))?;
+        let values = if binary {
+            let data = bytes.get(cursor + 1..).unwrap_or_default();
+            if data.len() / stride < samples {
+                return Err(invalid_data("truncated PPM data".to_string()));
+            }
+            data.chunks_exact(stride)
+                .take(samples)
+                .map(|sample| match sample {
+                    [high, low] => u16::from_be_bytes([*high, *low]) as f32 / max_value,
+                    [value] => *value as f32 / max_value,
+                    _ => unreachable!(),
+                })
+                .collect::<Vec<f32>>()
+        } else {
+            (0..samples)
+                .map(|_| Ok(parse_token::<usize>(&bytes, &mut cursor)? as f32 / max_value))
+                .collect::<io::Result<Vec<f32>>>()?
+        };
+        Ok(Self {
+            width,
+            height,
+            pixels: values
+                .chunks_exact(3)
+                .map(|rgb| Colour::new(rgb[0], rgb[1], rgb[2]))
+                .collect(),
+        })
+    }
+
+    pub fn read_hdr(mut reader: impl Read) -> io::Result<Self> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        let mut cursor = 0;
+        let magic = read_line(&bytes, &mut cursor)?;
+        if !magic.starts_with("#?") {
+            return Err(invalid_data("missing HDR signature".to_string()));
+        }
+        loop {
+            let line = read_line(&bytes, &mut cursor)?;
+            if line.is_empty() {
+                break;
+            }
+            if let Some(format) = line.strip_prefix("FORMAT=") {
+                if format != "32-bit_rle_rgbe" {
+                    return Err(invalid_data(format!("unsupported HDR format {format}")));
+                }
+            }
+        }
+        let resolution = read_line(&bytes, &mut cursor)?;
+        let (height, width) = match resolution.split_whitespace().collect::<Vec<_>>()[..] {
+            ["-Y", height, "+X", width] => (height, width),
+            _ => {
+                return Err(invalid_data(format!(
+                    "unsupported HDR orientation {resolution}"
+                )))
+            }
+        };
+        let parse = |value: &str| {
+            value
+                .parse::<usize>()
+                .map_err(|_| invalid_data(format!("invalid HDR resolution {resolution}")))
+        };
+        let (width, height) = (parse(width)?, parse(height)?);
+        if width == 0 || height == 0 {
+            return Err(invalid_data(format!("invalid HDR size {width}x{height}")));
+        }
+        let (count, row_length) = width
+            .checked_mul(height)
+            .zip(width.checked_mul(4))
+            .ok_or_else(|| invalid_data(format!("HDR size {width}x{height} is too large")))?;
+        // Run-length encoded files can hold more pixels than bytes, so this only bounds the
+        // reservation; a short file still fails on its first missing scanline.
+        let mut pixels = Vec::with_capacity(count.min(bytes.len().saturating_sub(cursor)));
+        for _ in 0..height {
+            let header = bytes.get(cursor..cursor + 4).unwrap_or_default();
+            let scanline = if (8..0x8000).contains(&width)
+                && header.len() == 4
+                && header[..2] == [2, 2]
+                && usize::from(header[2]) << 8 | usize::from(header[3]) == width
+            {
+                cursor += 4;
+                read_rle_scanline(&bytes, &mut cursor, width)?
+            } else {
+                let data = bytes
+                    .get(cursor..cursor + row_length)
+                    .ok_or_else(|| invalid_data("truncated HDR data".to_string()))?;
+                cursor += row_length;
+                data.chunks_exact(4)
+                    .map(|rgbe| [rgbe[0], rgbe[1], rgbe[2], rgbe[3]])
+                    .collect()
+            };
+            pixels.extend(scanline.into_iter().map(rgbe_to_colour));
+        }
+        Ok(Self {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    pub fn read_pfm(mut reader: impl Read) -> io::Result<Self> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        let mut cursor = 0;
+        let magic = next_token(&bytes, &mut cursor)?;
+        let channels = match magic.as_str() {
+            "PF" => 3,
+            "Pf" => 1,
+            _ => return Err(invalid_data(format!("unsupported PFM type {magic}"))),
+        };
+        let width: usize = parse_token(&bytes, &mut cursor)?;
+        let height: usize = parse_token(&bytes, &mut cursor)?;
+        let scale: f32 = parse_token(&bytes, &mut cursor)?;
+        if width == 0 || height == 0 {
+            return Err(invalid_data(format!("invalid PFM size {width}x{height}")));
+        }
+        let length = width
+            .checked_mul(height)
+            .and_then(|count| count.checked_mul(channels * 4))
+            .ok_or_else(|| invalid_data(format!("PFM size {width}x{height} is too large")))?;
+        let data = bytes.get(cursor + 1..).unwrap_or_default();
+        if data.len() < length {
+            return Err(invalid_data("truncated PFM data".to_string()));
+        }
+        let values = data
+            .chunks_exact(4)
+            .take(width * height * channels)
+            .map(|chunk| {
+                let chunk = chunk.try_into().unwrap();
+                if scale < 0. {
+                    f32::from_le_bytes(chunk)
+                } else {
+                    f32::from_be_bytes(chunk)
+                }
+            })
+            .collect::<Vec<f32>>();
+        let rows = values
+            .chunks_exact(width * channels)
+            .rev()
+            .flat_map(|row| {
+                row.chunks_exact(channels).map(|samples| match samples {
+                    [gray] => Colour::gray(*gray),
+                    [red, green, blue] => Colour::new(*red, *green, *blue),
+                    _ => unreachable!(),
+                })
+            })
+            .collect();
+        Ok(Self {
+            width,
+            height,
+            pixels: rows,
+        })
+    }
+
+    pub fn write_pfm(&self, mut writer: impl Write) -> io::Result<()> {
+        write!(writer, "PF\n{} {}\n-1.0\n", self.width, self.height)?;
+        for row in self.pixels.chunks_exact(self.width).rev() {
+            for pixel in row {
+                for value in pixel.as_rgb() {
+                    writer.write_all(&value.to_le_bytes())?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn read_png(reader: impl Read) -> io::Result<Self> {
+        let mut decoder = png::Decoder::new(reader);
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info().map_err(io::Error::other)?;
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buffer).map_err(io::Error::other)?;
+        let channels = info.color_type.samples();
+        Ok(Self {
+            width: info.width as usize,
+            height: info.height as usize,
+            pixels: buffer[..info.buffer_size()]
+                .chunks_exact(channels)
+                .map(|samples| {
+                    let [red, green, blue] = match samples {
+                        [gray] | [gray, _] => [*gray; 3],
+                        [red, green, blue, ..] => [*red, *green, *blue],
+                        [] => unreachable!(),
+                    }
+                    .map(|value| value as f32 / 255.);
+                    Colour::new(red, green, blue)
+                })
+                .collect(),
+        })
+    }
+}
+
+fn read_line(bytes: &[u8], cursor: &mut usize) -> io::Result<String> {
+    let start = *cursor;
+    let end = bytes[start..]
+        .iter()
+        .position(|byte| *byte == b'\n')
+        .ok_or_else(|| invalid_data("unexpected end of HDR header".to_string()))?;
+    *cursor = start + end + 1;
+    Ok(String::from_utf8_lossy(&bytes[start..start + end]).into_owned())
+}
+
+fn read_rle_scanline(bytes: &[u8], cursor: &mut usize, width: usize) -> io::Result<Vec<[u8; 4]>> {
+    let mut scanline = vec![[0; 4]; width];
+    let mut next = || {
+        let byte = bytes
+            .get(*cursor)
+            .copied()
+            .ok_or_else(|| invalid_data("truncated HDR data".to_string()));
+        *cursor += 1;
+        byte
+    };
+    for channel in 0..4 {
+        let mut x = 0;
+        while x < width {
+            let count = next()? as usize;
+            let (count, run) = if count > 128 {
+                (count - 128, true)
+            } else {
+                (count, false)
+            };
+            if count == 0 || x + count > width {
+                return Err(invalid_data("invalid HDR run length".to_string()));
+            }
+            let value = if run { next()? } else { 0 };
+            for pixel in &mut scanline[x..x + count] {
+                pixel[channel] = if run { value } else { next()? };
+            }
+            x += count;
+        }
+    }
+    Ok(scanline)
+}
+
+fn rgbe_to_colour([red, green, blue, exponent]: [u8; 4]) -> Colour {
+    if exponent == 0 {
+        return Colour::default();
+    }
+    let scale = 2_f32.powi(exponent as i32 - 136);
+    Colour::new(red as f32, green as f32, blue as f32) * scale
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message)
+}
+
+fn next_token(bytes: &[u8], cursor: &mut usize) -> io::Result<String> {
+    loop {
+        match bytes.get(*cursor) {
+            Some(b'#') => {
+                while bytes.get(*cursor).is_some_and(|byte| *byte != b'\n') {
+                    *cursor += 1;
+                }
+            }
+            Some(byte) if byte.is_ascii_whitespace() => *cursor += 1,
+            Some(_) => break,
+            None => return Err(invalid_data("unexpected end of PPM header".to_string())),
+        }
+    }
+    let start = *cursor;
+    while bytes
+        .get(*cursor)
+        .is_some_and(|byte| !byte.is_ascii_whitespace())
+    {
+        *cursor += 1;
+    }
+    Ok(String::from_utf8_lossy(&bytes[start..*cursor]).into_owned())
+}
+
+fn parse_token<T: std::str::FromStr>(bytes: &[u8], cursor: &mut usize) -> io::Result<T> {
+    let token = next_token(bytes, cursor)?;
+    token
+        .parse()
+        .map_err(|_| invalid_data(format!("invalid PPM value {token}")))
 }
 
 impl Display for Image {
@@ -61,3 +393,126 @@ impl<'a> Iterator for PixelIterator<'a> {
         }
     }
 }
+
+impl Fingerprint for Image {
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        state.write_usize(self.width);
+        state.write_usize(self.height);
+        state.floats(self.pixels.iter().flat_map(|pixel| pixel.as_rgb()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_binary_ppm() {
+        let mut bytes = b"P6\n# comment\n2 1\n255\n".to_vec();
+        bytes.extend([255, 0, 0, 0, 51, 255]);
+        let image = Image::read_ppm(bytes.as_slice()).unwrap();
+        assert_eq!((image.width, image.height), (2, 1));
+        assert_eq!(image.pixel(0, 0), Colour::new(1., 0., 0.));
+        assert_eq!(image.pixel(1, 0), Colour::new(0., 0.2, 1.));
+    }
+
+    #[test]
+    fn test_read_ascii_ppm() {
+        let image = Image::read_ppm("P3 1 2 10 10 5 0 0 0 10".as_bytes()).unwrap();
+        assert_eq!((image.width, image.height), (1, 2));
+        assert_eq!(image.pixel(0, 0), Colour::new(1., 0.5, 0.));
+        assert_eq!(image.pixel(0, 1), Colour::new(0., 0., 1.));
+    }
+
+    #[test]
+    fn test_read_truncated_ppm() {
+        assert!(Image::read_ppm(b"P6 2 2 255\n\0\0\0".as_slice()).is_err());
+        assert!(Image::read_ppm(b"P5 2 2 255\n".as_slice()).is_err());
+        let error = Image::read_ppm(b"P6 18446744073709551615 2 255\n".as_slice()).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_read_png() {
+        let mut bytes = Vec::new();
+        {
+            let mut encoder = png::Encoder::new(&mut bytes, 2, 1);
+            encoder.set_color(png::ColorType::Rgb);
+            encoder.set_depth(png::BitDepth::Eight);
+            let mut writer = encoder.write_header().unwrap();
+            writer.write_image_data(&[255, 0, 0, 0, 51, 255]).unwrap();
+        }
+        let image = Image::read_png(bytes.as_slice()).unwrap();
+        assert_eq!((image.width, image.height), (2, 1));
+        assert_eq!(image.pixel(0, 0), Colour::new(1., 0., 0.));
+        assert_eq!(image.pixel(1, 0), Colour::new(0., 0.2, 1.));
+    }
+
+    #[test]
+    fn test_read_hdr() {
+        let mut bytes = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 2 +X 1\n".to_vec();
+        bytes.extend([128, 64, 0, 129, 0, 0, 0, 0]);
+        let image = Image::read_hdr(bytes.as_slice()).unwrap();
+        assert_eq!((image.width, image.height), (1, 2));
+        assert_eq!(image.pixel(0, 0), Colour::new(1., 0.5, 0.));
+        assert_eq!(image.pixel(0, 1), Colour::default());
+        let width = 8;
+        let mut bytes = format!("#?RGBE\n\n-Y 1 +X {width}\n").into_bytes();
+        bytes.extend([2, 2, 0, width as u8]);
+        bytes.extend([128 + 8, 128]);
+        bytes.extend([4, 0, 32, 64, 96, 128 + 4, 255]);
+        bytes.extend([128 + 8, 0]);
+        bytes.extend([128 + 8, 130]);
+        let image = Image::read_hdr(bytes.as_slice()).unwrap();
+        assert_eq!(image.pixel(0, 0), Colour::new(2., 0., 0.));
+        assert_eq!(image.pixel(2, 0), Colour::new(2., 1., 0.));
+        assert_eq!(image.pixel(7, 0), Colour::new(2., 255. / 64., 0.));
+        assert!(Image::read_hdr(b"#?RADIANCE\n\n+Y 1 +X 1\n".as_slice()).is_err());
+    }
+
+    #[test]
+    fn test_rejects_bad_hdr_sizes() {
+        for header in [
+            "-Y 0 +X 4",
+            "-Y 4000000000 +X 4000000000",
+            "-Y 1 +X 4611686018427387904",
+            "-Y 18446744073709551615 +X 2",
+        ] {
+            let bytes = format!("#?RADIANCE\n\n{header}\n").into_bytes();
+            let error = Image::read_hdr(bytes.as_slice()).unwrap_err();
+            assert_eq!(error.kind(), ErrorKind::InvalidData, "{header}");
+        }
+    }
+
+    #[test]
+    fn test_read_pfm() {
+        let mut bytes = b"PF\n1 2\n-1.0\n".to_vec();
+        for value in [0.5_f32, 1., 2., 4., 8., 16.] {
+            bytes.extend(value.to_le_bytes());
+        }
+        let image = Image::read_pfm(bytes.as_slice()).unwrap();
+        assert_eq!((image.width, image.height), (1, 2));
+        assert_eq!(image.pixel(0, 0), Colour::new(4., 8., 16.));
+        assert_eq!(image.pixel(0, 1), Colour::new(0.5, 1., 2.));
+        let mut bytes = b"Pf 1 1 1.0\n".to_vec();
+        bytes.extend(3_f32.to_be_bytes());
+        assert_eq!(
+            Image::read_pfm(bytes.as_slice()).unwrap().pixel(0, 0),
+            Colour::gray(3.)
+        );
+        assert!(Image::read_pfm(b"PF 0 2 -1.0\n".as_slice()).is_err());
+        assert!(Image::read_pfm(b"PF 18446744073709551615 2 -1.0\n".as_slice()).is_err());
+    }
+
+    #[test]
+    fn test_write_pfm() {
+        let mut image = Image::new(2, 3, Colour::default());
+        image.pixels[1] = Colour::new(-1., 0.25, 1e6);
+        image.pixels[4] = Colour::gray(7.5);
+        let mut bytes = Vec::new();
+        image.write_pfm(&mut bytes).unwrap();
+        let read = Image::read_pfm(bytes.as_slice()).unwrap();
+        assert_eq!((read.width, read.height), (2, 3));
+        assert_eq!(read.pixels, image.pixels);
+    }
+}
@@ -0,0 +1,130 @@
+use std::sync::Arc;
+
+use crate::{
+    checkpoint::{Fingerprint, Fingerprinter},
+    material::Material,
+    physics::{Intersection, Object},
+    ray::Ray,
+    transform::Transform,
+    vector::Vec3D,
+};
+
+#[derive(Debug, Clone)]
+pub struct Instance {
+    pub object: Arc<dyn Object>,
+    pub transform: Transform,
+}
+
+impl Instance {
+    pub fn new(object: Arc<dyn Object>, transform: Transform) -> Self {
+        Self { object, transform }
+    }
+}
+
+impl Object for Instance {
+    fn intersections(&self, ray: &Ray) -> Vec<Intersection> {
+        let local_ray = self.transform.inverse().ray(ray);
+        let local_ray = Ray::new(local_ray.origin, local_ray.direction.normalise());
+        self.object
+            .intersections(&local_ray)
+            .into_iter()
+            .map(|intersection| {
+                let position = self.transform.point(intersection.position);
+                let normal = self.transform.normal(intersection.normal);
+                let tangent = self.transform.vector(intersection.tangent);
+                Intersection {
+                    position,
+                    distance: (position - ray.origin).length(),
+                    normal,
+                    tangent: (tangent - normal * tangent.dot(normal)).normalise(),
+                    ray: *ray,
+                    ..intersection
+                }
+            })
+            .collect()
+    }
+
+    fn extent(&self) -> f32 {
+        self.object.extent() * self.transform.maximum_scale()
+    }
+
+    fn centre(&self) -> Vec3D {
+        self.transform.point(self.object.centre())
+    }
+
+    fn material(&self) -> Material {
+        self.object.material()
+    }
+}
+
+impl Fingerprint for Instance {
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        self.object.fingerprint(state);
+        state.debug(&self.transform);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use super::*;
+    use crate::{cuboid::Cuboid, quaternion::Quaternion, sphere::Sphere};
+
+    #[test]
+    fn test_translated_instance() {
+        let sphere: Arc<dyn Object> = Arc::new(Sphere::unit(Material::default()));
+        let instance = Instance::new(sphere, Transform::translation(Vec3D::new(3., 0., 0.)));
+        let ray = Ray::new(Vec3D::new(3., 0., 5.), -Vec3D::Z);
+        let intersections = instance.intersections(&ray);
+        assert_eq!(intersections.len(), 2);
+        assert_eq!(intersections[0].position, Vec3D::new(3., 0., 1.));
+        assert_eq!(intersections[0].local_position, Vec3D::Z);
+        assert_eq!(intersections[0].normal, Vec3D::Z);
+        assert_eq!(intersections[0].distance, 4.);
+        assert_eq!(intersections[0].ray, ray);
+        assert_eq!(instance.centre(), Vec3D::new(3., 0., 0.));
+    }
+
+    #[test]
+    fn test_scaled_instance() {
+        let sphere: Arc<dyn Object> = Arc::new(Sphere::unit(Material::default()));
+        let instance = Instance::new(sphere, Transform::scale(Vec3D::new(2., 1., 1.)));
+        let ray = Ray::new(Vec3D::new(-5., 0., 0.), Vec3D::X);
+        let intersections = instance.intersections(&ray);
+        assert_eq!(intersections[0].position, Vec3D::new(-2., 0., 0.));
+        assert_eq!(intersections[0].distance, 3.);
+        assert_eq!(intersections[1].distance, 7.);
+        let diagonal = Vec3D::new(2., 1., 0.).normalise();
+        let intersections = instance.intersections(&Ray::new(diagonal * 10., -diagonal));
+        let expected = Vec3D::new(1., 2., 0.).normalise();
+        assert_eq!(intersections[0].normal, expected);
+        assert_eq!(intersections[0].tangent.dot(intersections[0].normal), 0.);
+        assert_eq!(instance.extent(), 4.);
+    }
+
+    #[test]
+    fn test_shared_object() {
+        let cuboid: Arc<dyn Object> = Arc::new(Cuboid::default());
+        let instances = (0..3)
+            .map(|i| {
+                Instance::new(
+                    cuboid.clone(),
+                    Transform::new(
+                        Vec3D::new(i as f32 * 4., 0., 0.),
+                        Quaternion::from_axis_angle(Vec3D::Y, PI / 4.),
+                        Vec3D::ONE,
+                    ),
+                )
+            })
+            .collect::<Vec<Instance>>();
+        assert_eq!(Arc::strong_count(&cuboid), 4);
+        for (i, instance) in instances.iter().enumerate() {
+            let ray = Ray::new(Vec3D::new(i as f32 * 4. + 0.5, 0., 5.), -Vec3D::Z);
+            let intersections = instance.intersections(&ray);
+            assert_eq!(intersections.len(), 2);
+            assert!((intersections[0].distance - (5.5 - 2_f32.sqrt())).abs() < 1e-5);
+            assert_eq!(intersections[0].normal, Vec3D::new(1., 0., 1.).normalise());
+        }
+    }
+}
@@ -0,0 +1,440 @@
+use crate::{
+    colour::Colour, constants::ENVIRONMENT_DISTANCE, medium::MediumEvent, microfacet::Bsdf,
+    physics::Intersection, ray::Ray, sampler::Sampler, scene::Scene, vector::Vec3D,
+};
+
+pub const MAX_BOUNCES: usize = 4;
+// The first two dimensions jitter the pixel. Every bounce then reads its fixed-size decisions from
+// its own block, so a low-discrepancy sampler stratifies the same decision at the same dimension
+// in every path, however many values earlier bounces consumed.
+pub const PIXEL_DIMENSIONS: usize = 2;
+const BOUNCE_DIMENSIONS: usize = 7;
+const BSDF_DIMENSION: usize = 0;
+const ENVIRONMENT_DIMENSION: usize = 2;
+const PHASE_DIMENSION: usize = 4;
+// Tracking through volumes takes an unbounded number of values, so each estimate gets a stream
+// far beyond the bounce blocks where it cannot run into the dimensions of another decision. The
+// first stream of a bounce samples the medium distance, then each light and the environment follow.
+const STREAM_DIMENSIONS: usize = 1 << 40;
+const STREAM_LENGTH: usize = 1 << 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct PathSample {
+    pub direct: Colour,
+    pub indirect: Colour,
+    pub depth: f32,
+    pub normal: Vec3D,
+    pub albedo: Colour,
+    pub position: Vec3D,
+    pub object_id: Option<usize>,
+}
+
+impl PathSample {
+    pub fn colour(&self) -> Colour {
+        self.direct + self.indirect
+    }
+
+    fn add(&mut self, vertices: usize, contribution: Colour) {
+        if vertices <= 1 {
+            self.direct = self.direct + contribution;
+        } else {
+            self.indirect = self.indirect + contribution;
+        }
+    }
+}
+
+fn start_decision(sampler: &mut dyn Sampler, bounce: usize, slot: usize) {
+    sampler.start_dimension(PIXEL_DIMENSIONS + bounce * BOUNCE_DIMENSIONS + slot);
+}
+
+fn start_stream(sampler: &mut dyn Sampler, bounce: usize, stream: usize) {
+    sampler.start_dimension(STREAM_DIMENSIONS + (bounce * STREAM_LENGTH + stream) * STREAM_LENGTH);
+}
+
+fn transmittance(
+    scene: &Scene,
+    from: Vec3D,
+    to: Vec3D,
+    (bounce, stream): (usize, usize),
+    sampler: &mut dyn Sampler,
+) -> Colour {
+    start_stream(sampler, bounce, stream + 1);
+    scene.transmittance(from, to, sampler)
+}
+
+pub fn radiance(scene: &Scene, ray: &Ray, sampler: &mut dyn Sampler) -> Colour {
+    trace(scene, ray, sampler).colour()
+}
+
+pub fn trace(scene: &Scene, ray: &Ray, sampler: &mut dyn Sampler) -> PathSample {
+    let mut ray = *ray;
+    let mut path = PathSample {
+        depth: ENVIRONMENT_DISTANCE,
+        ..Default::default()
+    };
+    let mut throughput = Colour::gray(1.);
+    let mut previous_pdf = None;
+    for bounce in 0..MAX_BOUNCES {
+        let hit = scene.intersect_indexed(&ray);
+        if let (0, Some((index, hit))) = (bounce, &hit) {
+            let material = hit.object.material();
+            path.depth = hit.distance;
+            path.normal = hit.normal;
+            path.albedo = material.bsdf(hit).base_colour;
+            path.position = hit.position;
+            path.object_id = Some(*index);
+        }
+        let hit = hit.map(|(_, hit)| hit);
+        let distance = hit.as_ref().map_or(f32::INFINITY, |hit| hit.distance);
+        start_stream(sampler, bounce, 0);
+        let (event, weight) = scene.sample_medium(&ray, distance, sampler);
+        throughput = throughput * weight;
+        let continues = bounce + 1 < MAX_BOUNCES;
+        if let Some(event) = event {
+            path.add(
+                bounce + 1,
+                throughput * medium_lighting(scene, &event, bounce, continues, sampler),
+            );
+            start_decision(sampler, bounce, PHASE_DIMENSION);
+            let Some((direction, weight, pdf)) = event.sample_direction(sampler) else {
+                break;
+            };
+            throughput = throughput * weight;
+            previous_pdf = Some(pdf);
+            ray = Ray::new(event.position, direction);
+            continue;
+        }
+        let Some(hit) = hit else {
+            if previous_pdf.is_none() {
+                path.add(bounce, throughput * sun_disks(scene, ray.direction));
+            }
+            let background = match &scene.environment {
+                Some(environment) => {
+                    environment.value(ray.direction)
+                        * previous_pdf.map_or(1., |pdf| {
+                            power_heuristic(pdf, environment.pdf(ray.direction))
+                        })
+                }
+                None => scene.background.value(ray.direction),
+            };
+            path.add(bounce, throughput * background);
+            break;
+        };
+        let material = hit.object.material();
+        let bsdf = material.bsdf(&hit);
+        let normal = material.shading_normal(&hit);
+        let to_viewer = -hit.ray.direction;
+        path.add(
+            bounce + 1,
+            throughput * direct_lighting(scene, &hit, &bsdf, normal, bounce, continues, sampler),
+        );
+        start_decision(sampler, bounce, BSDF_DIMENSION);
+        let Some(sample) = bsdf.sample(normal, to_viewer, sampler.next_pair()) else {
+            break;
+        };
+        if !hit.same_side_as_viewer(sample.direction) {
+            break;
+        }
+        throughput = throughput * sample.weight;
+        previous_pdf = Some(sample.pdf);
+        ray = hit.spawn_ray(sample.direction);
+    }
+    path
+}
+
+pub fn power_heuristic(pdf: f32, other: f32) -> f32 {
+    let (pdf, other) = (pdf * pdf, other * other);
+    if pdf + other <= 0. {
+        return 0.;
+    }
+    pdf / (pdf + other)
+}
+
+fn sun_disks(scene: &Scene, direction: Vec3D) -> Colour {
+    scene
+        .directional_lights
+        .iter()
+        .fold(Colour::default(), |total, light| {
+            total + light.radiance(direction)
+        })
+}
+
+pub fn direct_lighting(
+    scene: &Scene,
+    hit: &Intersection,
+    bsdf: &Bsdf,
+    normal: Vec3D,
+    bounce: usize,
+    continues: bool,
+    sampler: &mut dyn Sampler,
+) -> Colour {
+    let to_viewer = -hit.ray.direction;
+    let points = scene.lights.len();
+    let lights = scene
+        .lights
+        .iter()
+        .enumerate()
+        .filter(|(_, light)| hit.same_side_as_viewer(light.position - hit.position))
+        .fold(Colour::default(), |previous, (stream, light)| {
+            let origin = hit.spawn_ray(light.position - hit.position).origin;
+            let to_light = (light.position - hit.position).normalise();
+            previous
+                + bsdf.evaluate(normal, to_light, to_viewer)
+                    * transmittance(scene, origin, light.position, (bounce, stream), sampler)
+                    * (0_f32.max(normal.dot(to_light)) * light.intensity)
+        })
+        + scene
+            .directional_lights
+            .iter()
+            .enumerate()
+            .filter(|(_, light)| hit.same_side_as_viewer(light.direction))
+            .fold(Colour::default(), |previous, (stream, light)| {
+                let origin = hit.spawn_ray(light.direction).origin;
+                previous
+                    + bsdf.evaluate(normal, light.direction, to_viewer)
+                        * transmittance(
+                            scene,
+                            origin,
+                            origin + light.direction * ENVIRONMENT_DISTANCE,
+                            (bounce, points + stream),
+                            sampler,
+                        )
+                        * light.irradiance
+                        * 0_f32.max(normal.dot(light.direction))
+            });
+    let Some(environment) = &scene.environment else {
+        return lights;
+    };
+    start_decision(sampler, bounce, ENVIRONMENT_DIMENSION);
+    let Some(sample) = environment.sample(sampler.next_pair()) else {
+        return lights;
+    };
+    if !hit.same_side_as_viewer(sample.direction) {
+        return lights;
+    }
+    let weight = if continues {
+        power_heuristic(sample.pdf, bsdf.pdf(normal, sample.direction, to_viewer))
+    } else {
+        1.
+    };
+    let origin = hit.spawn_ray(sample.direction).origin;
+    let stream = points + scene.directional_lights.len();
+    lights
+        + bsdf.evaluate(normal, sample.direction, to_viewer)
+            * transmittance(
+                scene,
+                origin,
+                origin + sample.direction * ENVIRONMENT_DISTANCE,
+                (bounce, stream),
+                sampler,
+            )
+            * sample.radiance
+            * (0_f32.max(normal.dot(sample.direction)) * weight / sample.pdf)
+}
+
+pub fn medium_lighting(
+    scene: &Scene,
+    event: &MediumEvent,
+    bounce: usize,
+    continues: bool,
+    sampler: &mut dyn Sampler,
+) -> Colour {
+    let points = scene.lights.len();
+    let lights =
+        scene
+            .lights
+            .iter()
+            .enumerate()
+            .fold(Colour::default(), |previous, (stream, light)| {
+                let to_light = (light.position - event.position).normalise();
+                previous
+                    + event.in_scattering(to_light)
+                        * transmittance(
+                            scene,
+                            event.position,
+                            light.position,
+                            (bounce, stream),
+                            sampler,
+                        )
+                        * light.intensity
+            })
+            + scene.directional_lights.iter().enumerate().fold(
+                Colour::default(),
+                |previous, (stream, light)| {
+                    previous
+                        + event.in_scattering(light.direction)
+                            * transmittance(
+                                scene,
+                                event.position,
+                                event.position + light.direction * ENVIRONMENT_DISTANCE,
+                                (bounce, points + stream),
+                                sampler,
+                            )
+                            * light.irradiance
+                },
+            );
+    let Some(environment) = &scene.environment else {
+        return lights;
+    };
+    start_decision(sampler, bounce, ENVIRONMENT_DIMENSION);
+    let Some(sample) = environment.sample(sampler.next_pair()) else {
+        return lights;
+    };
+    let weight = if continues {
+        power_heuristic(sample.pdf, event.pdf(sample.direction))
+    } else {
+        1.
+    };
+    let stream = points + scene.directional_lights.len();
+    lights
+        + event.in_scattering(sample.direction)
+            * transmittance(
+                scene,
+                event.position,
+                event.position + sample.direction * ENVIRONMENT_DISTANCE,
+                (bounce, stream),
+                sampler,
+            )
+            * sample.radiance
+            * (weight / sample.pdf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        background::Background,
+        environment::Environment,
+        image::Image,
+        material::Material,
+        random::Rng,
+        sampler::{IndependentSampler, SobolSampler},
+        sphere::Sphere,
+    };
+
+    #[test]
+    fn test_power_heuristic() {
+        assert_eq!(power_heuristic(1., 1.), 0.5);
+        assert_eq!(power_heuristic(3., 1.), 0.9);
+        assert_eq!(power_heuristic(0., 0.), 0.);
+        assert!((power_heuristic(2., 5.) + power_heuristic(5., 2.) - 1.).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_bounces_see_background() {
+        let mut scene = Scene {
+            objects: vec![Box::new(Sphere::new(
+                Vec3D::new(0., 0., -5.),
+                1.,
+                Material::default(),
+            ))],
+            ..Default::default()
+        };
+        let ray = Ray::new(Vec3D::default(), -Vec3D::Z);
+        let mut rng = Rng::new(0, 0);
+        assert_eq!(radiance(&scene, &ray, &mut rng), Colour::default());
+        scene.background = Background::Solid(Colour::gray(1.));
+        let lit = (0..64).fold(Colour::default(), |total, _| {
+            total + radiance(&scene, &ray, &mut rng)
+        });
+        assert!(lit.luminance() > 0.);
+        let miss = Ray::new(Vec3D::default(), Vec3D::Z);
+        assert_eq!(radiance(&scene, &miss, &mut rng), Colour::gray(1.));
+    }
+
+    #[test]
+    fn test_path_sample_passes() {
+        let scene = Scene {
+            objects: vec![
+                Box::new(Sphere::new(Vec3D::new(5., 0., 0.), 1., Material::default())),
+                Box::new(Sphere::new(
+                    Vec3D::new(0., 0., -5.),
+                    1.,
+                    Material {
+                        base_colour: Colour::new(0.5, 0.2, 0.1).into(),
+                        ..Default::default()
+                    },
+                )),
+            ],
+            background: Background::Solid(Colour::gray(1.)),
+            ..Default::default()
+        };
+        let mut rng = Rng::new(0, 0);
+        let path = trace(&scene, &Ray::new(Vec3D::default(), -Vec3D::Z), &mut rng);
+        assert_eq!(path.object_id, Some(1));
+        assert!((path.depth - 4.).abs() < 1e-4);
+        assert_eq!(path.normal, Vec3D::Z);
+        assert_eq!(path.position, Vec3D::new(0., 0., -4.));
+        assert_eq!(path.albedo, Colour::new(0.5, 0.2, 0.1));
+        assert_eq!(path.colour(), path.direct + path.indirect);
+        let miss = trace(&scene, &Ray::new(Vec3D::default(), Vec3D::Y), &mut rng);
+        assert_eq!(miss.object_id, None);
+        assert_eq!(miss.direct, Colour::gray(1.));
+        assert_eq!(miss.indirect, Colour::default());
+    }
+
+    #[test]
+    fn test_unweighted_environment_matches_mis() {
+        let mut image = Image::new(8, 4, Colour::default());
+        for (x, y, pixel) in &mut image {
+            *pixel = Colour::gray(0.2 + (x + 2 * y) as f32 * 0.1);
+        }
+        let scene = Scene {
+            objects: vec![Box::new(Sphere::new(
+                Vec3D::new(0., 0., -5.),
+                1.,
+                Material::default(),
+            ))],
+            environment: Some(Environment::new(image, 0., 1.).unwrap()),
+            ..Default::default()
+        };
+        let ray = Ray::new(Vec3D::default(), -Vec3D::Z);
+        let hit = scene.intersect(&ray).unwrap();
+        let material = hit.object.material();
+        let (bsdf, normal) = (material.bsdf(&hit), material.shading_normal(&hit));
+        let mut rng = Rng::new(5, 0);
+        let count = 20000;
+        let (mut unweighted, mut traced) = (0., 0.);
+        for _ in 0..count {
+            unweighted +=
+                direct_lighting(&scene, &hit, &bsdf, normal, 0, false, &mut rng).luminance();
+            traced += trace(&scene, &ray, &mut rng).colour().luminance();
+        }
+        assert!(
+            (unweighted - traced).abs() < 0.03 * traced,
+            "{unweighted} {traced}"
+        );
+    }
+
+    #[test]
+    fn test_sampler_drives_bounces() {
+        let scene = Scene {
+            objects: vec![Box::new(Sphere::new(
+                Vec3D::new(0., 0., -5.),
+                1.,
+                Material::default(),
+            ))],
+            background: Background::Gradient {
+                bottom: Colour::default(),
+                top: Colour::gray(1.),
+            },
+            ..Default::default()
+        };
+        let ray = Ray::new(Vec3D::default(), -Vec3D::Z);
+        let estimate = |sampler: &mut dyn Sampler, pixel, count| {
+            (0..count).fold(0., |total, index| {
+                sampler.start_sample(pixel, index);
+                total + trace(&scene, &ray, sampler).colour().luminance()
+            }) / count as f32
+        };
+        let reference = estimate(&mut IndependentSampler::new(1), (0, 0), 1 << 16);
+        let error = |sampler: &mut dyn Sampler| {
+            (0..64).fold(0., |total, x| {
+                total + (estimate(sampler, (x, 0), 64) - reference).powi(2)
+            })
+        };
+        let independent = error(&mut IndependentSampler::new(0));
+        let sobol = error(&mut SobolSampler::new(0));
+        assert!(sobol < 0.5 * independent, "{sobol} {independent}");
+    }
+}
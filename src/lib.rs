@@ -0,0 +1,46 @@
+#![feature(ptr_metadata)]
+pub mod aov;
+pub mod background;
+pub mod camera;
+pub mod checkpoint;
+pub mod colour;
+pub mod cone;
+pub mod constants;
+pub mod csg;
+pub mod cuboid;
+pub mod cylinder;
+pub mod denoise;
+pub mod disk;
+pub mod distribution;
+pub mod environment;
+pub mod film;
+pub mod filter;
+pub mod heightfield;
+pub mod image;
+pub mod instance;
+pub mod integrator;
+pub mod lighting;
+pub mod material;
+pub mod math;
+pub mod matrix;
+pub mod medium;
+pub mod microfacet;
+pub mod noise;
+pub mod openexr;
+pub mod physics;
+pub mod quaternion;
+pub mod random;
+pub mod ray;
+pub mod renderer;
+pub mod sampler;
+pub mod scene;
+pub mod scene_graph;
+pub mod sdf;
+pub mod shading;
+pub mod sky;
+pub mod sphere;
+pub mod texture;
+pub mod torus;
+pub mod transform;
+pub mod vector;
+pub mod voxel;
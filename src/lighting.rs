@@ -1,5 +1,8 @@
-use crate::vector::Vec3D;
+use std::f32::consts::PI;
 
+use crate::{colour::Colour, vector::Vec3D};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct PointLight {
     pub position: Vec3D,
     pub intensity: f32,
@@ -13,3 +16,33 @@ impl PointLight {
         }
     }
 }
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DirectionalLight {
+    pub direction: Vec3D,
+    pub irradiance: Colour,
+    pub angular_radius: f32,
+}
+
+impl DirectionalLight {
+    pub fn new(direction: Vec3D, irradiance: Colour, angular_radius: f32) -> Self {
+        Self {
+            direction: direction.normalise(),
+            irradiance,
+            angular_radius,
+        }
+    }
+
+    pub fn solid_angle(&self) -> f32 {
+        2. * PI * (1. - self.angular_radius.cos())
+    }
+
+    pub fn radiance(&self, direction: Vec3D) -> Colour {
+        if self.angular_radius <= 0.
+            || direction.normalise().dot(self.direction) < self.angular_radius.cos()
+        {
+            return Colour::default();
+        }
+        self.irradiance * (1. / self.solid_angle())
+    }
+}
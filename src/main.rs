@@ -1,39 +1,59 @@
-#![feature(ptr_metadata)]
-use std::{f32::consts::PI, fs::File, io::Write, process::Command};
+use std::{
+    f32::consts::PI,
+    fs::{self, File},
+    io::{ErrorKind, Write},
+    process::Command,
+};
 
-use log::trace;
+use log::{error, trace};
 use simple_logger::SimpleLogger;
-
-mod camera;
-mod colour;
-mod constants;
-mod image;
-mod lighting;
-mod material;
-mod math;
-mod physics;
-mod quaternion;
-mod ray;
-mod sphere;
-mod vector;
-
-use colour::Colour;
-
-use crate::{
-    camera::Camera, image::Image, lighting::PointLight, material::Material, physics::Object,
-    quaternion::Quaternion, sphere::Sphere, vector::Vec3D,
+use tiny_raytracer_rs::{
+    aov::Aov,
+    background::Background,
+    camera::Camera,
+    checkpoint::Checkpoint,
+    colour::Colour,
+    denoise::{Denoiser, Guides},
+    filter::Filter,
+    image::Image,
+    lighting::PointLight,
+    material::Material,
+    openexr::{write_layers, Channels, Compression, Layer, SampleFormat},
+    quaternion::Quaternion,
+    renderer::{
+        AdaptiveSampling, Progressive, RenderSettings, Renderer, SnapshotInterval, StopReason,
+    },
+    sampler::SobolSampler,
+    scene::Scene,
+    sphere::Sphere,
+    vector::Vec3D,
 };
 
 const IMAGE_SIZE: (usize, usize) = (1024, 768);
 const IMAGE_NAME: &str = "out.ppm";
 const PIXEL_TO_WORLD: f32 = 0.008;
+const HEATMAP_NAME: &str = "samples.ppm";
+const CHECKPOINT_NAME: &str = "render.checkpoint";
+const EXR_NAME: &str = "out.exr";
+const DENOISED_NAME: &str = "denoised.ppm";
+const SEED: u64 = 0;
+const TILE_SIZE: usize = 32;
+const PROGRESSIVE: Progressive = Progressive {
+    snapshot: SnapshotInterval::Passes(4),
+    time_limit: None,
+    pass_limit: None,
+    noise_target: None,
+};
+const FILTER: Filter = Filter::Mitchell {
+    radius: 2.,
+    b: 1. / 3.,
+    c: 1. / 3.,
+};
 
 fn main() {
     SimpleLogger::new().with_colors(true).init().unwrap();
 
-    trace!("making an image");
     let (width, height) = IMAGE_SIZE;
-    let mut image = Image::new(width, height, Colour::new(0.2, 0.7, 0.8));
 
     trace!("creating objects");
     let camera = Camera::new(
@@ -42,79 +62,152 @@ fn main() {
         5.6,
     );
     let ivory = Material {
-        albedo: 0.3..0.6,
-        diffuse_colour: Colour::new(0.4, 0.4, 0.3),
-        specular_exponent: 50.,
+        base_colour: Colour::new(0.8, 0.8, 0.6).into(),
+        metallic: 0.,
+        roughness: Colour::gray(0.3).into(),
+        ..Default::default()
     };
     let red_rubber = Material {
-        albedo: 0.1..0.9,
-        diffuse_colour: Colour::new(0.3, 0.1, 0.1),
-        specular_exponent: 10.,
+        base_colour: Colour::new(0.6, 0.2, 0.2).into(),
+        metallic: 0.,
+        roughness: Colour::gray(0.7).into(),
+        ..Default::default()
+    };
+    let scene = Scene {
+        objects: vec![
+            Box::new(Sphere::new(Vec3D::new(-3., 0., -16.), 2., ivory.clone())),
+            Box::new(Sphere::new(
+                Vec3D::new(-1.0, -1.5, -12.),
+                2.,
+                red_rubber.clone(),
+            )),
+            Box::new(Sphere::new(
+                Vec3D::new(1.5, -0.5, -18.),
+                3.,
+                red_rubber.clone(),
+            )),
+            Box::new(Sphere::new(Vec3D::new(7., 5., -18.), 4., ivory.clone())),
+        ],
+        lights: vec![
+            PointLight::new(Vec3D::new(-20., 20., 20.), 1.5),
+            PointLight::new(Vec3D::new(30., -50., -25.), 1.8),
+            PointLight::new(Vec3D::new(30., -20., 30.), 1.7),
+        ],
+        background: Background::Solid(Colour::new(0.2, 0.7, 0.8)),
+        ..Default::default()
     };
-    let objects = [
-        Sphere::new(Vec3D::new(-3., 0., -16.), 2., ivory.clone()),
-        Sphere::new(Vec3D::new(-1.0, -1.5, -12.), 2., red_rubber.clone()),
-        Sphere::new(Vec3D::new(1.5, -0.5, -18.), 3., red_rubber.clone()),
-        Sphere::new(Vec3D::new(7., 5., -18.), 4., ivory.clone()),
-    ];
-    let lights = [
-        PointLight::new(Vec3D::new(-20., 20., 20.), 1.5),
-        PointLight::new(Vec3D::new(30., -50., -25.), 1.8),
-        PointLight::new(Vec3D::new(30., -20., 30.), 1.7),
-    ];
 
     trace!("rendering");
-    for (x, y, pixel) in &mut image {
-        let ray = camera.ray_from_position(
-            (x as i32 - width as i32 / 2) as f32 * PIXEL_TO_WORLD,
-            (y as i32 - height as i32 / 2) as f32 * PIXEL_TO_WORLD,
-        );
-        let mut intersections = objects.iter().fold(Vec::new(), |previous, object| {
-            let mut previous = previous;
-            previous.extend(object.intersections(&ray));
-            previous
-        });
-        intersections.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap());
-        if !intersections.is_empty() {
-            *pixel = {
-                let first_hit = intersections.first().unwrap();
-                let material = first_hit.object.material();
-                let (diffuse_light_intensity, specular_light_intensity) =
-                    lights.iter().fold((0., 0.), |previous, light| {
-                        let hit_to_light = (first_hit.position - light.position).normalise();
-                        (
-                            previous.0
-                                + 0_f32.max(hit_to_light.dot(first_hit.normal.normalise()))
-                                    * light.intensity,
-                            previous.1
-                                + 0_f32
-                                    .max(
-                                        hit_to_light
-                                            .reflect(first_hit.normal.cross(hit_to_light).cross(first_hit.normal))
-                                            .normalise()
-                                            .dot(-first_hit.ray.direction.normalise()),
-                                    )
-                                    .powf(material.specular_exponent)
-                                    * light.intensity,
-                        )
-                    });
-                (material.diffuse_colour * diffuse_light_intensity * material.albedo.end)
-                    + (Colour::from_hex(0xffffff)
-                        * specular_light_intensity
-                        * material.albedo.start)
+    let settings = RenderSettings {
+        width,
+        height,
+        pixel_to_world: PIXEL_TO_WORLD,
+        filter: FILTER,
+        tile_size: TILE_SIZE,
+        adaptive: AdaptiveSampling::default(),
+        aovs: &Aov::ALL,
+    };
+    let sampler = SobolSampler::new(SEED);
+    let mut renderer = match Checkpoint::load(CHECKPOINT_NAME) {
+        Ok(checkpoint) => match Renderer::resume(settings, &scene, &camera, &sampler, checkpoint) {
+            Ok(renderer) => {
+                trace!("resuming from {CHECKPOINT_NAME}");
+                renderer
+            }
+            Err(error) => {
+                error!("cannot resume from {CHECKPOINT_NAME}: {error}");
+                return;
             }
+        },
+        Err(error) if error.kind() == ErrorKind::NotFound => Renderer::new(settings),
+        Err(error) => {
+            error!("cannot read {CHECKPOINT_NAME}: {error}");
+            return;
+        }
+    };
+    let reason = renderer.render_progressive(
+        &scene,
+        &camera,
+        &mut sampler.clone(),
+        PROGRESSIVE,
+        |renderer, pass| {
+            trace!("writing snapshot after pass {pass}");
+            write_image(&mut renderer.image(), IMAGE_NAME);
+            if let Err(error) = renderer
+                .checkpoint(&scene, &camera, &sampler)
+                .save(CHECKPOINT_NAME)
+            {
+                error!("cannot write {CHECKPOINT_NAME}: {error}");
+            }
+        },
+    );
+    trace!("stopped: {reason:?}");
+    // A time limit only pauses the render; every other reason means it is finished.
+    if reason != StopReason::TimeLimit {
+        match fs::remove_file(CHECKPOINT_NAME) {
+            Err(error) if error.kind() != ErrorKind::NotFound => {
+                error!("cannot remove {CHECKPOINT_NAME}: {error}");
+            }
+            _ => {}
         }
     }
 
     trace!("writing to file");
-    write_image(&mut image);
+    write_image(&mut renderer.image(), IMAGE_NAME);
+    write_image(&mut renderer.sample_heatmap(), HEATMAP_NAME);
+    let beauty = renderer.image();
+    let aovs = renderer.aov_images();
+    let guide = |aov: Aov| {
+        aovs.iter()
+            .find(|(other, _)| *other == aov)
+            .map(|(_, image)| image)
+    };
+    let denoised = guide(Aov::Normal)
+        .zip(guide(Aov::Albedo))
+        .zip(guide(Aov::Depth))
+        .and_then(|((normal, albedo), depth)| {
+            Denoiser::default()
+                .denoise(
+                    &beauty,
+                    Guides {
+                        normal,
+                        albedo,
+                        depth,
+                    },
+                )
+                .inspect_err(|error| error!("cannot denoise: {error}"))
+                .ok()
+        });
+    if let Some(mut denoised) = denoised.clone() {
+        write_image(&mut denoised, DENOISED_NAME);
+    }
+    let layers = [Layer::new("", &beauty, SampleFormat::Half)]
+        .into_iter()
+        .chain(
+            denoised
+                .iter()
+                .map(|image| Layer::new("denoised", image, SampleFormat::Half)),
+        )
+        .chain(aovs.iter().map(|(aov, image)| match aov {
+            Aov::Depth => Layer {
+                channels: Channels::Z,
+                ..Layer::new(aov.name(), image, SampleFormat::Float)
+            },
+            Aov::Position => Layer::new(aov.name(), image, SampleFormat::Float),
+            _ => Layer::new(aov.name(), image, SampleFormat::Half),
+        }))
+        .collect::<Vec<Layer>>();
+    if let Err(error) = write_layers(EXR_NAME, &layers, Compression::Zip) {
+        error!("cannot write {EXR_NAME}: {error}");
+    }
 
     trace!("opening");
+    #[allow(clippy::zombie_processes)] // the viewer outlives the process; there is nothing to reap
     Command::new("open").arg(IMAGE_NAME).spawn().unwrap();
 }
 
-fn write_image(image: &mut Image) {
-    let mut image_file = File::create(IMAGE_NAME).unwrap();
+fn write_image(image: &mut Image, name: &str) {
+    let mut image_file = File::create(name).unwrap();
     writeln!(image_file, "P6 {} {} 255", image.width, image.height).unwrap();
     image_file
         .write_all(
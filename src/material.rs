@@ -1,10 +1,234 @@
-use std::ops::Range;
+use std::sync::Arc;
 
-use crate::colour::Colour;
+use crate::{
+    checkpoint::{Fingerprint, Fingerprinter},
+    colour::Colour,
+    microfacet::Bsdf,
+    physics::Intersection,
+    shading::ShadingModel,
+    texture::Texture,
+    vector::Vec3D,
+};
 
-#[derive(Debug, Clone, PartialEq, Default)]
+const BUMP_DELTA: f32 = 0.001;
+
+#[derive(Debug, Clone)]
+pub struct BumpMap {
+    pub height: Arc<dyn Texture>,
+    pub strength: f32,
+}
+
+#[derive(Debug, Clone)]
 pub struct Material {
-    pub diffuse_colour: Colour,
-    pub specular_exponent: f32,
-    pub albedo: Range<f32>
+    pub base_colour: Arc<dyn Texture>,
+    pub metallic: f32,
+    pub roughness: Arc<dyn Texture>,
+    pub normal_map: Option<Arc<dyn Texture>>,
+    pub bump_map: Option<BumpMap>,
+    pub shading_model: Option<ShadingModel>,
+}
+
+impl Material {
+    pub fn bsdf(&self, hit: &Intersection) -> Bsdf {
+        Bsdf {
+            base_colour: self.base_colour.value(hit.uv, hit.local_position),
+            metallic: self.metallic.clamp(0., 1.),
+            roughness: self
+                .roughness
+                .value(hit.uv, hit.local_position)
+                .luminance()
+                .clamp(0., 1.),
+            shading_model: self.shading_model,
+        }
+    }
+
+    pub fn shading_normal(&self, hit: &Intersection) -> Vec3D {
+        let mut normal = hit.normal;
+        let mut tangent = hit.tangent;
+        if let Some(normal_map) = &self.normal_map {
+            let encoded = normal_map.value(hit.uv, hit.local_position);
+            let bitangent = normal.cross(tangent);
+            let perturbed = (tangent * (2. * encoded.red - 1.)
+                + bitangent * (2. * encoded.green - 1.)
+                + normal * (2. * encoded.blue - 1.))
+                .normalise();
+            if perturbed.dot(normal) > 0. {
+                tangent = (tangent - perturbed * tangent.dot(perturbed)).normalise();
+                normal = perturbed;
+            }
+        }
+        if let Some(bump_map) = &self.bump_map {
+            let (u, v) = hit.uv;
+            let bitangent = normal.cross(tangent);
+            // Step along the surface in both parametrisations, so that image heights follow the
+            // uv and solid textures such as noise follow the position.
+            let height = |uv, position| bump_map.height.value(uv, position).luminance();
+            let centre = height((u, v), hit.local_position);
+            let slope_u = (height(
+                (u + BUMP_DELTA, v),
+                hit.local_position + tangent * BUMP_DELTA,
+            ) - centre)
+                / BUMP_DELTA;
+            let slope_v = (height(
+                (u, v + BUMP_DELTA),
+                hit.local_position + bitangent * BUMP_DELTA,
+            ) - centre)
+                / BUMP_DELTA;
+            normal = (normal - (tangent * slope_u + bitangent * slope_v) * bump_map.strength)
+                .normalise();
+        }
+        normal
+    }
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Self {
+            base_colour: Colour::gray(0.8).into(),
+            metallic: 0.,
+            roughness: Colour::gray(0.5).into(),
+            normal_map: None,
+            bump_map: None,
+            shading_model: None,
+        }
+    }
+}
+
+impl Fingerprint for BumpMap {
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        self.height.fingerprint(state);
+        state.floats([self.strength]);
+    }
+}
+
+impl Fingerprint for Material {
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        self.base_colour.fingerprint(state);
+        state.floats([self.metallic]);
+        self.roughness.fingerprint(state);
+        self.normal_map.fingerprint(state);
+        self.bump_map.fingerprint(state);
+        state.debug(&self.shading_model);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        noise::{NoisePattern, NoiseTexture},
+        physics::Object,
+        ray::Ray,
+        sphere::Sphere,
+        texture::ProceduralTexture,
+    };
+
+    fn front_hit() -> Intersection {
+        let sphere = Sphere::default();
+        let ray = Ray::new(Vec3D::new(0., 0., 5.), Vec3D::new(0., 0., -1.));
+        sphere.intersections(&ray).remove(0)
+    }
+
+    #[test]
+    fn test_shading_model_reaches_bsdf() {
+        let hit = front_hit();
+        assert_eq!(Material::default().bsdf(&hit).shading_model, None);
+        let material = Material {
+            shading_model: Some(ShadingModel::Phong),
+            ..Default::default()
+        };
+        assert_eq!(material.bsdf(&hit).shading_model, Some(ShadingModel::Phong));
+    }
+
+    #[test]
+    fn test_unperturbed_normal() {
+        let hit = front_hit();
+        assert_eq!(Material::default().shading_normal(&hit), hit.normal);
+    }
+
+    #[test]
+    fn test_flat_normal_map() {
+        let hit = front_hit();
+        let material = Material {
+            normal_map: Some(Colour::new(0.5, 0.5, 1.).into()),
+            ..Default::default()
+        };
+        assert_eq!(material.shading_normal(&hit), hit.normal);
+    }
+
+    #[test]
+    fn test_tilted_normal_map() {
+        let hit = front_hit();
+        let material = Material {
+            normal_map: Some(Colour::new(1., 0.5, 1.).into()),
+            ..Default::default()
+        };
+        let expected = (hit.normal + hit.tangent).normalise();
+        assert_eq!(material.shading_normal(&hit), expected);
+        let material = Material {
+            normal_map: Some(Colour::new(0.5, 1., 1.).into()),
+            ..Default::default()
+        };
+        let expected = (hit.normal + hit.normal.cross(hit.tangent)).normalise();
+        assert_eq!(material.shading_normal(&hit), expected);
+    }
+
+    #[test]
+    fn test_constant_bump_map() {
+        let hit = front_hit();
+        let material = Material {
+            bump_map: Some(BumpMap {
+                height: Colour::gray(0.7).into(),
+                strength: 3.,
+            }),
+            ..Default::default()
+        };
+        assert_eq!(material.shading_normal(&hit), hit.normal);
+    }
+
+    #[test]
+    fn test_sloped_bump_map() {
+        let hit = front_hit();
+        let material = Material {
+            bump_map: Some(BumpMap {
+                height: Arc::new(ProceduralTexture(|(u, _), _| Colour::gray(u))),
+                strength: 1.,
+            }),
+            ..Default::default()
+        };
+        let normal = material.shading_normal(&hit);
+        assert!((normal - (hit.normal - hit.tangent).normalise()).length() < 1e-2);
+        assert_eq!(hit.normal, Vec3D::Z);
+    }
+
+    #[test]
+    fn test_solid_bump_map() {
+        let hit = front_hit();
+        let material = Material {
+            bump_map: Some(BumpMap {
+                height: Arc::new(ProceduralTexture(|_, position: Vec3D| {
+                    Colour::gray(position.x)
+                })),
+                strength: 1.,
+            }),
+            ..Default::default()
+        };
+        let bitangent = hit.normal.cross(hit.tangent);
+        let expected =
+            (hit.normal - hit.tangent * hit.tangent.x - bitangent * bitangent.x).normalise();
+        assert!((material.shading_normal(&hit) - expected).length() < 1e-2);
+        let noise = Material {
+            bump_map: Some(BumpMap {
+                height: Arc::new(NoiseTexture::new(
+                    3,
+                    NoisePattern::Fbm { octaves: 4 },
+                    4.,
+                    (Colour::gray(0.), Colour::gray(1.)),
+                )),
+                strength: 5.,
+            }),
+            ..Default::default()
+        };
+        assert!((noise.shading_normal(&hit) - hit.normal).length() > 0.01);
+    }
 }
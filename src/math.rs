@@ -1,4 +1,7 @@
-use std::ops::{Add, Div, Mul, Range, Sub};
+use std::{
+    f64::consts::PI,
+    ops::{Add, Div, Mul, Range, Sub},
+};
 
 pub fn map_range<T>(value: T, from: Range<T>, to: Range<T>) -> T
 where
@@ -6,3 +9,186 @@ where
 {
     to.start + (value - from.start) * (to.end - to.start) / (from.end - from.start)
 }
+
+pub fn solve_quadratic(a: f32, b: f32, c: f32) -> Vec<f32> {
+    quadratic_roots(a.into(), b.into(), c.into())
+        .into_iter()
+        .map(|root| root as f32)
+        .collect()
+}
+
+pub fn solve_cubic(a: f64, b: f64, c: f64, d: f64) -> Vec<f64> {
+    if a == 0. {
+        return quadratic_roots(b, c, d);
+    }
+    let (b, c, d) = (b / a, c / a, d / a);
+    let q = (b * b - 3. * c) / 9.;
+    let r = (2. * b * b * b - 9. * b * c + 27. * d) / 54.;
+    let shift = b / 3.;
+    let mut roots = if r * r < q * q * q {
+        let theta = (r / (q * q * q).sqrt()).clamp(-1., 1.).acos();
+        let scale = -2. * q.sqrt();
+        (0..3)
+            .map(|k| scale * ((theta + 2. * PI * k as f64) / 3.).cos() - shift)
+            .collect::<Vec<f64>>()
+    } else {
+        let first = -(r.abs() + (r * r - q * q * q).sqrt()).cbrt().copysign(r);
+        let second = if first == 0. { 0. } else { q / first };
+        let mut roots = vec![first + second - shift];
+        if first == second && first != 0. {
+            roots.push(-first - shift);
+        }
+        roots
+    };
+    roots.iter_mut().for_each(|root| {
+        *root = polish(&[1., b, c, d], *root);
+    });
+    sort_roots(roots)
+}
+
+pub fn solve_quartic(a: f64, b: f64, c: f64, d: f64, e: f64) -> Vec<f64> {
+    if a == 0. {
+        return solve_cubic(b, c, d, e);
+    }
+    let (b, c, d, e) = (b / a, c / a, d / a, e / a);
+    let shift = b / 4.;
+    let p = c - 3. * b * b / 8.;
+    let q = d - b * c / 2. + b * b * b / 8.;
+    let r = e - b * d / 4. + b * b * c / 16. - 3. * b * b * b * b / 256.;
+    let depressed = if q.abs() < 1e-12 * (1. + p.abs() + r.abs()) {
+        quadratic_roots(1., p, r)
+            .into_iter()
+            .filter(|square| *square >= 0.)
+            .flat_map(|square| [-square.sqrt(), square.sqrt()])
+            .collect::<Vec<f64>>()
+    } else {
+        let resolvent = solve_cubic(1., p, p * p / 4. - r, -q * q / 8.)
+            .into_iter()
+            .fold(f64::NEG_INFINITY, f64::max);
+        if resolvent <= 0. {
+            return Vec::new();
+        }
+        let slope = (2. * resolvent).sqrt();
+        let offset = p / 2. + resolvent;
+        let mut roots = quadratic_roots(1., slope, offset - q / (2. * slope));
+        roots.extend(quadratic_roots(1., -slope, offset + q / (2. * slope)));
+        roots
+    };
+    sort_roots(
+        depressed
+            .into_iter()
+            .map(|root| polish(&[1., b, c, d, e], root - shift))
+            .collect(),
+    )
+}
+
+fn quadratic_roots(a: f64, b: f64, c: f64) -> Vec<f64> {
+    if a == 0. {
+        return if b == 0. { Vec::new() } else { vec![-c / b] };
+    }
+    let discriminant = b * b - 4. * a * c;
+    if discriminant < 0. {
+        return Vec::new();
+    }
+    if discriminant == 0. {
+        return vec![-b / (2. * a)];
+    }
+    let q = -0.5 * (b + discriminant.sqrt().copysign(b));
+    let (first, second) = (q / a, c / q);
+    vec![first.min(second), first.max(second)]
+}
+
+fn polish(coefficients: &[f64], root: f64) -> f64 {
+    let mut root = root;
+    for _ in 0..4 {
+        let (value, derivative) =
+            coefficients
+                .iter()
+                .fold((0., 0.), |(value, derivative), coefficient| {
+                    (value * root + coefficient, derivative * root + value)
+                });
+        if derivative == 0. || !(value / derivative).is_finite() {
+            break;
+        }
+        let next = root - value / derivative;
+        if (next - root).abs() > 1e-3 * (1. + root.abs()) {
+            break;
+        }
+        root = next;
+    }
+    root
+}
+
+fn sort_roots(mut roots: Vec<f64>) -> Vec<f64> {
+    roots.retain(|root| root.is_finite());
+    roots.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap());
+    roots.dedup_by(|a, b| (*a - *b).abs() <= 1e-9 * (1. + b.abs()));
+    roots
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_roots(actual: Vec<f64>, expected: &[f64]) {
+        assert_eq!(actual.len(), expected.len(), "{actual:?} != {expected:?}");
+        for (actual_root, expected_root) in actual.iter().zip(expected) {
+            assert!(
+                (actual_root - expected_root).abs() < 1e-6,
+                "{actual:?} != {expected:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_map_range() {
+        assert_eq!(map_range(5., 0. ..10., 0. ..1.), 0.5);
+        assert_eq!(map_range(2., 1. ..3., 10. ..20.), 15.);
+    }
+
+    #[test]
+    fn test_solve_quadratic() {
+        assert_eq!(solve_quadratic(1., -3., 2.), vec![1., 2.]);
+        assert_eq!(solve_quadratic(1., -2., 1.), vec![1.]);
+        assert_eq!(solve_quadratic(1., 0., 1.), Vec::<f32>::new());
+        assert_eq!(solve_quadratic(0., 2., -4.), vec![2.]);
+        assert_eq!(solve_quadratic(2., 0., 0.), vec![0.]);
+        let roots = solve_quadratic(1., -1e4, 1.);
+        assert!((roots[0] - 1e-4).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_solve_cubic() {
+        assert_roots(solve_cubic(1., -6., 11., -6.), &[1., 2., 3.]);
+        assert_roots(solve_cubic(2., 0., 0., -16.), &[2.]);
+        assert_roots(solve_cubic(1., -3., 3., -1.), &[1.]);
+        assert_roots(solve_cubic(1., 0., -3., 2.), &[-2., 1.]);
+        assert_roots(solve_cubic(0., 1., -3., 2.), &[1., 2.]);
+    }
+
+    #[test]
+    fn test_solve_quartic() {
+        assert_roots(solve_quartic(1., -10., 35., -50., 24.), &[1., 2., 3., 4.]);
+        assert_roots(solve_quartic(1., 0., -5., 0., 4.), &[-2., -1., 1., 2.]);
+        assert_roots(solve_quartic(1., 0., -2., 0., 1.), &[-1., 1.]);
+        assert_roots(solve_quartic(1., 0., 0., 0., 1.), &[]);
+        assert_roots(solve_quartic(3., -3., 0., 0., 0.), &[0., 1.]);
+        assert_roots(solve_quartic(0., 1., -6., 11., -6.), &[1., 2., 3.]);
+    }
+
+    #[test]
+    fn test_solve_quartic_widely_spaced_roots() {
+        let roots = [-1000., -0.5, 0.25, 998.];
+        let [r0, r1, r2, r3] = roots;
+        assert_roots(
+            solve_quartic(
+                1.,
+                -(r0 + r1 + r2 + r3),
+                r0 * r1 + r0 * r2 + r0 * r3 + r1 * r2 + r1 * r3 + r2 * r3,
+                -(r0 * r1 * r2 + r0 * r1 * r3 + r0 * r2 * r3 + r1 * r2 * r3),
+                r0 * r1 * r2 * r3,
+            ),
+            &roots,
+        );
+    }
+}
@@ -0,0 +1,201 @@
+use std::ops::Mul;
+
+use crate::{constants::EPSILON, quaternion::Quaternion, vector::Vec3D};
+
+#[derive(Debug, Clone, Copy)]
+pub struct Mat4 {
+    pub rows: [[f32; 4]; 4],
+}
+
+impl Mat4 {
+    pub const IDENTITY: Self = Self {
+        rows: [
+            [1., 0., 0., 0.],
+            [0., 1., 0., 0.],
+            [0., 0., 1., 0.],
+            [0., 0., 0., 1.],
+        ],
+    };
+
+    pub const fn new(rows: [[f32; 4]; 4]) -> Self {
+        Self { rows }
+    }
+
+    pub fn translation(offset: Vec3D) -> Self {
+        let mut matrix = Self::IDENTITY;
+        matrix.rows[0][3] = offset.x;
+        matrix.rows[1][3] = offset.y;
+        matrix.rows[2][3] = offset.z;
+        matrix
+    }
+
+    pub fn scale(factors: Vec3D) -> Self {
+        let mut matrix = Self::IDENTITY;
+        matrix.rows[0][0] = factors.x;
+        matrix.rows[1][1] = factors.y;
+        matrix.rows[2][2] = factors.z;
+        matrix
+    }
+
+    pub fn rotation(rotation: Quaternion) -> Self {
+        let Quaternion { w, x, y, z } = rotation;
+        Self::new([
+            [
+                1. - 2. * (y * y + z * z),
+                2. * (x * y - w * z),
+                2. * (x * z + w * y),
+                0.,
+            ],
+            [
+                2. * (x * y + w * z),
+                1. - 2. * (x * x + z * z),
+                2. * (y * z - w * x),
+                0.,
+            ],
+            [
+                2. * (x * z - w * y),
+                2. * (y * z + w * x),
+                1. - 2. * (x * x + y * y),
+                0.,
+            ],
+            [0., 0., 0., 1.],
+        ])
+    }
+
+    pub fn transpose(self) -> Self {
+        let mut rows = [[0.; 4]; 4];
+        for (i, row) in rows.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = self.rows[j][i];
+            }
+        }
+        Self::new(rows)
+    }
+
+    pub fn inverse(self) -> Option<Self> {
+        let mut matrix = self.rows;
+        let mut inverse = Self::IDENTITY.rows;
+        for column in 0..4 {
+            let pivot = (column..4)
+                .max_by(|a, b| {
+                    matrix[*a][column]
+                        .abs()
+                        .partial_cmp(&matrix[*b][column].abs())
+                        .unwrap()
+                })
+                .unwrap();
+            if matrix[pivot][column].abs() < EPSILON {
+                return None;
+            }
+            matrix.swap(column, pivot);
+            inverse.swap(column, pivot);
+            let divisor = matrix[column][column];
+            for j in 0..4 {
+                matrix[column][j] /= divisor;
+                inverse[column][j] /= divisor;
+            }
+            for row in 0..4 {
+                if row == column {
+                    continue;
+                }
+                let factor = matrix[row][column];
+                for j in 0..4 {
+                    matrix[row][j] -= factor * matrix[column][j];
+                    inverse[row][j] -= factor * inverse[column][j];
+                }
+            }
+        }
+        Some(Self::new(inverse))
+    }
+
+    pub fn transform_point(self, point: Vec3D) -> Vec3D {
+        self.transform_vector(point) + Vec3D::new(self.rows[0][3], self.rows[1][3], self.rows[2][3])
+    }
+
+    pub fn transform_vector(self, vector: Vec3D) -> Vec3D {
+        let row = |i: usize| {
+            self.rows[i][0] * vector.x + self.rows[i][1] * vector.y + self.rows[i][2] * vector.z
+        };
+        Vec3D::new(row(0), row(1), row(2))
+    }
+}
+
+impl Default for Mat4 {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl PartialEq for Mat4 {
+    fn eq(&self, other: &Self) -> bool {
+        self.rows
+            .iter()
+            .flatten()
+            .zip(other.rows.iter().flatten())
+            .all(|(a, b)| (a - b).abs() < EPSILON)
+    }
+}
+
+impl Mul for Mat4 {
+    type Output = Self;
+    fn mul(self, rhs: Self) -> Self::Output {
+        let mut rows = [[0.; 4]; 4];
+        for (i, row) in rows.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = (0..4).map(|k| self.rows[i][k] * rhs.rows[k][j]).sum();
+            }
+        }
+        Self::new(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use super::*;
+
+    #[test]
+    fn test_multiplication() {
+        let translation = Mat4::translation(Vec3D::new(1., 2., 3.));
+        let scale = Mat4::scale(Vec3D::new(2., 2., 2.));
+        assert_eq!(translation * Mat4::IDENTITY, translation);
+        assert_eq!(
+            (translation * scale).transform_point(Vec3D::ONE),
+            Vec3D::new(3., 4., 5.)
+        );
+        assert_eq!(
+            (scale * translation).transform_point(Vec3D::ONE),
+            Vec3D::new(4., 6., 8.)
+        );
+    }
+
+    #[test]
+    fn test_rotation_matches_quaternion() {
+        let quaternion = Quaternion::from_axis_angle(Vec3D::new(1., 2., -1.), 0.7);
+        let matrix = Mat4::rotation(quaternion);
+        for vector in [Vec3D::X, Vec3D::Y, Vec3D::Z, Vec3D::new(0.3, -2., 1.5)] {
+            assert_eq!(matrix.transform_vector(vector), quaternion * vector);
+        }
+        let quarter_turn = Mat4::rotation(Quaternion::from_axis_angle(Vec3D::Z, PI / 2.));
+        assert_eq!(quarter_turn.transform_point(Vec3D::X), Vec3D::Y);
+    }
+
+    #[test]
+    fn test_inverse() {
+        let matrix = Mat4::translation(Vec3D::new(1., -2., 3.))
+            * Mat4::rotation(Quaternion::from_axis_angle(Vec3D::Y, 1.1))
+            * Mat4::scale(Vec3D::new(2., 0.5, 3.));
+        let inverse = matrix.inverse().unwrap();
+        assert_eq!(matrix * inverse, Mat4::IDENTITY);
+        assert_eq!(inverse * matrix, Mat4::IDENTITY);
+        assert!(Mat4::scale(Vec3D::new(1., 0., 1.)).inverse().is_none());
+    }
+
+    #[test]
+    fn test_transpose() {
+        let matrix = Mat4::translation(Vec3D::new(1., 2., 3.));
+        assert_eq!(matrix.transpose().rows[3], [1., 2., 3., 1.]);
+        assert_eq!(matrix.transpose().transpose(), matrix);
+    }
+}
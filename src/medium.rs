@@ -0,0 +1,468 @@
+use std::{f32::consts::PI, sync::Arc};
+
+use crate::{
+    checkpoint::{Fingerprint, Fingerprinter},
+    colour::Colour,
+    physics::Object,
+    ray::Ray,
+    sampler::Sampler,
+    vector::Vec3D,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Medium {
+    pub absorption: Colour,
+    pub scattering: Colour,
+    pub asymmetry: f32,
+}
+
+impl Medium {
+    pub fn new(absorption: Colour, scattering: Colour, asymmetry: f32) -> Self {
+        Self {
+            absorption,
+            scattering,
+            asymmetry,
+        }
+    }
+
+    pub fn extinction(self) -> Colour {
+        self.absorption + self.scattering
+    }
+
+    pub fn transmittance(self, distance: f32) -> Colour {
+        attenuate(self.extinction(), distance)
+    }
+
+    pub fn phase(self, cos_theta: f32) -> f32 {
+        henyey_greenstein(cos_theta, self.asymmetry)
+    }
+}
+
+pub fn henyey_greenstein(cos_theta: f32, asymmetry: f32) -> f32 {
+    let g = asymmetry;
+    let denominator = 1. + g * g - 2. * g * cos_theta;
+    (1. - g * g) / (4. * PI * denominator * denominator.sqrt())
+}
+
+pub fn sample_henyey_greenstein(direction: Vec3D, asymmetry: f32, (u, v): (f32, f32)) -> Vec3D {
+    let g = asymmetry;
+    let cos_theta = if g.abs() < 1e-3 {
+        1. - 2. * u
+    } else {
+        let term = (1. - g * g) / (1. - g + 2. * g * u);
+        ((1. + g * g - term * term) / (2. * g)).clamp(-1., 1.)
+    };
+    let sin_theta = (1. - cos_theta * cos_theta).max(0.).sqrt();
+    let phi = 2. * PI * v;
+    let (tangent, bitangent) = direction.basis();
+    (tangent * (sin_theta * phi.cos())
+        + bitangent * (sin_theta * phi.sin())
+        + direction * cos_theta)
+        .normalise()
+}
+
+fn attenuate(extinction: Colour, distance: f32) -> Colour {
+    let channel = |extinction: f32| {
+        if extinction == 0. {
+            1.
+        } else {
+            (-extinction * distance).exp()
+        }
+    };
+    Colour::new(
+        channel(extinction.red),
+        channel(extinction.green),
+        channel(extinction.blue),
+    )
+}
+
+fn average(colour: Colour) -> f32 {
+    (colour.red + colour.green + colour.blue) / 3.
+}
+
+#[derive(Debug, Clone)]
+pub struct Volume {
+    pub boundary: Arc<dyn Object>,
+    pub medium: Medium,
+}
+
+impl Volume {
+    pub fn new(boundary: Arc<dyn Object>, medium: Medium) -> Self {
+        Self { boundary, medium }
+    }
+
+    pub fn intervals(&self, ray: &Ray) -> Vec<(f32, f32)> {
+        let mut intersections = self.boundary.intersections(ray);
+        intersections.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap());
+        let exits = |direction: Vec3D, normal: Vec3D| direction.dot(normal) > 0.;
+        let mut start = intersections
+            .first()
+            .filter(|intersection| exits(ray.direction, intersection.normal))
+            .map(|_| 0.);
+        let mut intervals = Vec::new();
+        for intersection in intersections {
+            if !exits(ray.direction, intersection.normal) {
+                start = start.or(Some(intersection.distance));
+            } else if let Some(start) = start.take() {
+                intervals.push((start, intersection.distance));
+            }
+        }
+        if let Some(start) = start {
+            intervals.push((start, f32::INFINITY));
+        }
+        intervals
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediumSegment {
+    pub start: f32,
+    pub end: f32,
+    pub media: Vec<Medium>,
+}
+
+impl MediumSegment {
+    pub fn extinction(&self) -> Colour {
+        self.media.iter().fold(Colour::default(), |total, medium| {
+            total + medium.extinction()
+        })
+    }
+}
+
+pub fn segments(
+    fog: Option<Medium>,
+    volumes: &[Volume],
+    ray: &Ray,
+    max_distance: f32,
+) -> Vec<MediumSegment> {
+    let intervals = volumes
+        .iter()
+        .map(|volume| (volume.medium, volume.intervals(ray)))
+        .collect::<Vec<_>>();
+    let mut boundaries = vec![0., max_distance];
+    boundaries.extend(
+        intervals
+            .iter()
+            .flat_map(|(_, intervals)| intervals.iter().flat_map(|(start, end)| [*start, *end]))
+            .filter(|distance| (0. ..max_distance).contains(distance)),
+    );
+    boundaries.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap());
+    boundaries.dedup();
+    boundaries
+        .windows(2)
+        .filter_map(|window| {
+            let (start, end) = (window[0], window[1]);
+            let media = fog
+                .into_iter()
+                .chain(
+                    intervals
+                        .iter()
+                        .filter(|(_, intervals)| {
+                            intervals
+                                .iter()
+                                .any(|(low, high)| *low <= start && start < *high)
+                        })
+                        .map(|(medium, _)| *medium),
+                )
+                .collect::<Vec<Medium>>();
+            (!media.is_empty()).then_some(MediumSegment { start, end, media })
+        })
+        .collect()
+}
+
+pub fn transmittance(segments: &[MediumSegment]) -> Colour {
+    segments.iter().fold(Colour::gray(1.), |total, segment| {
+        total * attenuate(segment.extinction(), segment.end - segment.start)
+    })
+}
+
+pub fn survival_weight(segments: &[MediumSegment], distance: f32) -> Colour {
+    let mut transmittance = Colour::gray(1.);
+    let mut probability = 1.;
+    for segment in segments {
+        let length = segment.end.min(distance) - segment.start;
+        if length <= 0. {
+            break;
+        }
+        let extinction = segment.extinction();
+        transmittance = transmittance * attenuate(extinction, length);
+        probability *= (-average(extinction) * length).exp();
+    }
+    transmittance * (1. / probability)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediumEvent {
+    pub position: Vec3D,
+    pub direction: Vec3D,
+    pub media: Vec<Medium>,
+}
+
+impl MediumEvent {
+    pub fn in_scattering(&self, outgoing: Vec3D) -> Colour {
+        let cos_theta = self.direction.dot(outgoing);
+        self.media.iter().fold(Colour::default(), |total, medium| {
+            total + medium.scattering * medium.phase(cos_theta)
+        })
+    }
+
+    pub fn pdf(&self, outgoing: Vec3D) -> f32 {
+        let total = self
+            .media
+            .iter()
+            .map(|medium| average(medium.scattering))
+            .sum::<f32>();
+        if total <= 0. {
+            return 0.;
+        }
+        let cos_theta = self.direction.dot(outgoing);
+        self.media
+            .iter()
+            .map(|medium| average(medium.scattering) / total * medium.phase(cos_theta))
+            .sum::<f32>()
+    }
+
+    pub fn sample_direction(&self, sampler: &mut dyn Sampler) -> Option<(Vec3D, Colour, f32)> {
+        let weights = self
+            .media
+            .iter()
+            .map(|medium| average(medium.scattering))
+            .collect::<Vec<f32>>();
+        let total = weights.iter().sum::<f32>();
+        if total <= 0. {
+            return None;
+        }
+        let mut choice = sampler.next_f32() * total;
+        let chosen = weights
+            .iter()
+            .position(|weight| {
+                choice -= weight;
+                choice < 0.
+            })
+            .unwrap_or(weights.len() - 1);
+        let direction = sample_henyey_greenstein(
+            self.direction,
+            self.media[chosen].asymmetry,
+            sampler.next_pair(),
+        );
+        let pdf = self.pdf(direction);
+        Some((direction, self.in_scattering(direction) * (1. / pdf), pdf))
+    }
+}
+
+pub fn sample_distance(
+    segments: &[MediumSegment],
+    ray: &Ray,
+    u: f32,
+) -> (Option<MediumEvent>, Colour) {
+    let mut remaining = -(1. - u).ln();
+    let mut transmittance = Colour::gray(1.);
+    let mut probability = 1.;
+    for segment in segments {
+        let extinction = segment.extinction();
+        let density = average(extinction);
+        if density <= 0. {
+            continue;
+        }
+        let length = segment.end - segment.start;
+        if density * length >= remaining {
+            let offset = remaining / density;
+            let transmittance = transmittance * attenuate(extinction, offset);
+            let pdf = density * probability * (-remaining).exp();
+            let event = MediumEvent {
+                position: ray.origin + ray.direction * (segment.start + offset),
+                direction: ray.direction,
+                media: segment.media.clone(),
+            };
+            return (Some(event), transmittance * (1. / pdf));
+        }
+        remaining -= density * length;
+        transmittance = transmittance * attenuate(extinction, length);
+        probability *= (-density * length).exp();
+    }
+    (None, transmittance * (1. / probability))
+}
+
+impl Fingerprint for Volume {
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        self.boundary.fingerprint(state);
+        state.debug(&self.medium);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cuboid::Cuboid, material::Material, random::Rng, scene::Scene, sphere::Sphere};
+
+    fn smoke() -> Medium {
+        Medium::new(Colour::gray(0.1), Colour::new(0.4, 0.5, 0.6), 0.3)
+    }
+
+    #[test]
+    fn test_henyey_greenstein_normalisation() {
+        for asymmetry in [-0.7, 0., 0.4, 0.9] {
+            let steps = 20000;
+            let integral = (0..steps)
+                .map(|i| {
+                    let cos_theta = -1. + 2. * (i as f32 + 0.5) / steps as f32;
+                    henyey_greenstein(cos_theta, asymmetry) * 2. * PI * 2. / steps as f32
+                })
+                .sum::<f32>();
+            assert!((integral - 1.).abs() < 0.01, "{asymmetry}: {integral}");
+        }
+        assert_eq!(henyey_greenstein(0.3, 0.), 1. / (4. * PI));
+    }
+
+    #[test]
+    fn test_henyey_greenstein_sampling() {
+        let mut rng = Rng::new(2, 0);
+        let direction = Vec3D::new(1., 2., -1.).normalise();
+        for asymmetry in [-0.5, 0., 0.8] {
+            let count = 20000;
+            let mean = (0..count)
+                .map(|_| {
+                    let sample = sample_henyey_greenstein(direction, asymmetry, rng.next_pair());
+                    assert!((sample.length() - 1.).abs() < 1e-4);
+                    sample.dot(direction)
+                })
+                .sum::<f32>()
+                / count as f32;
+            assert!((mean - asymmetry).abs() < 0.02, "{asymmetry}: {mean}");
+        }
+    }
+
+    #[test]
+    fn test_volume_intervals() {
+        let volume = Volume::new(
+            Arc::new(Sphere::new(Vec3D::ZERO, 1., Material::default())),
+            smoke(),
+        );
+        let ray = Ray::new(Vec3D::new(-5., 0., 0.), Vec3D::X);
+        assert_eq!(volume.intervals(&ray), vec![(4., 6.)]);
+        let ray = Ray::new(Vec3D::new(0.5, 0., 0.), Vec3D::X);
+        assert_eq!(volume.intervals(&ray), vec![(0., 0.5)]);
+        let ray = Ray::new(Vec3D::new(-5., 2., 0.), Vec3D::X);
+        assert!(volume.intervals(&ray).is_empty());
+    }
+
+    #[test]
+    fn test_segments_and_transmittance() {
+        let fog = Medium::new(Colour::gray(0.01), Colour::gray(0.02), 0.);
+        let volume = Volume::new(
+            Arc::new(Sphere::new(Vec3D::ZERO, 1., Material::default())),
+            smoke(),
+        );
+        let ray = Ray::new(Vec3D::new(-5., 0., 0.), Vec3D::X);
+        let segments = segments(Some(fog), std::slice::from_ref(&volume), &ray, 10.);
+        assert_eq!(
+            segments
+                .iter()
+                .map(|segment| (segment.start, segment.end, segment.media.len()))
+                .collect::<Vec<_>>(),
+            vec![(0., 4., 1), (4., 6., 2), (6., 10., 1)]
+        );
+        let expected = fog.transmittance(10.) * smoke().transmittance(2.);
+        let actual = transmittance(&segments);
+        assert!((actual - expected).as_rgb().iter().all(|x| x.abs() < 1e-6));
+        let inside = super::segments(None, &[volume], &ray, 5.);
+        assert_eq!(inside.len(), 1);
+        assert_eq!((inside[0].start, inside[0].end), (4., 5.));
+    }
+
+    #[test]
+    fn test_distance_sampling_is_unbiased() {
+        let medium = Medium::new(Colour::gray(0.2), Colour::new(0.3, 0.6, 0.9), 0.);
+        let ray = Ray::new(Vec3D::ZERO, Vec3D::X);
+        let segments = vec![MediumSegment {
+            start: 0.,
+            end: 2.,
+            media: vec![medium],
+        }];
+        let mut rng = Rng::new(7, 0);
+        let count = 100000;
+        let (mut transmitted, mut scattered) = (Colour::default(), Colour::default());
+        for _ in 0..count {
+            match sample_distance(&segments, &ray, rng.next_f32()) {
+                (Some(event), weight) => {
+                    assert!((0. ..2.).contains(&event.position.x));
+                    scattered = scattered + weight * medium.scattering;
+                }
+                (None, weight) => transmitted = transmitted + weight,
+            }
+        }
+        let transmitted = transmitted * (1. / count as f32);
+        let scattered = scattered * (1. / count as f32);
+        let expected = medium.transmittance(2.);
+        assert!((transmitted - expected)
+            .as_rgb()
+            .iter()
+            .all(|x| x.abs() < 0.01));
+        let albedo = |scattering: f32, extinction: f32| {
+            scattering / extinction * (1. - (-extinction * 2_f32).exp())
+        };
+        let expected = Colour::new(albedo(0.3, 0.5), albedo(0.6, 0.8), albedo(0.9, 1.1));
+        assert!((scattered - expected)
+            .as_rgb()
+            .iter()
+            .all(|x| x.abs() < 0.01));
+    }
+
+    #[test]
+    fn test_phase_sampling_weight() {
+        let event = MediumEvent {
+            position: Vec3D::ZERO,
+            direction: Vec3D::Z,
+            media: vec![
+                smoke(),
+                Medium::new(Colour::default(), Colour::gray(0.2), -0.6),
+            ],
+        };
+        let mut rng = Rng::new(3, 0);
+        let count = 50000;
+        let total = (0..count).fold(Colour::default(), |total, _| {
+            total + event.sample_direction(&mut rng).unwrap().1
+        }) * (1. / count as f32);
+        let expected = smoke().scattering + Colour::gray(0.2);
+        assert!((total - expected).as_rgb().iter().all(|x| x.abs() < 0.01));
+        let absorbing = MediumEvent {
+            media: vec![Medium::new(Colour::gray(1.), Colour::default(), 0.)],
+            ..event
+        };
+        assert!(absorbing.sample_direction(&mut rng).is_none());
+    }
+
+    #[test]
+    fn test_scene_transmittance() {
+        let scene = Scene {
+            objects: vec![Box::new(Cuboid::new(
+                Vec3D::new(-1., 2., -1.),
+                Vec3D::new(1., 3., 1.),
+                Material::default(),
+            ))],
+            volumes: vec![Volume::new(
+                Arc::new(Sphere::new(Vec3D::ZERO, 1., Material::default())),
+                smoke(),
+            )],
+            ..Default::default()
+        };
+        let mut rng = Rng::new(1, 0);
+        let through_smoke =
+            scene.transmittance(Vec3D::new(-5., 0., 0.), Vec3D::new(5., 0., 0.), &mut rng);
+        let expected = smoke().transmittance(2.);
+        assert!((through_smoke - expected)
+            .as_rgb()
+            .iter()
+            .all(|x| x.abs() < 1e-5));
+        let blocked = scene.transmittance(Vec3D::ZERO, Vec3D::new(0., 10., 0.), &mut rng);
+        assert_eq!(blocked, Colour::default());
+        let ray = Ray::new(Vec3D::new(-5., 0., 0.), Vec3D::X);
+        let events = (0..1000)
+            .filter_map(|_| scene.sample_medium(&ray, f32::INFINITY, &mut rng).0)
+            .collect::<Vec<MediumEvent>>();
+        assert!(!events.is_empty());
+        assert!(events
+            .iter()
+            .all(|event| (-1. ..=1.).contains(&event.position.x)));
+    }
+}
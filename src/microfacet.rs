@@ -0,0 +1,409 @@
+use std::f32::consts::PI;
+
+use crate::{colour::Colour, shading::ShadingModel, vector::Vec3D};
+
+const MINIMUM_ALPHA: f32 = 0.002;
+const DIELECTRIC_REFLECTANCE: f32 = 0.04;
+
+pub fn ggx_distribution(normal_dot_halfway: f32, alpha: f32) -> f32 {
+    if normal_dot_halfway <= 0. {
+        return 0.;
+    }
+    let alpha_squared = alpha * alpha;
+    let denominator = normal_dot_halfway * normal_dot_halfway * (alpha_squared - 1.) + 1.;
+    alpha_squared / (PI * denominator * denominator)
+}
+
+pub fn smith_masking(normal_dot_direction: f32, alpha: f32) -> f32 {
+    if normal_dot_direction <= 0. {
+        return 0.;
+    }
+    let alpha_squared = alpha * alpha;
+    let cosine_squared = normal_dot_direction * normal_dot_direction;
+    2. * normal_dot_direction
+        / (normal_dot_direction + (alpha_squared + (1. - alpha_squared) * cosine_squared).sqrt())
+}
+
+pub fn smith_shadowing_masking(normal_dot_viewer: f32, normal_dot_light: f32, alpha: f32) -> f32 {
+    smith_masking(normal_dot_viewer, alpha) * smith_masking(normal_dot_light, alpha)
+}
+
+pub fn fresnel_schlick(cosine: f32, reflectance: Colour) -> Colour {
+    let weight = (1. - cosine.clamp(0., 1.)).powi(5);
+    reflectance + (Colour::gray(1.) - reflectance) * weight
+}
+
+fn to_world(local: Vec3D, normal: Vec3D) -> Vec3D {
+    let (tangent, bitangent) = normal.basis();
+    tangent * local.x + bitangent * local.y + normal * local.z
+}
+
+fn sample_ggx_halfway(normal: Vec3D, alpha: f32, (u, v): (f32, f32)) -> Vec3D {
+    let cos_theta = ((1. - u) / (1. + (alpha * alpha - 1.) * u)).sqrt();
+    let sin_theta = (1. - cos_theta * cos_theta).max(0.).sqrt();
+    let phi = 2. * PI * v;
+    to_world(
+        Vec3D::new(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta),
+        normal,
+    )
+}
+
+fn sample_power_cosine(axis: Vec3D, exponent: f32, (u, v): (f32, f32)) -> Vec3D {
+    let cos_theta = u.powf(1. / (exponent + 1.));
+    let sin_theta = (1. - cos_theta * cos_theta).max(0.).sqrt();
+    let phi = 2. * PI * v;
+    to_world(
+        Vec3D::new(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta),
+        axis,
+    )
+}
+
+fn power_cosine_pdf(cosine: f32, exponent: f32) -> f32 {
+    if cosine <= 0. {
+        return 0.;
+    }
+    (exponent + 1.) / (2. * PI) * cosine.powf(exponent)
+}
+
+fn sample_cosine_hemisphere(normal: Vec3D, (u, v): (f32, f32)) -> Vec3D {
+    let radius = u.sqrt();
+    let phi = 2. * PI * v;
+    to_world(
+        Vec3D::new(
+            radius * phi.cos(),
+            radius * phi.sin(),
+            (1. - u).max(0.).sqrt(),
+        ),
+        normal,
+    )
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BsdfSample {
+    pub direction: Vec3D,
+    pub weight: Colour,
+    pub pdf: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bsdf {
+    pub base_colour: Colour,
+    pub metallic: f32,
+    pub roughness: f32,
+    // None selects the GGX microfacet lobe.
+    pub shading_model: Option<ShadingModel>,
+}
+
+impl Bsdf {
+    pub fn alpha(&self) -> f32 {
+        (self.roughness * self.roughness).max(MINIMUM_ALPHA)
+    }
+
+    // The Blinn-Phong exponent whose lobe is closest to a GGX lobe of the same roughness.
+    pub fn exponent(&self) -> f32 {
+        (2. / (self.alpha() * self.alpha()) - 2.).max(0.)
+    }
+
+    pub fn specular_reflectance(&self) -> Colour {
+        Colour::gray(DIELECTRIC_REFLECTANCE) * (1. - self.metallic)
+            + self.base_colour * self.metallic
+    }
+
+    fn specular_probability(&self) -> f32 {
+        let specular = self.specular_reflectance().luminance();
+        let diffuse = self.base_colour.luminance() * (1. - self.metallic);
+        if specular + diffuse <= 0. {
+            return 1.;
+        }
+        (specular / (specular + diffuse)).clamp(0.25, 1.)
+    }
+
+    pub fn evaluate(&self, normal: Vec3D, to_light: Vec3D, to_viewer: Vec3D) -> Colour {
+        let normal = normal.normalise();
+        let to_light = to_light.normalise();
+        let to_viewer = to_viewer.normalise();
+        let normal_dot_light = normal.dot(to_light);
+        let normal_dot_viewer = normal.dot(to_viewer);
+        if normal_dot_light <= 0. || normal_dot_viewer <= 0. {
+            return Colour::default();
+        }
+        let halfway = (to_light + to_viewer).normalise();
+        let alpha = self.alpha();
+        let fresnel = fresnel_schlick(to_viewer.dot(halfway), self.specular_reflectance());
+        let specular = fresnel
+            * match self.shading_model {
+                Some(model) => model.specular(normal, to_light, to_viewer, self.exponent()),
+                None => {
+                    ggx_distribution(normal.dot(halfway), alpha)
+                        * smith_shadowing_masking(normal_dot_viewer, normal_dot_light, alpha)
+                        / (4. * normal_dot_viewer * normal_dot_light)
+                }
+            };
+        let diffuse = (Colour::gray(1.) - fresnel) * self.base_colour * ((1. - self.metallic) / PI);
+        specular + diffuse
+    }
+
+    pub fn pdf(&self, normal: Vec3D, to_light: Vec3D, to_viewer: Vec3D) -> f32 {
+        let normal = normal.normalise();
+        let to_light = to_light.normalise();
+        let to_viewer = to_viewer.normalise();
+        let normal_dot_light = normal.dot(to_light);
+        if normal_dot_light <= 0. || normal.dot(to_viewer) <= 0. {
+            return 0.;
+        }
+        let halfway = (to_light + to_viewer).normalise();
+        let specular = match self.shading_model {
+            Some(ShadingModel::Phong) => {
+                power_cosine_pdf((-to_viewer).reflect(normal).dot(to_light), self.exponent())
+            }
+            Some(ShadingModel::BlinnPhong) => {
+                power_cosine_pdf(normal.dot(halfway), self.exponent())
+                    / (4. * to_viewer.dot(halfway))
+            }
+            None => {
+                ggx_distribution(normal.dot(halfway), self.alpha()) * normal.dot(halfway)
+                    / (4. * to_viewer.dot(halfway))
+            }
+        };
+        let diffuse = normal_dot_light / PI;
+        let probability = self.specular_probability();
+        probability * specular + (1. - probability) * diffuse
+    }
+
+    pub fn sample(
+        &self,
+        normal: Vec3D,
+        to_viewer: Vec3D,
+        (u, v): (f32, f32),
+    ) -> Option<BsdfSample> {
+        let normal = normal.normalise();
+        let to_viewer = to_viewer.normalise();
+        if normal.dot(to_viewer) <= 0. {
+            return None;
+        }
+        let probability = self.specular_probability();
+        let direction = if u < probability {
+            let u = u / probability;
+            match self.shading_model {
+                Some(ShadingModel::Phong) => {
+                    sample_power_cosine((-to_viewer).reflect(normal), self.exponent(), (u, v))
+                }
+                Some(ShadingModel::BlinnPhong) => {
+                    let halfway = sample_power_cosine(normal, self.exponent(), (u, v));
+                    (-to_viewer).reflect(halfway)
+                }
+                None => {
+                    let halfway = sample_ggx_halfway(normal, self.alpha(), (u, v));
+                    (-to_viewer).reflect(halfway)
+                }
+            }
+        } else {
+            sample_cosine_hemisphere(normal, ((u - probability) / (1. - probability), v))
+        };
+        let pdf = self.pdf(normal, direction, to_viewer);
+        if pdf <= 0. {
+            return None;
+        }
+        Some(BsdfSample {
+            direction,
+            weight: self.evaluate(normal, direction, to_viewer) * (normal.dot(direction) / pdf),
+            pdf,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::random::Rng;
+
+    fn integrate_hemisphere(function: impl Fn(Vec3D) -> f32) -> f32 {
+        let steps = 1024;
+        let step = (PI / 2.) / steps as f32;
+        let azimuth_steps = 64;
+        let azimuth_step = (2. * PI) / azimuth_steps as f32;
+        let mut total = 0.;
+        for i in 0..steps {
+            let theta = (i as f32 + 0.5) * step;
+            for j in 0..azimuth_steps {
+                let phi = (j as f32 + 0.5) * azimuth_step;
+                let direction = Vec3D::new(
+                    theta.sin() * phi.cos(),
+                    theta.sin() * phi.sin(),
+                    theta.cos(),
+                );
+                total += function(direction) * theta.sin() * step * azimuth_step;
+            }
+        }
+        total
+    }
+
+    #[test]
+    fn test_ggx_distribution_normalisation() {
+        for alpha in [0.1, 0.3, 0.6, 1.] {
+            let projected_area =
+                integrate_hemisphere(|halfway| ggx_distribution(halfway.z, alpha) * halfway.z);
+            assert!(
+                (projected_area - 1.).abs() < 0.01,
+                "{alpha}: {projected_area}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_smith_masking() {
+        assert!((smith_masking(1., 0.5) - 1.).abs() < 1e-6);
+        assert_eq!(smith_masking(0., 0.5), 0.);
+        assert!(smith_masking(0.2, 0.8) < smith_masking(0.2, 0.2));
+    }
+
+    #[test]
+    fn test_fresnel_schlick() {
+        let reflectance = Colour::new(0.9, 0.6, 0.3);
+        assert_eq!(fresnel_schlick(1., reflectance), reflectance);
+        assert_eq!(fresnel_schlick(0., reflectance), Colour::gray(1.));
+    }
+
+    #[test]
+    fn test_energy_conservation() {
+        let to_viewer = Vec3D::new(0.3, 0., 1.).normalise();
+        let albedos = [0.3, 0.5, 0.7, 1.].map(|roughness| {
+            let bsdf = Bsdf {
+                base_colour: Colour::gray(1.),
+                metallic: 1.,
+                roughness,
+                shading_model: None,
+            };
+            integrate_hemisphere(|to_light| {
+                bsdf.evaluate(Vec3D::Z, to_light, to_viewer).red * to_light.z
+            })
+        });
+        assert!(albedos[0] > 0.9, "{albedos:?}");
+        assert!(albedos.iter().all(|albedo| *albedo <= 1.01), "{albedos:?}");
+        assert!(
+            albedos.windows(2).all(|pair| pair[1] < pair[0]),
+            "{albedos:?}"
+        );
+    }
+
+    #[test]
+    fn test_sample_pdf_matches_pdf() {
+        for shading_model in [
+            None,
+            Some(ShadingModel::Phong),
+            Some(ShadingModel::BlinnPhong),
+        ] {
+            let bsdf = Bsdf {
+                base_colour: Colour::new(0.8, 0.4, 0.2),
+                metallic: 0.3,
+                roughness: 0.4,
+                shading_model,
+            };
+            let normal = Vec3D::new(0.2, -0.3, 1.).normalise();
+            let to_viewer = Vec3D::new(-0.5, 0.4, 1.).normalise();
+            let mut rng = Rng::new(1, 0);
+            for _ in 0..1000 {
+                if let Some(sample) = bsdf.sample(normal, to_viewer, rng.next_pair()) {
+                    assert!(sample.direction.dot(normal) > 0.);
+                    let pdf = bsdf.pdf(normal, sample.direction, to_viewer);
+                    assert!((sample.pdf - pdf).abs() <= 1e-3 * pdf.max(1.));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_pdf_normalisation() {
+        for shading_model in [
+            None,
+            Some(ShadingModel::Phong),
+            Some(ShadingModel::BlinnPhong),
+        ] {
+            for (metallic, roughness) in [(0., 0.3), (1., 0.5), (0.5, 0.8)] {
+                let bsdf = Bsdf {
+                    base_colour: Colour::gray(0.5),
+                    metallic,
+                    roughness,
+                    shading_model,
+                };
+                let to_viewer = Vec3D::new(0.4, 0., 1.).normalise();
+                let total =
+                    integrate_hemisphere(|to_light| bsdf.pdf(Vec3D::Z, to_light, to_viewer));
+                assert!(
+                    total <= 1.01,
+                    "{shading_model:?} {metallic} {roughness}: {total}"
+                );
+                assert!(
+                    total > 0.8,
+                    "{shading_model:?} {metallic} {roughness}: {total}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_empirical_highlights() {
+        let to_viewer = Vec3D::new(0.5, 0., 1.).normalise();
+        let mirror = Vec3D::new(-0.5, 0., 1.).normalise();
+        for shading_model in [ShadingModel::Phong, ShadingModel::BlinnPhong] {
+            let bsdf = Bsdf {
+                base_colour: Colour::gray(1.),
+                metallic: 1.,
+                roughness: 0.3,
+                shading_model: Some(shading_model),
+            };
+            let peak = bsdf.evaluate(Vec3D::Z, mirror, to_viewer).red;
+            for to_light in [
+                Vec3D::Z,
+                Vec3D::new(-0.6, 0., 1.),
+                Vec3D::new(-0.5, 0.1, 1.),
+            ] {
+                assert!(bsdf.evaluate(Vec3D::Z, to_light, to_viewer).red < peak);
+            }
+        }
+    }
+
+    #[test]
+    fn test_importance_sampling_converges() {
+        let bsdf = Bsdf {
+            base_colour: Colour::new(0.9, 0.7, 0.5),
+            metallic: 0.8,
+            roughness: 0.3,
+            shading_model: None,
+        };
+        let to_viewer = Vec3D::new(0.5, 0.2, 1.).normalise();
+        let reference = integrate_hemisphere(|to_light| {
+            bsdf.evaluate(Vec3D::Z, to_light, to_viewer).green * to_light.z
+        });
+        let mut rng = Rng::new(7, 0);
+        let count = 20000;
+        let mut importance_total = 0.;
+        let mut importance_squares = 0.;
+        let mut uniform_total = 0.;
+        let mut uniform_squares = 0.;
+        for _ in 0..count {
+            let estimate = bsdf
+                .sample(Vec3D::Z, to_viewer, rng.next_pair())
+                .map_or(0., |sample| sample.weight.green);
+            importance_total += estimate;
+            importance_squares += estimate * estimate;
+
+            let (u, v) = rng.next_pair();
+            let sin_theta = (1. - u * u).sqrt();
+            let phi = 2. * PI * v;
+            let to_light = Vec3D::new(sin_theta * phi.cos(), sin_theta * phi.sin(), u);
+            let estimate = bsdf.evaluate(Vec3D::Z, to_light, to_viewer).green * u * 2. * PI;
+            uniform_total += estimate;
+            uniform_squares += estimate * estimate;
+        }
+        let importance_mean = importance_total / count as f32;
+        let uniform_mean = uniform_total / count as f32;
+        assert!(
+            (importance_mean - reference).abs() < 0.02,
+            "{importance_mean} {reference}"
+        );
+        let importance_variance = importance_squares / count as f32 - importance_mean.powi(2);
+        let uniform_variance = uniform_squares / count as f32 - uniform_mean.powi(2);
+        assert!(importance_variance < uniform_variance / 4.);
+    }
+}
@@ -0,0 +1,248 @@
+use crate::{
+    checkpoint::{Fingerprint, Fingerprinter},
+    colour::Colour,
+    random::Rng,
+    texture::Texture,
+    vector::Vec3D,
+};
+
+const LACUNARITY: f32 = 2.;
+const GAIN: f32 = 0.5;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Perlin {
+    permutation: Vec<usize>,
+}
+
+impl Perlin {
+    pub fn new(seed: u64) -> Self {
+        let mut rng = Rng::new(seed, 0);
+        let mut permutation = (0..256).collect::<Vec<usize>>();
+        for i in (1..permutation.len()).rev() {
+            permutation.swap(i, rng.next_u32() as usize % (i + 1));
+        }
+        permutation.extend_from_within(..);
+        Self { permutation }
+    }
+
+    pub fn noise(&self, position: Vec3D) -> f32 {
+        let floor = Vec3D::new(position.x.floor(), position.y.floor(), position.z.floor());
+        let local = position - floor;
+        let [x, y, z] = [floor.x, floor.y, floor.z].map(|value| value.rem_euclid(256.) as usize);
+        let [u, v, w] = [local.x, local.y, local.z].map(fade);
+        let hash = |i: usize, j: usize, k: usize| {
+            self.permutation[self.permutation[self.permutation[x + i] + y + j] + z + k]
+        };
+        let corner = |i: usize, j: usize, k: usize| {
+            gradient(
+                hash(i, j, k),
+                local - Vec3D::new(i as f32, j as f32, k as f32),
+            )
+        };
+        lerp(
+            w,
+            lerp(
+                v,
+                lerp(u, corner(0, 0, 0), corner(1, 0, 0)),
+                lerp(u, corner(0, 1, 0), corner(1, 1, 0)),
+            ),
+            lerp(
+                v,
+                lerp(u, corner(0, 0, 1), corner(1, 0, 1)),
+                lerp(u, corner(0, 1, 1), corner(1, 1, 1)),
+            ),
+        )
+    }
+
+    pub fn fbm(&self, position: Vec3D, octaves: usize) -> f32 {
+        self.octaves(position, octaves, |value| value)
+    }
+
+    pub fn turbulence(&self, position: Vec3D, octaves: usize) -> f32 {
+        self.octaves(position, octaves, f32::abs)
+    }
+
+    fn octaves(&self, position: Vec3D, octaves: usize, shape: impl Fn(f32) -> f32) -> f32 {
+        let mut total = 0.;
+        let mut amplitude = 1.;
+        let mut frequency = 1.;
+        let mut normalisation = 0.;
+        for _ in 0..octaves {
+            total += shape(self.noise(position * frequency)) * amplitude;
+            normalisation += amplitude;
+            amplitude *= GAIN;
+            frequency *= LACUNARITY;
+        }
+        if normalisation > 0. {
+            total / normalisation
+        } else {
+            0.
+        }
+    }
+}
+
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6. - 15.) + 10.)
+}
+
+fn lerp(t: f32, a: f32, b: f32) -> f32 {
+    a + t * (b - a)
+}
+
+fn gradient(hash: usize, offset: Vec3D) -> f32 {
+    let hash = hash & 15;
+    let u = if hash < 8 { offset.x } else { offset.y };
+    let v = match hash {
+        0..=3 => offset.y,
+        12 | 14 => offset.x,
+        _ => offset.z,
+    };
+    (if hash & 1 == 0 { u } else { -u }) + (if hash & 2 == 0 { v } else { -v })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NoisePattern {
+    Noise,
+    Fbm { octaves: usize },
+    Turbulence { octaves: usize },
+    Marble { octaves: usize, strength: f32 },
+    Wood { rings: f32, distortion: f32 },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NoiseTexture {
+    pub perlin: Perlin,
+    pub pattern: NoisePattern,
+    pub scale: f32,
+    pub colours: (Colour, Colour),
+}
+
+impl NoiseTexture {
+    pub fn new(seed: u64, pattern: NoisePattern, scale: f32, colours: (Colour, Colour)) -> Self {
+        Self {
+            perlin: Perlin::new(seed),
+            pattern,
+            scale,
+            colours,
+        }
+    }
+
+    pub fn marble(seed: u64, scale: f32, base: Colour, vein: Colour) -> Self {
+        Self::new(
+            seed,
+            NoisePattern::Marble {
+                octaves: 6,
+                strength: 8.,
+            },
+            scale,
+            (vein, base),
+        )
+    }
+
+    pub fn wood(seed: u64, scale: f32, light: Colour, dark: Colour) -> Self {
+        Self::new(
+            seed,
+            NoisePattern::Wood {
+                rings: 8.,
+                distortion: 0.6,
+            },
+            scale,
+            (light, dark),
+        )
+    }
+
+    pub fn amount(&self, position: Vec3D) -> f32 {
+        let position = position * self.scale;
+        let amount = match self.pattern {
+            NoisePattern::Noise => 0.5 * (1. + self.perlin.noise(position)),
+            NoisePattern::Fbm { octaves } => 0.5 * (1. + self.perlin.fbm(position, octaves)),
+            NoisePattern::Turbulence { octaves } => self.perlin.turbulence(position, octaves),
+            NoisePattern::Marble { octaves, strength } => {
+                0.5 * (1.
+                    + (position.x + strength * self.perlin.turbulence(position, octaves)).sin())
+            }
+            NoisePattern::Wood { rings, distortion } => {
+                let radius = position.x.hypot(position.z);
+                (radius * rings + distortion * self.perlin.noise(position)).rem_euclid(1.)
+            }
+        };
+        amount.clamp(0., 1.)
+    }
+}
+
+impl Texture for NoiseTexture {
+    fn value(&self, _: (f32, f32), position: Vec3D) -> Colour {
+        let amount = self.amount(position);
+        self.colours.0 * (1. - amount) + self.colours.1 * amount
+    }
+}
+
+impl Fingerprint for NoiseTexture {
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        state.debug(self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn positions() -> impl Iterator<Item = Vec3D> {
+        let mut rng = Rng::new(3, 0);
+        (0..1000)
+            .map(move |_| Vec3D::new(rng.next_f32(), rng.next_f32(), rng.next_f32()) * 20. - 10.)
+    }
+
+    #[test]
+    fn test_deterministic_for_seed() {
+        let a = Perlin::new(12);
+        let b = Perlin::new(12);
+        let c = Perlin::new(13);
+        assert!(positions().all(|position| a.noise(position) == b.noise(position)));
+        assert!(positions().any(|position| a.noise(position) != c.noise(position)));
+    }
+
+    #[test]
+    fn test_zero_on_lattice() {
+        let perlin = Perlin::new(0);
+        for (x, y, z) in [(0., 0., 0.), (1., 2., 3.), (-4., 7., -1.)] {
+            assert_eq!(perlin.noise(Vec3D::new(x, y, z)), 0.);
+        }
+    }
+
+    #[test]
+    fn test_range() {
+        let perlin = Perlin::new(5);
+        let values = positions()
+            .map(|position| perlin.noise(position))
+            .collect::<Vec<_>>();
+        assert!(values.iter().all(|value| (-1. ..=1.).contains(value)));
+        assert!(values.iter().any(|value| *value > 0.2));
+        assert!(values.iter().any(|value| *value < -0.2));
+        assert!(positions().all(|position| (-1. ..=1.).contains(&perlin.fbm(position, 6))));
+        assert!(positions().all(|position| (0. ..=1.).contains(&perlin.turbulence(position, 6))));
+    }
+
+    #[test]
+    fn test_continuity() {
+        let perlin = Perlin::new(9);
+        for position in positions() {
+            let difference = perlin.noise(position) - perlin.noise(position + Vec3D::triple(1e-3));
+            assert!(difference.abs() < 0.01);
+        }
+    }
+
+    #[test]
+    fn test_presets() {
+        let marble = NoiseTexture::marble(1, 2., Colour::gray(1.), Colour::gray(0.));
+        let wood = NoiseTexture::wood(1, 2., Colour::gray(1.), Colour::gray(0.));
+        for texture in [marble, wood] {
+            let amounts = positions()
+                .map(|position| texture.amount(position))
+                .collect::<Vec<_>>();
+            assert!(amounts.iter().all(|amount| (0. ..=1.).contains(amount)));
+            assert!(amounts.iter().any(|amount| *amount > 0.8));
+            assert!(amounts.iter().any(|amount| *amount < 0.2));
+        }
+    }
+}
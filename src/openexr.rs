@@ -0,0 +1,214 @@
+use std::{io, path::Path};
+
+use exr::prelude::{
+    f16, AnyChannel, AnyChannels, Blocks, Compression as ExrCompression, Encoding, FlatSamples,
+    Image as ExrImage, Layer as ExrLayer, LayerAttributes, LineOrder, SmallVec, WritableImage,
+};
+
+use crate::image::Image;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SampleFormat {
+    #[default]
+    Half,
+    Float,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Compression {
+    None,
+    Rle,
+    #[default]
+    Zip,
+    Piz,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Channels {
+    #[default]
+    Rgb,
+    // A single `Z` channel taken from the red component, as used for depth.
+    Z,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Layer<'a> {
+    pub name: &'a str,
+    pub image: &'a Image,
+    pub format: SampleFormat,
+    pub channels: Channels,
+}
+
+impl<'a> Layer<'a> {
+    pub fn new(name: &'a str, image: &'a Image, format: SampleFormat) -> Self {
+        Self {
+            name,
+            image,
+            format,
+            channels: Channels::Rgb,
+        }
+    }
+}
+
+impl Compression {
+    fn encoding(self) -> Encoding {
+        Encoding {
+            compression: match self {
+                Self::None => ExrCompression::Uncompressed,
+                Self::Rle => ExrCompression::RLE,
+                Self::Zip => ExrCompression::ZIP16,
+                Self::Piz => ExrCompression::PIZ,
+            },
+            blocks: Blocks::ScanLines,
+            line_order: LineOrder::Increasing,
+        }
+    }
+}
+
+pub fn write_layers(
+    path: impl AsRef<Path>,
+    layers: &[Layer],
+    compression: Compression,
+) -> io::Result<()> {
+    let Some(first) = layers.first() else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "an EXR file needs at least one layer".to_string(),
+        ));
+    };
+    let size = (first.image.width, first.image.height);
+    let mut channels = SmallVec::new();
+    for layer in layers {
+        let (name, image) = (layer.name, layer.image);
+        if (image.width, image.height) != size {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("layer {name} does not match the image size"),
+            ));
+        }
+        let names: &[&str] = match layer.channels {
+            Channels::Rgb => &["R", "G", "B"],
+            Channels::Z => &["Z"],
+        };
+        for (index, channel) in names.iter().enumerate() {
+            let values = image.pixels.iter().map(|pixel| pixel.as_rgb()[index]);
+            let samples = match layer.format {
+                SampleFormat::Half => FlatSamples::F16(values.map(f16::from_f32).collect()),
+                SampleFormat::Float => FlatSamples::F32(values.collect()),
+            };
+            let name = if name.is_empty() {
+                channel.to_string()
+            } else {
+                format!("{name}.{channel}")
+            };
+            channels.push(AnyChannel::new(name.as_str(), samples));
+        }
+    }
+    let layer = ExrLayer::new(
+        size,
+        LayerAttributes::default(),
+        compression.encoding(),
+        AnyChannels::sort(channels),
+    );
+    ExrImage::from_layer(layer)
+        .write()
+        .to_file(path)
+        .map_err(io::Error::other)
+}
+
+impl Image {
+    pub fn write_exr(
+        &self,
+        path: impl AsRef<Path>,
+        format: SampleFormat,
+        compression: Compression,
+    ) -> io::Result<()> {
+        write_layers(path, &[Layer::new("", self, format)], compression)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, process};
+
+    use exr::prelude::read_all_flat_layers_from_file;
+
+    use super::*;
+    use crate::colour::Colour;
+
+    fn channel(path: &Path, name: &str) -> Vec<f32> {
+        let image = read_all_flat_layers_from_file(path).unwrap();
+        image.layer_data[0]
+            .channel_data
+            .list
+            .iter()
+            .find(|channel| channel.name.to_string() == name)
+            .unwrap()
+            .sample_data
+            .values_as_f32()
+            .collect()
+    }
+
+    #[test]
+    fn test_layers_round_trip() {
+        let mut beauty = Image::new(3, 2, Colour::new(0.25, 0.5, 1.));
+        beauty.pixels[4] = Colour::new(100.5, -2., 0.);
+        let depth = Image::new(3, 2, Colour::gray(17.123_456));
+        for (format, compression) in [
+            (SampleFormat::Float, Compression::Zip),
+            (SampleFormat::Float, Compression::Rle),
+            (SampleFormat::Half, Compression::Piz),
+            (SampleFormat::Half, Compression::None),
+        ] {
+            let path = env::temp_dir().join(format!(
+                "layers-{}-{format:?}-{compression:?}.exr",
+                process::id()
+            ));
+            let layers = [
+                Layer::new("", &beauty, format),
+                Layer {
+                    channels: Channels::Z,
+                    ..Layer::new("depth", &depth, SampleFormat::Float)
+                },
+                Layer::new("albedo", &depth, format),
+            ];
+            write_layers(&path, &layers, compression).unwrap();
+            let red = channel(&path, "R");
+            assert_eq!(red.len(), 6);
+            assert_eq!(red[4], 100.5);
+            assert_eq!(channel(&path, "G")[4], -2.);
+            assert_eq!(channel(&path, "depth.Z")[0], 17.123_456);
+            let albedo = channel(&path, "albedo.B");
+            match format {
+                SampleFormat::Float => assert_eq!(albedo[0], 17.123_456),
+                SampleFormat::Half => assert!((albedo[0] - 17.123_456).abs() < 0.01),
+            }
+            let names = read_all_flat_layers_from_file(&path).unwrap().layer_data[0]
+                .channel_data
+                .list
+                .len();
+            assert_eq!(names, 7);
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_rejects_mismatched_layers() {
+        let path = env::temp_dir().join(format!("mismatched-{}.exr", process::id()));
+        let (small, wide) = (
+            Image::new(2, 2, Colour::default()),
+            Image::new(3, 2, Colour::default()),
+        );
+        let error = write_layers(
+            &path,
+            &[
+                Layer::new("", &small, SampleFormat::Half),
+                Layer::new("albedo", &wide, SampleFormat::Half),
+            ],
+            Compression::Zip,
+        )
+        .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        assert!(write_layers(&path, &[], Compression::Zip).is_err());
+    }
+}
@@ -1,8 +1,10 @@
 use std::fmt::Debug;
 
-use crate::{ray::Ray, vector::Vec3D, material::Material};
+use crate::{
+    checkpoint::Fingerprint, constants::RAY_OFFSET, material::Material, ray::Ray, vector::Vec3D,
+};
 
-pub trait Object: Debug {
+pub trait Object: Fingerprint + Debug + Send + Sync {
     fn intersections(&self, ray: &Ray) -> Vec<Intersection>;
     fn extent(&self) -> f32;
     fn centre(&self) -> Vec3D;
@@ -12,10 +14,28 @@ pub trait Object: Debug {
 #[derive(Debug)]
 pub struct Intersection {
     pub position: Vec3D,
+    pub local_position: Vec3D,
     pub distance: f32,
     pub normal: Vec3D,
+    pub tangent: Vec3D,
+    pub uv: (f32, f32),
     pub object: Box<dyn Object>,
-    pub ray: Ray
+    pub ray: Ray,
+}
+
+impl Intersection {
+    pub fn spawn_ray(&self, direction: Vec3D) -> Ray {
+        let offset = if direction.dot(self.normal) < 0. {
+            -RAY_OFFSET
+        } else {
+            RAY_OFFSET
+        };
+        Ray::new(self.position + self.normal * offset, direction)
+    }
+
+    pub fn same_side_as_viewer(&self, direction: Vec3D) -> bool {
+        direction.dot(self.normal) * self.ray.direction.dot(self.normal) < 0.
+    }
 }
 
 impl PartialOrd for Intersection {
@@ -0,0 +1,837 @@
+use std::{
+    io::{self, ErrorKind},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    aov::Aov,
+    camera::Camera,
+    checkpoint::{fingerprint, Checkpoint, Fingerprint, Fingerprinter},
+    colour::Colour,
+    film::{Film, FilmTile},
+    filter::Filter,
+    image::Image,
+    integrator::trace,
+    sampler::Sampler,
+    scene::Scene,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct PixelStatistics {
+    pub count: usize,
+    pub mean: f32,
+    pub m2: f32,
+}
+
+impl PixelStatistics {
+    pub fn add(&mut self, colour: Colour) {
+        let value = colour.luminance();
+        self.count += 1;
+        let delta = value - self.mean;
+        self.mean += delta / self.count as f32;
+        self.m2 += delta * (value - self.mean);
+    }
+
+    pub fn variance(&self) -> f32 {
+        if self.count < 2 {
+            return f32::INFINITY;
+        }
+        self.m2 / (self.count - 1) as f32
+    }
+
+    pub fn error(&self) -> f32 {
+        (self.variance() / self.count as f32).sqrt() / self.mean.abs().max(1e-2)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AdaptiveSampling {
+    pub minimum_samples: usize,
+    pub maximum_samples: usize,
+    pub batch_size: usize,
+    pub threshold: f32,
+}
+
+impl Default for AdaptiveSampling {
+    fn default() -> Self {
+        Self {
+            minimum_samples: 4,
+            maximum_samples: 64,
+            batch_size: 4,
+            threshold: 0.02,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderSettings {
+    pub width: usize,
+    pub height: usize,
+    pub pixel_to_world: f32,
+    pub filter: Filter,
+    pub tile_size: usize,
+    pub adaptive: AdaptiveSampling,
+    pub aovs: &'static [Aov],
+}
+
+// Only the settings that decide what each sample adds to the image. The tile size and adaptive
+// limits only change how long a render runs, so a checkpoint resumes with different ones.
+impl Fingerprint for RenderSettings {
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        state.debug(&(
+            self.width,
+            self.height,
+            self.pixel_to_world,
+            self.filter,
+            self.aovs,
+        ));
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SnapshotInterval {
+    Passes(usize),
+    Time(Duration),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Progressive {
+    pub snapshot: SnapshotInterval,
+    pub time_limit: Option<Duration>,
+    pub pass_limit: Option<usize>,
+    pub noise_target: Option<f32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    Converged,
+    PassLimit,
+    TimeLimit,
+    NoiseTarget,
+}
+
+#[derive(Debug, Clone)]
+pub struct Renderer {
+    pub settings: RenderSettings,
+    pub film: Film,
+    pub statistics: Vec<PixelStatistics>,
+    pub aovs: Vec<(Aov, Film)>,
+}
+
+impl Renderer {
+    pub fn new(settings: RenderSettings) -> Self {
+        let count = settings.width * settings.height;
+        Self {
+            settings,
+            film: Film::new(
+                settings.width,
+                settings.height,
+                accumulation(settings.filter),
+            ),
+            statistics: vec![PixelStatistics::default(); count],
+            aovs: settings
+                .aovs
+                .iter()
+                .map(|aov| {
+                    (
+                        *aov,
+                        Film::new(
+                            settings.width,
+                            settings.height,
+                            accumulation(aov.filter(settings.filter)),
+                        ),
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    pub fn checkpoint(&self, scene: &Scene, camera: &Camera, sampler: &dyn Sampler) -> Checkpoint {
+        let (sums, weights) = self.film.buffers();
+        Checkpoint {
+            scene_hash: fingerprint(&(scene, camera)),
+            settings_hash: fingerprint(&(&self.settings, sampler)),
+            width: self.settings.width,
+            height: self.settings.height,
+            sums: sums.to_vec(),
+            weights: weights.to_vec(),
+            statistics: self.statistics.clone(),
+            aovs: self
+                .aovs
+                .iter()
+                .map(|(_, film)| {
+                    let (sums, weights) = film.buffers();
+                    (sums.to_vec(), weights.to_vec())
+                })
+                .collect(),
+        }
+    }
+
+    pub fn resume(
+        settings: RenderSettings,
+        scene: &Scene,
+        camera: &Camera,
+        sampler: &dyn Sampler,
+        checkpoint: Checkpoint,
+    ) -> io::Result<Self> {
+        let scene_hash = fingerprint(&(scene, camera));
+        if checkpoint.scene_hash != scene_hash {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "checkpoint was written for a different scene (hash {:016x}, expected {scene_hash:016x})",
+                    checkpoint.scene_hash
+                ),
+            ));
+        }
+        if checkpoint.settings_hash != fingerprint(&(&settings, sampler)) {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                "checkpoint was written with different render settings or sampler".to_string(),
+            ));
+        }
+        let mismatch = || {
+            io::Error::new(
+                ErrorKind::InvalidData,
+                "checkpoint buffers do not match the image size".to_string(),
+            )
+        };
+        if (checkpoint.width, checkpoint.height) != (settings.width, settings.height)
+            || checkpoint.aovs.len() != settings.aovs.len()
+        {
+            return Err(mismatch());
+        }
+        let film = Film::from_buffers(
+            settings.width,
+            settings.height,
+            accumulation(settings.filter),
+            checkpoint.sums,
+            checkpoint.weights,
+        )
+        .ok_or_else(mismatch)?;
+        let aovs = settings
+            .aovs
+            .iter()
+            .zip(checkpoint.aovs)
+            .map(|(aov, (sums, weights))| {
+                Film::from_buffers(
+                    settings.width,
+                    settings.height,
+                    accumulation(aov.filter(settings.filter)),
+                    sums,
+                    weights,
+                )
+                .map(|film| (*aov, film))
+                .ok_or_else(mismatch)
+            })
+            .collect::<io::Result<Vec<(Aov, Film)>>>()?;
+        Ok(Self {
+            settings,
+            film,
+            statistics: checkpoint.statistics,
+            aovs,
+        })
+    }
+
+    pub fn active(&self, x: usize, y: usize) -> bool {
+        self.active_below(x, y, self.settings.adaptive.maximum_samples)
+    }
+
+    fn active_below(&self, x: usize, y: usize, maximum_samples: usize) -> bool {
+        let adaptive = self.settings.adaptive;
+        let statistics = self.statistics[y * self.settings.width + x];
+        statistics.count < adaptive.minimum_samples
+            || (statistics.count < maximum_samples && statistics.error() > adaptive.threshold)
+    }
+
+    pub fn render_pass(
+        &mut self,
+        scene: &Scene,
+        camera: &Camera,
+        sampler: &mut dyn Sampler,
+    ) -> usize {
+        let maximum_samples = self.settings.adaptive.maximum_samples;
+        self.pass(scene, camera, sampler, maximum_samples)
+    }
+
+    fn pass(
+        &mut self,
+        scene: &Scene,
+        camera: &Camera,
+        sampler: &mut dyn Sampler,
+        maximum_samples: usize,
+    ) -> usize {
+        let RenderSettings {
+            width,
+            height,
+            pixel_to_world,
+            tile_size,
+            adaptive,
+            ..
+        } = self.settings;
+        let mut taken = 0;
+        for tile_y in (0..height).step_by(tile_size) {
+            for tile_x in (0..width).step_by(tile_size) {
+                let mut tile = self.film.tile((tile_x, tile_y), (tile_size, tile_size));
+                let mut aov_tiles = self
+                    .aovs
+                    .iter()
+                    .map(|(_, film)| film.tile((tile_x, tile_y), (tile_size, tile_size)))
+                    .collect::<Vec<FilmTile>>();
+                for y in tile_y..(tile_y + tile_size).min(height) {
+                    for x in tile_x..(tile_x + tile_size).min(width) {
+                        if !self.active_below(x, y, maximum_samples) {
+                            continue;
+                        }
+                        let index = y * width + x;
+                        let statistics = &mut self.statistics[index];
+                        let batch = adaptive
+                            .batch_size
+                            .max(1)
+                            .min(maximum_samples.saturating_sub(statistics.count));
+                        for _ in 0..batch {
+                            let first = statistics.count == 0;
+                            sampler.start_sample((x, y), statistics.count);
+                            let (jitter_x, jitter_y) = sampler.next_pair();
+                            let (film_x, film_y) = (x as f32 + jitter_x, y as f32 + jitter_y);
+                            let ray = camera.ray_from_position(
+                                (film_x - width as f32 / 2.) * pixel_to_world,
+                                (film_y - height as f32 / 2.) * pixel_to_world,
+                            );
+                            let path = trace(scene, &ray, sampler);
+                            let colour = path.colour();
+                            tile.add_sample((film_x, film_y), colour);
+                            for ((aov, _), tile) in self.aovs.iter().zip(&mut aov_tiles) {
+                                if !aov.single_sample() {
+                                    tile.add_sample((film_x, film_y), aov.value(&path));
+                                } else if first {
+                                    let centre = (x as f32 + 0.5, y as f32 + 0.5);
+                                    tile.add_sample(centre, aov.value(&path));
+                                }
+                            }
+                            statistics.add(colour);
+                        }
+                        taken += batch;
+                    }
+                }
+                self.film.merge(&tile);
+                for ((_, film), tile) in self.aovs.iter_mut().zip(&aov_tiles) {
+                    film.merge(tile);
+                }
+            }
+        }
+        taken
+    }
+
+    pub fn render(&mut self, scene: &Scene, camera: &Camera, sampler: &mut dyn Sampler) -> usize {
+        let mut total = 0;
+        loop {
+            let taken = self.render_pass(scene, camera, sampler);
+            if taken == 0 {
+                return total;
+            }
+            total += taken;
+        }
+    }
+
+    // Passes keep accumulating until a limit or the noise target is reached. With a pass or time
+    // limit, pixels still above the adaptive threshold keep sampling past `maximum_samples`, so the
+    // render only stops with `StopReason::Converged` once every pixel meets the threshold. Without
+    // either limit `maximum_samples` still bounds every pixel.
+    pub fn render_progressive(
+        &mut self,
+        scene: &Scene,
+        camera: &Camera,
+        sampler: &mut dyn Sampler,
+        progressive: Progressive,
+        mut snapshot: impl FnMut(&Self, usize),
+    ) -> StopReason {
+        let start = Instant::now();
+        let mut last_snapshot = start;
+        let maximum_samples =
+            if progressive.pass_limit.is_some() || progressive.time_limit.is_some() {
+                usize::MAX
+            } else {
+                self.settings.adaptive.maximum_samples
+            };
+        let mut pass = 0;
+        loop {
+            pass += 1;
+            if self.pass(scene, camera, sampler, maximum_samples) == 0 {
+                return StopReason::Converged;
+            }
+            let due = match progressive.snapshot {
+                SnapshotInterval::Passes(passes) => pass % passes.max(1) == 0,
+                SnapshotInterval::Time(interval) => last_snapshot.elapsed() >= interval,
+            };
+            if due {
+                snapshot(self, pass);
+                last_snapshot = Instant::now();
+            }
+            if progressive
+                .noise_target
+                .is_some_and(|target| self.noise() <= target)
+            {
+                return StopReason::NoiseTarget;
+            }
+            if progressive.pass_limit.is_some_and(|limit| pass >= limit) {
+                return StopReason::PassLimit;
+            }
+            if progressive
+                .time_limit
+                .is_some_and(|limit| start.elapsed() >= limit)
+            {
+                return StopReason::TimeLimit;
+            }
+        }
+    }
+
+    // Mean relative error over all pixels. A pixel with fewer than two samples has no variance
+    // estimate yet and counts as infinitely noisy, so a noise target can only be met once every
+    // pixel has at least two samples.
+    pub fn noise(&self) -> f32 {
+        self.statistics
+            .iter()
+            .map(PixelStatistics::error)
+            .sum::<f32>()
+            / self.statistics.len() as f32
+    }
+
+    pub fn image(&self) -> Image {
+        self.film.resolve(self.settings.filter)
+    }
+
+    pub fn aov_images(&self) -> Vec<(Aov, Image)> {
+        self.aovs
+            .iter()
+            .map(|(aov, film)| (*aov, film.resolve(aov.filter(self.settings.filter))))
+            .collect()
+    }
+
+    pub fn sample_heatmap(&self) -> Image {
+        let maximum = self.settings.adaptive.maximum_samples.max(1) as f32;
+        Image {
+            width: self.settings.width,
+            height: self.settings.height,
+            pixels: self
+                .statistics
+                .iter()
+                .map(|statistics| {
+                    let t = 2. * statistics.count as f32 / maximum - 1.;
+                    Colour::new(t.clamp(0., 1.), 1. - t.abs(), (-t).clamp(0., 1.))
+                })
+                .collect(),
+        }
+    }
+}
+
+// Adaptive sampling gives neighbouring pixels very different sample counts. Splatting each
+// sample through a wide filter would let a densely sampled pixel outweigh a sparse neighbour, so
+// wide filters accumulate per-pixel means and apply the filter to those when resolving the image.
+fn accumulation(filter: Filter) -> Filter {
+    if filter.radius() > 0.5 {
+        Filter::default()
+    } else {
+        filter
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        background::Background, constants::ENVIRONMENT_DISTANCE, lighting::PointLight,
+        material::Material, quaternion::Quaternion, sampler::SobolSampler, sphere::Sphere,
+        vector::Vec3D,
+    };
+
+    fn settings() -> RenderSettings {
+        RenderSettings {
+            width: 16,
+            height: 12,
+            pixel_to_world: 0.5,
+            filter: Filter::default(),
+            tile_size: 5,
+            adaptive: AdaptiveSampling {
+                minimum_samples: 4,
+                maximum_samples: 32,
+                batch_size: 4,
+                threshold: 0.01,
+            },
+            aovs: &[],
+        }
+    }
+
+    fn camera() -> Camera {
+        Camera::new(
+            Vec3D::default(),
+            Quaternion::from_axis_angle(Vec3D::X, -std::f32::consts::FRAC_PI_2),
+            5.,
+        )
+    }
+
+    #[test]
+    fn test_wide_filters_adapt_per_pixel() {
+        let scene = Scene {
+            objects: vec![Box::new(Sphere::new(
+                Vec3D::new(0., 0., -20.),
+                3.,
+                Material::default(),
+            ))],
+            lights: vec![PointLight::new(Vec3D::new(10., 10., 0.), 1.)],
+            background: Background::Solid(Colour::gray(0.5)),
+            ..Default::default()
+        };
+        let mut renderer = Renderer::new(RenderSettings {
+            filter: Filter::Mitchell {
+                radius: 2.,
+                b: 1. / 3.,
+                c: 1. / 3.,
+            },
+            ..settings()
+        });
+        renderer.render(&scene, &camera(), &mut SobolSampler::new(0));
+        let corner = renderer.statistics[0].count;
+        let centre = renderer.statistics[6 * 16 + 8].count;
+        assert_eq!(corner, settings().adaptive.minimum_samples);
+        assert!(centre > corner, "{centre} {corner}");
+        assert!(
+            (renderer.image().pixel(0, 0) - Colour::gray(0.5))
+                .luminance()
+                .abs()
+                < 1e-5
+        );
+    }
+
+    #[test]
+    fn test_statistics() {
+        let mut statistics = PixelStatistics::default();
+        assert_eq!(statistics.error(), f32::INFINITY);
+        for value in [1., 2., 3., 4.] {
+            statistics.add(Colour::gray(value));
+        }
+        assert!((statistics.mean - 2.5).abs() < 1e-5);
+        assert!((statistics.variance() - 5. / 3.).abs() < 1e-4);
+        let mut flat = PixelStatistics::default();
+        (0..4).for_each(|_| flat.add(Colour::gray(0.3)));
+        assert!(flat.error() < 1e-4);
+    }
+
+    #[test]
+    fn test_flat_background_stops_at_minimum() {
+        let scene = Scene {
+            background: Background::Solid(Colour::new(0.2, 0.7, 0.8)),
+            ..Default::default()
+        };
+        let mut renderer = Renderer::new(settings());
+        let taken = renderer.render(&scene, &camera(), &mut SobolSampler::new(0));
+        assert_eq!(taken, 16 * 12 * 4);
+        assert_eq!(renderer.image().pixel(3, 4), Colour::new(0.2, 0.7, 0.8));
+        assert!(renderer
+            .sample_heatmap()
+            .pixels
+            .iter()
+            .all(|pixel| *pixel == Colour::new(0., 0.25, 0.75)));
+    }
+
+    #[test]
+    fn test_noisy_pixels_get_more_samples() {
+        let scene = Scene {
+            objects: vec![Box::new(Sphere::new(
+                Vec3D::new(0., 0., -20.),
+                3.,
+                Material::default(),
+            ))],
+            lights: vec![PointLight::new(Vec3D::new(10., 10., 0.), 1.)],
+            background: Background::Gradient {
+                bottom: Colour::gray(0.),
+                top: Colour::gray(1.),
+            },
+            ..Default::default()
+        };
+        let mut renderer = Renderer::new(settings());
+        renderer.render(&scene, &camera(), &mut SobolSampler::new(0));
+        let centre = renderer.statistics[6 * 16 + 8].count;
+        let corner = renderer.statistics[0].count;
+        assert!(centre > corner, "{centre} {corner}");
+        assert!(renderer
+            .statistics
+            .iter()
+            .all(|statistics| (4..=32).contains(&statistics.count)));
+    }
+
+    #[test]
+    fn test_progressive_stopping() {
+        let scene = Scene {
+            objects: vec![Box::new(Sphere::new(
+                Vec3D::new(0., 0., -20.),
+                3.,
+                Material::default(),
+            ))],
+            background: Background::Solid(Colour::gray(1.)),
+            ..Default::default()
+        };
+        let progressive = Progressive {
+            snapshot: SnapshotInterval::Passes(2),
+            time_limit: None,
+            pass_limit: Some(5),
+            noise_target: None,
+        };
+        let mut renderer = Renderer::new(settings());
+        let mut snapshots = Vec::new();
+        let reason = renderer.render_progressive(
+            &scene,
+            &camera(),
+            &mut SobolSampler::new(0),
+            progressive,
+            |renderer, pass| snapshots.push((pass, renderer.statistics[0].count)),
+        );
+        assert_eq!(reason, StopReason::PassLimit);
+        assert_eq!(snapshots, vec![(2, 4), (4, 4)]);
+
+        let mut renderer = Renderer::new(settings());
+        let reason = renderer.render_progressive(
+            &scene,
+            &camera(),
+            &mut SobolSampler::new(0),
+            Progressive {
+                time_limit: Some(Duration::ZERO),
+                pass_limit: None,
+                ..progressive
+            },
+            |_, _| {},
+        );
+        assert_eq!(reason, StopReason::TimeLimit);
+        assert_eq!(renderer.statistics[6 * 16 + 8].count, 4);
+
+        let mut renderer = Renderer::new(settings());
+        let reason = renderer.render_progressive(
+            &scene,
+            &camera(),
+            &mut SobolSampler::new(0),
+            Progressive {
+                noise_target: Some(0.2),
+                pass_limit: None,
+                ..progressive
+            },
+            |_, _| {},
+        );
+        assert_eq!(reason, StopReason::NoiseTarget);
+        assert!(renderer.noise() <= 0.2);
+        let reason = renderer.render_progressive(
+            &scene,
+            &camera(),
+            &mut SobolSampler::new(0),
+            Progressive {
+                pass_limit: None,
+                ..progressive
+            },
+            |_, _| {},
+        );
+        assert_eq!(reason, StopReason::Converged);
+    }
+
+    #[test]
+    fn test_limited_progressive_passes_the_cap() {
+        let scene = Scene {
+            objects: vec![Box::new(Sphere::new(
+                Vec3D::new(0., 0., -20.),
+                3.,
+                Material::default(),
+            ))],
+            lights: vec![PointLight::new(Vec3D::new(10., 10., 0.), 1.)],
+            background: Background::Gradient {
+                bottom: Colour::gray(0.),
+                top: Colour::gray(1.),
+            },
+            ..Default::default()
+        };
+        let mut renderer = Renderer::new(settings());
+        let reason = renderer.render_progressive(
+            &scene,
+            &camera(),
+            &mut SobolSampler::new(0),
+            Progressive {
+                snapshot: SnapshotInterval::Passes(1),
+                time_limit: None,
+                pass_limit: Some(20),
+                noise_target: None,
+            },
+            |_, _| {},
+        );
+        assert_eq!(reason, StopReason::PassLimit);
+        let centre = renderer.statistics[6 * 16 + 8].count;
+        assert!(centre > settings().adaptive.maximum_samples, "{centre}");
+        assert_eq!(renderer.statistics[0].count, 4);
+    }
+
+    #[test]
+    fn test_resume_matches_uninterrupted_render() {
+        let scene = Scene {
+            objects: vec![Box::new(Sphere::new(
+                Vec3D::new(0., 0., -20.),
+                3.,
+                Material::default(),
+            ))],
+            lights: vec![PointLight::new(Vec3D::new(10., 10., 0.), 1.)],
+            ..Default::default()
+        };
+        let mut uninterrupted = Renderer::new(settings());
+        let mut interrupted = Renderer::new(settings());
+        for _ in 0..3 {
+            uninterrupted.render_pass(&scene, &camera(), &mut SobolSampler::new(0));
+        }
+        interrupted.render_pass(&scene, &camera(), &mut SobolSampler::new(0));
+        let mut bytes = Vec::new();
+        interrupted
+            .checkpoint(&scene, &camera(), &SobolSampler::new(0))
+            .write(&mut bytes)
+            .unwrap();
+        let checkpoint = Checkpoint::read(bytes.as_slice()).unwrap();
+        let mut resumed = Renderer::resume(
+            settings(),
+            &scene,
+            &camera(),
+            &SobolSampler::new(0),
+            checkpoint.clone(),
+        )
+        .unwrap();
+        for _ in 0..2 {
+            resumed.render_pass(&scene, &camera(), &mut SobolSampler::new(0));
+        }
+        assert_eq!(resumed.image().pixels, uninterrupted.image().pixels);
+        assert_eq!(resumed.statistics, uninterrupted.statistics);
+
+        let other = Scene {
+            lights: vec![PointLight::new(Vec3D::new(10., 10., 0.), 2.)],
+            ..Default::default()
+        };
+        let error = Renderer::resume(
+            settings(),
+            &other,
+            &camera(),
+            &SobolSampler::new(0),
+            checkpoint.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+        assert!(error.to_string().contains("different scene"));
+        let moved = Camera {
+            position: Vec3D::new(0., 0.5, 0.),
+            ..camera()
+        };
+        assert!(Renderer::resume(
+            settings(),
+            &scene,
+            &moved,
+            &SobolSampler::new(0),
+            checkpoint.clone()
+        )
+        .is_err());
+        let sampler = SobolSampler::new(1);
+        let error = Renderer::resume(settings(), &scene, &camera(), &sampler, checkpoint.clone())
+            .unwrap_err();
+        assert!(error.to_string().contains("sampler"));
+        let longer = RenderSettings {
+            tile_size: 3,
+            adaptive: AdaptiveSampling {
+                maximum_samples: 64,
+                ..settings().adaptive
+            },
+            ..settings()
+        };
+        let mut extended = Renderer::resume(
+            longer,
+            &scene,
+            &camera(),
+            &SobolSampler::new(0),
+            checkpoint.clone(),
+        )
+        .unwrap();
+        extended.render(&scene, &camera(), &mut SobolSampler::new(0));
+        assert!(extended
+            .statistics
+            .iter()
+            .any(|statistics| statistics.count > 32));
+        let wider = RenderSettings {
+            width: 17,
+            ..settings()
+        };
+        assert!(
+            Renderer::resume(wider, &scene, &camera(), &SobolSampler::new(0), checkpoint).is_err()
+        );
+    }
+
+    #[test]
+    fn test_aov_passes() {
+        let scene = Scene {
+            objects: vec![Box::new(Sphere::new(
+                Vec3D::new(0., 0., -20.),
+                3.,
+                Material::default(),
+            ))],
+            lights: vec![PointLight::new(Vec3D::new(10., 10., 0.), 1.)],
+            background: Background::Solid(Colour::gray(0.5)),
+            ..Default::default()
+        };
+        let settings = RenderSettings {
+            filter: Filter::Mitchell {
+                radius: 2.,
+                b: 1. / 3.,
+                c: 1. / 3.,
+            },
+            aovs: &Aov::ALL,
+            ..settings()
+        };
+        let mut renderer = Renderer::new(settings);
+        renderer.render(&scene, &camera(), &mut SobolSampler::new(0));
+        let images = renderer.aov_images();
+        assert_eq!(
+            images.iter().map(|(aov, _)| *aov).collect::<Vec<Aov>>(),
+            Aov::ALL
+        );
+        let pixel = |aov: Aov, x, y| {
+            images
+                .iter()
+                .find(|(other, _)| *other == aov)
+                .unwrap()
+                .1
+                .pixel(x, y)
+        };
+        assert!(
+            (pixel(Aov::ObjectId, 8, 6) - crate::aov::id_colour(0))
+                .luminance()
+                .abs()
+                < 1e-5
+        );
+        assert_eq!(pixel(Aov::ObjectId, 0, 0), Colour::default());
+        let ids = &images
+            .iter()
+            .find(|(aov, _)| *aov == Aov::ObjectId)
+            .unwrap()
+            .1;
+        assert!(ids
+            .pixels
+            .iter()
+            .all(|id| *id == Colour::default() || *id == crate::aov::id_colour(0)));
+        assert!((pixel(Aov::Depth, 0, 0).red / ENVIRONMENT_DISTANCE - 1.).abs() < 1e-4);
+        assert!((pixel(Aov::Depth, 8, 6).red - 17.).abs() < 0.5);
+        assert!(pixel(Aov::Normal, 8, 6).blue > 0.8);
+        let image = renderer.image();
+        for (x, y) in [(0, 0), (8, 6), (10, 4)] {
+            let sum = pixel(Aov::Direct, x, y) + pixel(Aov::Indirect, x, y);
+            assert!((sum - image.pixel(x, y)).luminance().abs() < 1e-4);
+        }
+
+        let checkpoint = renderer.checkpoint(&scene, &camera(), &SobolSampler::new(0));
+        let resumed = Renderer::resume(
+            settings,
+            &scene,
+            &camera(),
+            &SobolSampler::new(0),
+            checkpoint,
+        )
+        .unwrap();
+        assert_eq!(resumed.aov_images(), images);
+    }
+}
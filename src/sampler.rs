@@ -0,0 +1,681 @@
+use std::{fmt::Debug, sync::Arc};
+
+use crate::{
+    checkpoint::{Fingerprint, Fingerprinter},
+    random::Rng,
+};
+
+const PRIMES: [u32; 32] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97,
+    101, 103, 107, 109, 113, 127, 131,
+];
+const ONE_MINUS_EPSILON: f32 = 1. - f32::EPSILON / 2.;
+
+// Fingerprints cover the configuration that decides which values a sampler produces, not its
+// position within the current sample.
+pub trait Sampler: Fingerprint + Debug + Send + Sync {
+    fn start_sample(&mut self, pixel: (usize, usize), index: usize);
+
+    fn start_dimension(&mut self, dimension: usize);
+
+    fn next_f32(&mut self) -> f32;
+
+    fn next_pair(&mut self) -> (f32, f32) {
+        (self.next_f32(), self.next_f32())
+    }
+}
+
+fn hash(values: &[u64]) -> u64 {
+    values.iter().fold(0x9e37_79b9_7f4a_7c15, |state, value| {
+        let mut state = (state ^ value).wrapping_add(0x9e37_79b9_7f4a_7c15);
+        state = (state ^ (state >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        state = (state ^ (state >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        state ^ (state >> 31)
+    })
+}
+
+fn hash_f32(values: &[u64]) -> f32 {
+    (hash(values) >> 40) as f32 / (1 << 24) as f32
+}
+
+fn to_f32(bits: u32) -> f32 {
+    (bits as f32 * (1. / 4_294_967_296.)).min(ONE_MINUS_EPSILON)
+}
+
+fn permute(mut index: u32, count: u32, seed: u32) -> u32 {
+    if count <= 1 {
+        return 0;
+    }
+    let mut mask = count - 1;
+    mask |= mask >> 1;
+    mask |= mask >> 2;
+    mask |= mask >> 4;
+    mask |= mask >> 8;
+    mask |= mask >> 16;
+    loop {
+        index ^= seed;
+        index = index.wrapping_mul(0xe170_893d);
+        index ^= seed >> 16;
+        index ^= (index & mask) >> 4;
+        index ^= seed >> 8;
+        index = index.wrapping_mul(0x0929_eb3f);
+        index ^= seed >> 23;
+        index ^= (index & mask) >> 1;
+        index = index.wrapping_mul(1 | seed >> 27);
+        index = index.wrapping_mul(0x6935_fa69);
+        index ^= (index & mask) >> 11;
+        index = index.wrapping_mul(0x74dc_b303);
+        index ^= (index & mask) >> 2;
+        index = index.wrapping_mul(0x9e50_1cc3);
+        index ^= (index & mask) >> 2;
+        index = index.wrapping_mul(0xc860_a3df);
+        index &= mask;
+        index ^= index >> 5;
+        if index < count {
+            return (index.wrapping_add(seed)) % count;
+        }
+    }
+}
+
+// A bare random stream ignores sample and dimension positions, which is all that estimators
+// outside the renderer need.
+impl Sampler for Rng {
+    fn start_sample(&mut self, _: (usize, usize), _: usize) {}
+
+    fn start_dimension(&mut self, _: usize) {}
+
+    fn next_f32(&mut self) -> f32 {
+        Rng::next_f32(self)
+    }
+
+    fn next_pair(&mut self) -> (f32, f32) {
+        Rng::next_pair(self)
+    }
+}
+
+impl Fingerprint for Rng {
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        state.debug(self);
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct IndependentSampler {
+    pub seed: u64,
+    pixel: (usize, usize),
+    index: usize,
+    rng: Rng,
+}
+
+impl IndependentSampler {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            pixel: (0, 0),
+            index: 0,
+            rng: Rng::new(seed, 0),
+        }
+    }
+}
+
+impl Sampler for IndependentSampler {
+    fn start_sample(&mut self, pixel: (usize, usize), index: usize) {
+        self.pixel = pixel;
+        self.index = index;
+        self.start_dimension(0);
+    }
+
+    fn start_dimension(&mut self, dimension: usize) {
+        let (x, y) = self.pixel;
+        self.rng = Rng::new(
+            self.seed,
+            hash(&[x as u64, y as u64, self.index as u64, dimension as u64]),
+        );
+    }
+
+    fn next_f32(&mut self) -> f32 {
+        self.rng.next_f32()
+    }
+}
+
+impl Fingerprint for IndependentSampler {
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        state.debug(&("IndependentSampler", self.seed));
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct StratifiedSampler {
+    pub strata: (usize, usize),
+    pub seed: u64,
+    pixel: (usize, usize),
+    index: usize,
+    dimension: u64,
+}
+
+impl StratifiedSampler {
+    pub fn new(strata: (usize, usize), seed: u64) -> Option<Self> {
+        let count = strata.0.checked_mul(strata.1)?;
+        if count == 0 || u32::try_from(count).is_err() {
+            return None;
+        }
+        Some(Self {
+            strata,
+            seed,
+            pixel: (0, 0),
+            index: 0,
+            dimension: 0,
+        })
+    }
+
+    fn stratum(&mut self) -> (u32, u64) {
+        let count = (self.strata.0 * self.strata.1) as u32;
+        let key = hash(&[
+            self.pixel.0 as u64,
+            self.pixel.1 as u64,
+            self.dimension,
+            self.seed,
+        ]);
+        let stratum = permute(self.index as u32 % count, count, key as u32);
+        self.dimension += 1;
+        (stratum, key)
+    }
+}
+
+impl Sampler for StratifiedSampler {
+    fn start_sample(&mut self, pixel: (usize, usize), index: usize) {
+        self.pixel = pixel;
+        self.index = index;
+        self.dimension = 0;
+    }
+
+    fn start_dimension(&mut self, dimension: usize) {
+        self.dimension = dimension as u64;
+    }
+
+    fn next_f32(&mut self) -> f32 {
+        let count = (self.strata.0 * self.strata.1) as f32;
+        let (stratum, key) = self.stratum();
+        let jitter = hash_f32(&[key, self.index as u64]);
+        ((stratum as f32 + jitter) / count).min(ONE_MINUS_EPSILON)
+    }
+
+    fn next_pair(&mut self) -> (f32, f32) {
+        let (stratum, key) = self.stratum();
+        self.dimension += 1;
+        let (columns, rows) = (self.strata.0 as u32, self.strata.1 as u32);
+        let jitter = (
+            hash_f32(&[key, self.index as u64, 0]),
+            hash_f32(&[key, self.index as u64, 1]),
+        );
+        (
+            (((stratum % columns) as f32 + jitter.0) / columns as f32).min(ONE_MINUS_EPSILON),
+            (((stratum / columns) as f32 + jitter.1) / rows as f32).min(ONE_MINUS_EPSILON),
+        )
+    }
+}
+
+impl Fingerprint for StratifiedSampler {
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        state.debug(&("StratifiedSampler", self.strata, self.seed));
+    }
+}
+
+fn radical_inverse(base: u32, mut index: u64) -> f32 {
+    let inverse_base = 1. / base as f64;
+    let mut scale = inverse_base;
+    let mut value = 0.;
+    while index > 0 {
+        value += (index % base as u64) as f64 * scale;
+        index /= base as u64;
+        scale *= inverse_base;
+    }
+    (value as f32).min(ONE_MINUS_EPSILON)
+}
+
+#[derive(Debug, Clone)]
+pub struct HaltonSampler {
+    pub seed: u64,
+    pixel: (usize, usize),
+    index: usize,
+    dimension: usize,
+}
+
+impl HaltonSampler {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            pixel: (0, 0),
+            index: 0,
+            dimension: 0,
+        }
+    }
+}
+
+impl Sampler for HaltonSampler {
+    fn start_sample(&mut self, pixel: (usize, usize), index: usize) {
+        self.pixel = pixel;
+        self.index = index;
+        self.dimension = 0;
+    }
+
+    fn start_dimension(&mut self, dimension: usize) {
+        self.dimension = dimension;
+    }
+
+    fn next_f32(&mut self) -> f32 {
+        let dimension = self.dimension;
+        self.dimension += 1;
+        let offset = hash_f32(&[
+            self.pixel.0 as u64,
+            self.pixel.1 as u64,
+            dimension as u64,
+            self.seed,
+        ]);
+        let Some(&base) = PRIMES.get(dimension) else {
+            return hash_f32(&[offset.to_bits() as u64, self.index as u64]);
+        };
+        (radical_inverse(base, self.index as u64) + offset).fract()
+    }
+}
+
+impl Fingerprint for HaltonSampler {
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        state.debug(&("HaltonSampler", self.seed));
+    }
+}
+
+fn sobol(index: u32) -> (u32, u32) {
+    let mut direction = 1 << 31;
+    let (mut x, mut y) = (0_u32, 0_u32);
+    for bit in 0..32 {
+        if index >> bit & 1 == 1 {
+            x ^= 1 << (31 - bit);
+            y ^= direction;
+        }
+        direction ^= direction >> 1;
+    }
+    (x, y)
+}
+
+fn laine_karras(mut value: u32, seed: u32) -> u32 {
+    value = value.wrapping_add(seed);
+    value ^= value.wrapping_mul(0x6c50_b47c);
+    value ^= value.wrapping_mul(0xb82f_1e52);
+    value ^= value.wrapping_mul(0xc7af_e638);
+    value ^= value.wrapping_mul(0x8d22_f6e6);
+    value
+}
+
+fn owen_scramble(value: u32, seed: u32) -> u32 {
+    laine_karras(value.reverse_bits(), seed).reverse_bits()
+}
+
+#[derive(Debug, Clone)]
+pub struct SobolSampler {
+    pub seed: u64,
+    pixel: (usize, usize),
+    index: usize,
+    dimension: u64,
+}
+
+impl SobolSampler {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            pixel: (0, 0),
+            index: 0,
+            dimension: 0,
+        }
+    }
+
+    fn seeds(&mut self) -> [u32; 3] {
+        let key = hash(&[
+            self.pixel.0 as u64,
+            self.pixel.1 as u64,
+            self.dimension,
+            self.seed,
+        ]);
+        self.dimension += 1;
+        [key as u32, (key >> 32) as u32, hash(&[key]) as u32]
+    }
+}
+
+impl Sampler for SobolSampler {
+    fn start_sample(&mut self, pixel: (usize, usize), index: usize) {
+        self.pixel = pixel;
+        self.index = index;
+        self.dimension = 0;
+    }
+
+    fn start_dimension(&mut self, dimension: usize) {
+        self.dimension = dimension as u64;
+    }
+
+    fn next_f32(&mut self) -> f32 {
+        let [shuffle, scramble, _] = self.seeds();
+        let index = owen_scramble(self.index as u32, shuffle);
+        to_f32(owen_scramble(sobol(index).0, scramble))
+    }
+
+    fn next_pair(&mut self) -> (f32, f32) {
+        let [shuffle, scramble_x, scramble_y] = self.seeds();
+        let (x, y) = sobol(owen_scramble(self.index as u32, shuffle));
+        (
+            to_f32(owen_scramble(x, scramble_x)),
+            to_f32(owen_scramble(y, scramble_y)),
+        )
+    }
+}
+
+impl Fingerprint for SobolSampler {
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        state.debug(&("SobolSampler", self.seed));
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlueNoiseMask {
+    pub size: usize,
+    pub values: Vec<f32>,
+}
+
+impl BlueNoiseMask {
+    pub fn new(size: usize, seed: u64) -> Option<Self> {
+        let count = size.checked_mul(size).filter(|count| *count > 0)?;
+        let sigma = 1.5_f32;
+        let kernel = (0..count)
+            .map(|index| {
+                let wrap = |offset: usize| offset.min(size - offset) as f32;
+                let (dx, dy) = (wrap(index % size), wrap(index / size));
+                (-(dx * dx + dy * dy) / (2. * sigma * sigma)).exp()
+            })
+            .collect::<Vec<f32>>();
+        let splat = |energy: &mut Vec<f32>, pixel: usize, sign: f32| {
+            let (px, py) = (pixel % size, pixel / size);
+            for (index, value) in energy.iter_mut().enumerate() {
+                let dx = (index % size + size - px) % size;
+                let dy = (index / size + size - py) % size;
+                *value += sign * kernel[dy * size + dx];
+            }
+        };
+        let extreme = |energy: &[f32], pattern: &[bool], set: bool, largest: bool| {
+            (0..count)
+                .filter(|&index| pattern[index] == set)
+                .max_by(|&a, &b| {
+                    let order = energy[a].partial_cmp(&energy[b]).unwrap();
+                    if largest {
+                        order
+                    } else {
+                        order.reverse()
+                    }
+                })
+                .unwrap()
+        };
+
+        let mut rng = Rng::new(seed, 0);
+        let initial = (count / 10).max(1);
+        let mut pattern = vec![false; count];
+        let mut energy = vec![0.; count];
+        let mut placed = 0;
+        while placed < initial {
+            let index = rng.next_u32() as usize % count;
+            if !pattern[index] {
+                pattern[index] = true;
+                splat(&mut energy, index, 1.);
+                placed += 1;
+            }
+        }
+        loop {
+            let cluster = extreme(&energy, &pattern, true, true);
+            pattern[cluster] = false;
+            splat(&mut energy, cluster, -1.);
+            let void = extreme(&energy, &pattern, false, false);
+            pattern[void] = true;
+            splat(&mut energy, void, 1.);
+            if void == cluster {
+                break;
+            }
+        }
+
+        let mut ranks = vec![0; count];
+        let (mut ranked, mut ranked_energy) = (pattern.clone(), energy.clone());
+        for rank in (0..initial).rev() {
+            let cluster = extreme(&ranked_energy, &ranked, true, true);
+            ranked[cluster] = false;
+            splat(&mut ranked_energy, cluster, -1.);
+            ranks[cluster] = rank;
+        }
+        for rank in initial..count {
+            let void = extreme(&energy, &pattern, false, false);
+            pattern[void] = true;
+            splat(&mut energy, void, 1.);
+            ranks[void] = rank;
+        }
+        Some(Self {
+            size,
+            values: ranks
+                .into_iter()
+                .map(|rank| (rank as f32 + 0.5) / count as f32)
+                .collect(),
+        })
+    }
+
+    pub fn value(&self, x: usize, y: usize) -> f32 {
+        self.values[(y % self.size) * self.size + x % self.size]
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BlueNoiseSampler {
+    pub mask: Arc<BlueNoiseMask>,
+    pub seed: u64,
+    pixel: (usize, usize),
+    index: usize,
+    dimension: u64,
+}
+
+impl BlueNoiseSampler {
+    const GOLDEN: f32 = 0.618_034;
+    const PLASTIC: (f32, f32) = (0.754_877_7, 0.569_840_3);
+
+    pub fn new(mask: Arc<BlueNoiseMask>, seed: u64) -> Self {
+        Self {
+            mask,
+            seed,
+            pixel: (0, 0),
+            index: 0,
+            dimension: 0,
+        }
+    }
+
+    fn dither(&mut self) -> f32 {
+        let offset = hash(&[self.dimension, self.seed]);
+        self.dimension += 1;
+        let size = self.mask.size;
+        self.mask.value(
+            self.pixel.0 + offset as usize % size,
+            self.pixel.1 + (offset >> 32) as usize % size,
+        )
+    }
+}
+
+impl Sampler for BlueNoiseSampler {
+    fn start_sample(&mut self, pixel: (usize, usize), index: usize) {
+        self.pixel = pixel;
+        self.index = index;
+        self.dimension = 0;
+    }
+
+    fn start_dimension(&mut self, dimension: usize) {
+        self.dimension = dimension as u64;
+    }
+
+    fn next_f32(&mut self) -> f32 {
+        (self.dither() + self.index as f32 * Self::GOLDEN).fract()
+    }
+
+    fn next_pair(&mut self) -> (f32, f32) {
+        let dither = (self.dither(), self.dither());
+        (
+            (dither.0 + self.index as f32 * Self::PLASTIC.0).fract(),
+            (dither.1 + self.index as f32 * Self::PLASTIC.1).fract(),
+        )
+    }
+}
+
+impl Fingerprint for BlueNoiseSampler {
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        state.debug(&("BlueNoiseSampler", self.mask.size, self.seed));
+        state.floats(self.mask.values.iter().copied());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn integration_error(
+        sampler: &mut dyn Sampler,
+        samples: usize,
+        trials: usize,
+        function: impl Fn(f32, f32) -> f32,
+        reference: f32,
+    ) -> f32 {
+        let squared = (0..trials)
+            .map(|trial| {
+                let estimate = (0..samples)
+                    .map(|index| {
+                        sampler.start_sample((trial, trial / 7), index);
+                        let (x, y) = sampler.next_pair();
+                        function(x, y)
+                    })
+                    .sum::<f32>()
+                    / samples as f32;
+                (estimate - reference).powi(2)
+            })
+            .sum::<f32>();
+        (squared / trials as f32).sqrt()
+    }
+
+    fn samplers() -> Vec<Box<dyn Sampler>> {
+        vec![
+            Box::new(IndependentSampler::new(1)),
+            Box::new(StratifiedSampler::new((8, 8), 1).unwrap()),
+            Box::new(HaltonSampler::new(1)),
+            Box::new(SobolSampler::new(1)),
+            Box::new(BlueNoiseSampler::new(
+                Arc::new(BlueNoiseMask::new(16, 1).unwrap()),
+                1,
+            )),
+        ]
+    }
+
+    #[test]
+    fn test_samples_in_range_and_deterministic() {
+        for mut sampler in samplers() {
+            let mut values = Vec::new();
+            for pass in 0..2 {
+                for index in 0..16 {
+                    sampler.start_sample((3, 5), index);
+                    for dimension in 0..40 {
+                        let value = if dimension % 3 == 0 {
+                            sampler.next_pair().0
+                        } else {
+                            sampler.next_f32()
+                        };
+                        assert!((0. ..1.).contains(&value), "{sampler:?}");
+                        if pass == 0 {
+                            values.push(value);
+                        } else {
+                            assert_eq!(values[index * 40 + dimension], value);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_streams_are_decorrelated() {
+        for mut sampler in samplers() {
+            let mut sequence = |pixel, skip| {
+                (0..16)
+                    .map(|index| {
+                        sampler.start_sample(pixel, index);
+                        (0..skip).for_each(|_| {
+                            sampler.next_f32();
+                        });
+                        sampler.next_f32()
+                    })
+                    .collect::<Vec<f32>>()
+            };
+            let first = sequence((0, 0), 0);
+            assert_ne!(first, sequence((1, 0), 0), "{sampler:?}");
+            assert_ne!(first, sequence((0, 1), 0), "{sampler:?}");
+            assert_ne!(first, sequence((0, 0), 1), "{sampler:?}");
+        }
+    }
+
+    #[test]
+    fn test_convergence() {
+        let smooth = |x: f32, y: f32| x * y + (3. * x).sin() * y;
+        let reference = 0.25 + (1. - 3_f32.cos()) / 6.;
+        let disk = |x: f32, y: f32| if x * x + y * y < 1. { 1. } else { 0. };
+        let errors = samplers()
+            .iter_mut()
+            .map(|sampler| {
+                (
+                    integration_error(sampler.as_mut(), 64, 64, smooth, reference),
+                    integration_error(sampler.as_mut(), 64, 64, disk, std::f32::consts::FRAC_PI_4),
+                    integration_error(sampler.as_mut(), 16, 64, disk, std::f32::consts::FRAC_PI_4),
+                )
+            })
+            .collect::<Vec<(f32, f32, f32)>>();
+        let independent = errors[0];
+        for (index, error) in errors.iter().enumerate().skip(1) {
+            assert!(
+                error.0 < independent.0 * 0.5,
+                "{index} {error:?} {independent:?}"
+            );
+            assert!(error.1 < independent.1, "{index} {error:?} {independent:?}");
+        }
+        for error in errors.iter().skip(1) {
+            assert!(error.1 < error.2);
+        }
+    }
+
+    #[test]
+    fn test_blue_noise_mask() {
+        let mask = BlueNoiseMask::new(16, 2).unwrap();
+        let mut sorted = mask.values.clone();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        for (rank, value) in sorted.iter().enumerate() {
+            assert_eq!(*value, (rank as f32 + 0.5) / 256.);
+        }
+        let difference = (0..16)
+            .flat_map(|y| (0..16).map(move |x| (x, y)))
+            .map(|(x, y)| (mask.value(x, y) - mask.value(x + 1, y)).abs())
+            .sum::<f32>()
+            / 256.;
+        assert!(difference > 0.4, "{difference}");
+    }
+
+    #[test]
+    fn test_rejects_empty_patterns() {
+        assert!(StratifiedSampler::new((0, 4), 0).is_none());
+        assert!(StratifiedSampler::new((usize::MAX, 2), 0).is_none());
+        assert!(BlueNoiseMask::new(0, 0).is_none());
+    }
+
+    #[test]
+    fn test_permute_is_bijective() {
+        for count in [1, 5, 16, 37] {
+            let mut seen = (0..count)
+                .map(|index| permute(index, count, 0xdead_beef))
+                .collect::<Vec<u32>>();
+            seen.sort();
+            assert_eq!(seen, (0..count).collect::<Vec<u32>>());
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use crate::{
+    background::Background,
+    checkpoint::{Fingerprint, Fingerprinter},
+    colour::Colour,
+    environment::Environment,
+    lighting::{DirectionalLight, PointLight},
+    medium::{self, Medium, MediumEvent, Volume},
+    physics::{Intersection, Object},
+    ray::Ray,
+    sampler::Sampler,
+    vector::Vec3D,
+    voxel::GridVolume,
+};
+
+#[derive(Debug, Default)]
+pub struct Scene {
+    pub objects: Vec<Box<dyn Object>>,
+    pub lights: Vec<PointLight>,
+    pub directional_lights: Vec<DirectionalLight>,
+    pub fog: Option<Medium>,
+    pub volumes: Vec<Volume>,
+    pub grid_volumes: Vec<GridVolume>,
+    pub environment: Option<Environment>,
+    pub background: Background,
+}
+
+impl Scene {
+    pub fn intersect(&self, ray: &Ray) -> Option<Intersection> {
+        self.intersect_indexed(ray)
+            .map(|(_, intersection)| intersection)
+    }
+
+    pub fn intersect_indexed(&self, ray: &Ray) -> Option<(usize, Intersection)> {
+        self.objects
+            .iter()
+            .enumerate()
+            .flat_map(|(index, object)| {
+                object
+                    .intersections(ray)
+                    .into_iter()
+                    .map(move |intersection| (index, intersection))
+            })
+            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+    }
+
+    pub fn visible(&self, from: &Intersection, to: Vec3D) -> bool {
+        let ray = from.spawn_ray(to - from.position);
+        !self.occluded(&ray, (to - ray.origin).length())
+    }
+
+    pub fn transmittance(&self, from: Vec3D, to: Vec3D, sampler: &mut dyn Sampler) -> Colour {
+        let ray = Ray::new(from, (to - from).normalise());
+        let distance = (to - from).length();
+        if self.occluded(&ray, distance) {
+            return Colour::default();
+        }
+        self.grid_volumes.iter().fold(
+            medium::transmittance(&self.media(&ray, distance)),
+            |total, volume| total * volume.transmittance(&ray, distance, sampler),
+        )
+    }
+
+    pub fn sample_medium(
+        &self,
+        ray: &Ray,
+        distance: f32,
+        sampler: &mut dyn Sampler,
+    ) -> (Option<MediumEvent>, Colour) {
+        if self.fog.is_none() && self.volumes.is_empty() && self.grid_volumes.is_empty() {
+            return (None, Colour::gray(1.));
+        }
+        let ray = Ray::new(ray.origin, ray.direction.normalise());
+        let segments = self.media(&ray, distance);
+        let (event, weight) = medium::sample_distance(&segments, &ray, sampler.next_f32());
+        let limit = event
+            .as_ref()
+            .map_or(distance, |event| (event.position - ray.origin).length());
+        let collision = self
+            .grid_volumes
+            .iter()
+            .filter_map(|volume| Some((volume.sample_distance(&ray, limit, sampler)?, volume)))
+            .min_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+        let Some((distance, volume)) = collision else {
+            return (event, weight);
+        };
+        let position = ray.origin + ray.direction * distance;
+        let event = MediumEvent {
+            position,
+            direction: ray.direction,
+            media: vec![volume.medium(position)],
+        };
+        let weight = medium::survival_weight(&segments, distance)
+            * (1. / (volume.extinction * volume.density(position)));
+        (Some(event), weight)
+    }
+
+    fn media(&self, ray: &Ray, distance: f32) -> Vec<medium::MediumSegment> {
+        medium::segments(self.fog, &self.volumes, ray, distance)
+    }
+
+    fn occluded(&self, ray: &Ray, distance: f32) -> bool {
+        self.objects.iter().any(|object| {
+            object
+                .intersections(ray)
+                .iter()
+                .any(|intersection| intersection.distance < distance)
+        })
+    }
+}
+
+impl Fingerprint for Scene {
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        self.objects.fingerprint(state);
+        state.debug(&(&self.lights, &self.directional_lights, self.fog));
+        self.volumes.fingerprint(state);
+        self.grid_volumes.fingerprint(state);
+        self.environment.fingerprint(state);
+        self.background.fingerprint(state);
+    }
+}
@@ -0,0 +1,153 @@
+use std::sync::Arc;
+
+use crate::{
+    instance::Instance, lighting::PointLight, physics::Object, quaternion::Quaternion,
+    scene::Scene, transform::Transform, vector::Vec3D,
+};
+
+#[derive(Debug, Clone)]
+pub struct Node {
+    pub name: String,
+    pub translation: Vec3D,
+    pub rotation: Quaternion,
+    pub scale: Vec3D,
+    pub objects: Vec<Arc<dyn Object>>,
+    pub lights: Vec<PointLight>,
+    pub children: Vec<Node>,
+}
+
+impl Node {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn local_transform(&self) -> Transform {
+        Transform::new(self.translation, self.rotation, self.scale)
+    }
+
+    pub fn find(&self, name: &str) -> Option<&Self> {
+        if self.name == name {
+            return Some(self);
+        }
+        self.children.iter().find_map(|child| child.find(name))
+    }
+
+    pub fn find_mut(&mut self, name: &str) -> Option<&mut Self> {
+        if self.name == name {
+            return Some(self);
+        }
+        self.children
+            .iter_mut()
+            .find_map(|child| child.find_mut(name))
+    }
+
+    pub fn world_transform(&self, name: &str) -> Option<Transform> {
+        let transform = self.local_transform();
+        if self.name == name {
+            return Some(transform);
+        }
+        self.children
+            .iter()
+            .find_map(|child| child.world_transform(name))
+            .map(|child| transform * child)
+    }
+
+    pub fn flatten(&self) -> Scene {
+        let mut scene = Scene::default();
+        self.flatten_into(Transform::IDENTITY, &mut scene);
+        scene
+    }
+
+    fn flatten_into(&self, parent: Transform, scene: &mut Scene) {
+        let transform = parent * self.local_transform();
+        scene.objects.extend(
+            self.objects.iter().map(|object| {
+                Box::new(Instance::new(object.clone(), transform)) as Box<dyn Object>
+            }),
+        );
+        scene.lights.extend(
+            self.lights
+                .iter()
+                .map(|light| PointLight::new(transform.point(light.position), light.intensity)),
+        );
+        for child in &self.children {
+            child.flatten_into(transform, scene);
+        }
+    }
+}
+
+impl Default for Node {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            translation: Vec3D::ZERO,
+            rotation: Quaternion::IDENTITY,
+            scale: Vec3D::ONE,
+            objects: Vec::new(),
+            lights: Vec::new(),
+            children: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use super::*;
+    use crate::{material::Material, ray::Ray, sphere::Sphere};
+
+    fn car() -> Node {
+        let wheel: Arc<dyn Object> = Arc::new(Sphere::new(Vec3D::ZERO, 0.5, Material::default()));
+        let wheels = [(-1., "front"), (1., "back")].map(|(x, name)| Node {
+            translation: Vec3D::new(x, -0.5, 0.),
+            objects: vec![wheel.clone()],
+            ..Node::new(name)
+        });
+        Node {
+            translation: Vec3D::new(0., 0., -10.),
+            lights: vec![PointLight::new(Vec3D::new(0., 1., 0.), 2.)],
+            children: wheels.into(),
+            ..Node::new("car")
+        }
+    }
+
+    #[test]
+    fn test_flatten() {
+        let scene = car().flatten();
+        assert_eq!(scene.objects.len(), 2);
+        assert_eq!(scene.objects[0].centre(), Vec3D::new(-1., -0.5, -10.));
+        assert_eq!(scene.objects[1].centre(), Vec3D::new(1., -0.5, -10.));
+        assert_eq!(
+            scene.lights,
+            vec![PointLight::new(Vec3D::new(0., 1., -10.), 2.)]
+        );
+    }
+
+    #[test]
+    fn test_moving_parent_moves_children() {
+        let mut car = car();
+        car.translation = Vec3D::new(5., 0., -10.);
+        car.rotation = Quaternion::from_axis_angle(Vec3D::Y, PI / 2.);
+        let scene = car.flatten();
+        assert_eq!(scene.objects[0].centre(), Vec3D::new(5., -0.5, -9.));
+        assert_eq!(scene.objects[1].centre(), Vec3D::new(5., -0.5, -11.));
+        let ray = Ray::new(Vec3D::new(5., -0.5, 0.), -Vec3D::Z);
+        let hit = scene.intersect(&ray).unwrap();
+        assert_eq!(hit.position, Vec3D::new(5., -0.5, -8.5));
+    }
+
+    #[test]
+    fn test_find() {
+        let mut car = car();
+        car.find_mut("back").unwrap().scale = Vec3D::triple(2.);
+        assert_eq!(car.find("back").unwrap().scale, Vec3D::triple(2.));
+        assert!(car.find("roof").is_none());
+        let transform = car.world_transform("back").unwrap();
+        assert_eq!(transform.point(Vec3D::X), Vec3D::new(3., -0.5, -10.));
+        assert_eq!(car.flatten().objects[1].extent(), 2.);
+    }
+}
@@ -0,0 +1,490 @@
+use crate::{
+    checkpoint::{Fingerprint, Fingerprinter},
+    material::Material,
+    physics::{Intersection, Object},
+    ray::Ray,
+    sphere::{spherical_tangent, spherical_uv},
+    vector::Vec3D,
+};
+
+const MAX_STEPS: usize = 512;
+const MINIMUM_STEP: f32 = 0.0001;
+const BISECTION_STEPS: usize = 16;
+const NORMAL_DELTA: f32 = 0.0001;
+const ESCAPE_RADIUS: f32 = 2.;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Sdf {
+    Sphere {
+        radius: f32,
+    },
+    Cuboid {
+        half_size: Vec3D,
+    },
+    Torus {
+        major_radius: f32,
+        minor_radius: f32,
+    },
+    Cylinder {
+        radius: f32,
+        height: f32,
+    },
+    Mandelbulb {
+        power: f32,
+        iterations: usize,
+    },
+    Translate {
+        offset: Vec3D,
+        sdf: Box<Sdf>,
+    },
+    Union(Box<Sdf>, Box<Sdf>),
+    Intersection(Box<Sdf>, Box<Sdf>),
+    Subtraction(Box<Sdf>, Box<Sdf>),
+    SmoothUnion {
+        left: Box<Sdf>,
+        right: Box<Sdf>,
+        smoothness: f32,
+    },
+    SmoothSubtraction {
+        left: Box<Sdf>,
+        right: Box<Sdf>,
+        smoothness: f32,
+    },
+    Repeat {
+        period: Vec3D,
+        sdf: Box<Sdf>,
+    },
+    Twist {
+        rate: f32,
+        sdf: Box<Sdf>,
+    },
+}
+
+impl Sdf {
+    pub fn translate(self, offset: Vec3D) -> Self {
+        Self::Translate {
+            offset,
+            sdf: Box::new(self),
+        }
+    }
+
+    pub fn union(self, other: Self) -> Self {
+        Self::Union(Box::new(self), Box::new(other))
+    }
+
+    pub fn intersection(self, other: Self) -> Self {
+        Self::Intersection(Box::new(self), Box::new(other))
+    }
+
+    pub fn subtract(self, other: Self) -> Self {
+        Self::Subtraction(Box::new(self), Box::new(other))
+    }
+
+    pub fn smooth_union(self, other: Self, smoothness: f32) -> Self {
+        Self::SmoothUnion {
+            left: Box::new(self),
+            right: Box::new(other),
+            smoothness,
+        }
+    }
+
+    pub fn smooth_subtract(self, other: Self, smoothness: f32) -> Self {
+        Self::SmoothSubtraction {
+            left: Box::new(self),
+            right: Box::new(other),
+            smoothness,
+        }
+    }
+
+    pub fn repeat(self, period: Vec3D) -> Self {
+        Self::Repeat {
+            period,
+            sdf: Box::new(self),
+        }
+    }
+
+    pub fn twist(self, rate: f32) -> Self {
+        Self::Twist {
+            rate,
+            sdf: Box::new(self),
+        }
+    }
+
+    pub fn distance(&self, position: Vec3D) -> f32 {
+        match self {
+            Self::Sphere { radius } => position.length() - radius,
+            Self::Cuboid { half_size } => {
+                let q = Vec3D::new(
+                    position.x.abs() - half_size.x,
+                    position.y.abs() - half_size.y,
+                    position.z.abs() - half_size.z,
+                );
+                Vec3D::new(q.x.max(0.), q.y.max(0.), q.z.max(0.)).length()
+                    + q.x.max(q.y).max(q.z).min(0.)
+            }
+            Self::Torus {
+                major_radius,
+                minor_radius,
+            } => (position.x.hypot(position.z) - major_radius).hypot(position.y) - minor_radius,
+            Self::Cylinder { radius, height } => {
+                let radial = position.x.hypot(position.z) - radius;
+                let axial = position.y.abs() - height / 2.;
+                radial.max(0.).hypot(axial.max(0.)) + radial.max(axial).min(0.)
+            }
+            Self::Mandelbulb { power, iterations } => mandelbulb(position, *power, *iterations),
+            Self::Translate { offset, sdf } => sdf.distance(position - *offset),
+            Self::Union(left, right) => left.distance(position).min(right.distance(position)),
+            Self::Intersection(left, right) => {
+                left.distance(position).max(right.distance(position))
+            }
+            Self::Subtraction(left, right) => {
+                left.distance(position).max(-right.distance(position))
+            }
+            Self::SmoothUnion {
+                left,
+                right,
+                smoothness,
+            } => {
+                let (a, b) = (left.distance(position), right.distance(position));
+                let h = (0.5 + 0.5 * (b - a) / smoothness).clamp(0., 1.);
+                b + (a - b) * h - smoothness * h * (1. - h)
+            }
+            Self::SmoothSubtraction {
+                left,
+                right,
+                smoothness,
+            } => {
+                let (a, b) = (left.distance(position), right.distance(position));
+                let h = (0.5 - 0.5 * (a + b) / smoothness).clamp(0., 1.);
+                a + (-b - a) * h + smoothness * h * (1. - h)
+            }
+            Self::Repeat { period, sdf } => {
+                let wrap = |value: f32, period: f32| {
+                    if period > 0. {
+                        value - period * (value / period).round()
+                    } else {
+                        value
+                    }
+                };
+                sdf.distance(Vec3D::new(
+                    wrap(position.x, period.x),
+                    wrap(position.y, period.y),
+                    wrap(position.z, period.z),
+                ))
+            }
+            Self::Twist { rate, sdf } => {
+                let (sin, cos) = (rate * position.y).sin_cos();
+                let twisted = Vec3D::new(
+                    cos * position.x - sin * position.z,
+                    position.y,
+                    sin * position.x + cos * position.z,
+                );
+                let stretch = (1. + (rate * position.x.hypot(position.z)).powi(2)).sqrt();
+                sdf.distance(twisted) / stretch
+            }
+        }
+    }
+
+    pub fn gradient(&self, position: Vec3D) -> Vec3D {
+        let difference = |axis: Vec3D| {
+            self.distance(position + axis * NORMAL_DELTA)
+                - self.distance(position - axis * NORMAL_DELTA)
+        };
+        Vec3D::new(
+            difference(Vec3D::X),
+            difference(Vec3D::Y),
+            difference(Vec3D::Z),
+        )
+        .normalise()
+    }
+}
+
+fn mandelbulb(position: Vec3D, power: f32, iterations: usize) -> f32 {
+    let mut z = position;
+    let mut derivative = 1.;
+    let mut radius = z.length();
+    for _ in 0..iterations {
+        if radius > ESCAPE_RADIUS {
+            break;
+        }
+        // z^power is zero in every direction at the origin, so any angle will do there.
+        let theta = if radius == 0. {
+            0.
+        } else {
+            (z.z / radius).acos() * power
+        };
+        let phi = z.y.atan2(z.x) * power;
+        derivative = radius.powf(power - 1.) * power * derivative + 1.;
+        z = radius.powf(power)
+            * Vec3D::new(
+                theta.sin() * phi.cos(),
+                theta.sin() * phi.sin(),
+                theta.cos(),
+            )
+            + position;
+        radius = z.length();
+    }
+    // An orbit that stays at the origin never escapes, so the point lies on the set.
+    if radius == 0. {
+        return 0.;
+    }
+    0.5 * radius.ln() * radius / derivative
+}
+
+#[derive(Debug, Clone)]
+pub struct SdfObject {
+    pub sdf: Sdf,
+    pub centre: Vec3D,
+    pub bounding_radius: f32,
+    pub material: Material,
+}
+
+impl SdfObject {
+    pub fn new(sdf: Sdf, centre: Vec3D, bounding_radius: f32, material: Material) -> Self {
+        Self {
+            sdf,
+            centre,
+            bounding_radius,
+            material,
+        }
+    }
+
+    fn bounds(&self, origin: Vec3D, direction: Vec3D) -> Option<(f32, f32)> {
+        let b = origin.dot(direction);
+        let c = origin.dot(origin) - self.bounding_radius * self.bounding_radius;
+        let discriminant = b * b - c;
+        if discriminant < 0. {
+            return None;
+        }
+        let root = discriminant.sqrt();
+        let far = -b + root;
+        (far > 0.).then_some(((-b - root).max(0.), far))
+    }
+
+    fn refine(&self, origin: Vec3D, direction: Vec3D, mut near: f32, mut far: f32) -> f32 {
+        let near_sign = self.sdf.distance(origin + direction * near) < 0.;
+        for _ in 0..BISECTION_STEPS {
+            let middle = (near + far) / 2.;
+            if (self.sdf.distance(origin + direction * middle) < 0.) == near_sign {
+                near = middle;
+            } else {
+                far = middle;
+            }
+        }
+        (near + far) / 2.
+    }
+}
+
+impl Object for SdfObject {
+    fn intersections(&self, ray: &Ray) -> Vec<Intersection> {
+        let origin = ray.origin - self.centre;
+        let direction = ray.direction.normalise();
+        let Some((near, far)) = self.bounds(origin, direction) else {
+            return Vec::new();
+        };
+        let mut distances = Vec::new();
+        let mut t = near;
+        let mut previous = self.sdf.distance(origin + direction * t);
+        for _ in 0..MAX_STEPS {
+            let step = previous.abs().max(MINIMUM_STEP);
+            let next_t = t + step;
+            if next_t > far {
+                break;
+            }
+            let distance = self.sdf.distance(origin + direction * next_t);
+            if (distance < 0.) != (previous < 0.) {
+                distances.push(self.refine(origin, direction, t, next_t));
+            }
+            t = next_t;
+            previous = distance;
+        }
+        distances
+            .into_iter()
+            .filter(|distance| *distance > 0.)
+            .map(|distance| {
+                let local_position = origin + direction * distance;
+                let normal = self.sdf.gradient(local_position);
+                let position = local_position + self.centre;
+                Intersection {
+                    distance: (position - ray.origin).length(),
+                    position,
+                    local_position,
+                    normal,
+                    tangent: spherical_tangent(normal),
+                    uv: spherical_uv(local_position.normalise()),
+                    object: Box::new(self.clone()),
+                    ray: *ray,
+                }
+            })
+            .collect()
+    }
+
+    fn extent(&self) -> f32 {
+        self.bounding_radius * 2.
+    }
+
+    fn centre(&self) -> Vec3D {
+        self.centre
+    }
+
+    fn material(&self) -> Material {
+        self.material.clone()
+    }
+}
+
+impl Fingerprint for SdfObject {
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        state.debug(&(&self.sdf, self.centre, self.bounding_radius));
+        self.material.fingerprint(state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{f32::consts::PI, sync::Arc};
+
+    use super::*;
+    use crate::{csg::Csg, scene::Scene, sphere::Sphere};
+
+    fn close(a: Vec3D, b: Vec3D) -> bool {
+        (a - b).length() < 0.001
+    }
+
+    #[test]
+    fn test_matches_analytic_sphere() {
+        let sdf = SdfObject::new(
+            Sdf::Sphere { radius: 1. },
+            Vec3D::new(0., 0., -5.),
+            1.5,
+            Material::default(),
+        );
+        let sphere = Sphere::new(Vec3D::new(0., 0., -5.), 1., Material::default());
+        for direction in [
+            Vec3D::new(0., 0., -1.),
+            Vec3D::new(0.1, 0.05, -1.).normalise(),
+            Vec3D::new(-0.15, 0.1, -1.).normalise(),
+        ] {
+            let ray = Ray::new(Vec3D::ZERO, direction);
+            let marched = sdf.intersections(&ray);
+            let analytic = sphere.intersections(&ray);
+            assert_eq!(marched.len(), analytic.len());
+            for (marched, analytic) in marched.iter().zip(&analytic) {
+                assert!(close(marched.position, analytic.position));
+                assert!(close(marched.normal, analytic.normal));
+            }
+        }
+    }
+
+    #[test]
+    fn test_miss_and_inside() {
+        let sdf = SdfObject::new(
+            Sdf::Cuboid {
+                half_size: Vec3D::ONE,
+            },
+            Vec3D::ZERO,
+            2.,
+            Material::default(),
+        );
+        assert!(sdf
+            .intersections(&Ray::new(Vec3D::new(0., 5., 0.), Vec3D::X))
+            .is_empty());
+        let intersections = sdf.intersections(&Ray::new(Vec3D::ZERO, Vec3D::X));
+        assert_eq!(intersections.len(), 1);
+        assert!(close(intersections[0].position, Vec3D::X));
+        assert!(close(intersections[0].normal, Vec3D::X));
+    }
+
+    #[test]
+    fn test_operators() {
+        let a = Sdf::Sphere { radius: 1. }.translate(Vec3D::new(-0.9, 0., 0.));
+        let b = Sdf::Sphere { radius: 1. }.translate(Vec3D::new(0.9, 0., 0.));
+        let union = a.clone().union(b.clone());
+        let smooth = a.clone().smooth_union(b.clone(), 0.5);
+        let point = Vec3D::new(0., 0.6, 0.);
+        assert!(smooth.distance(point) < union.distance(point));
+        assert!(union.distance(point) > 0. && smooth.distance(point) < 0.);
+        let carved = a.clone().smooth_subtract(b.clone(), 0.2);
+        assert!(carved.distance(Vec3D::new(-1.5, 0., 0.)) < 0.);
+        assert!(carved.distance(Vec3D::new(0.5, 0., 0.)) > 0.);
+        let repeated = Sdf::Sphere { radius: 0.25 }.repeat(Vec3D::new(1., 0., 1.));
+        assert_eq!(
+            repeated.distance(Vec3D::new(3.1, 0.5, -7.)),
+            repeated.distance(Vec3D::new(0.1, 0.5, 0.))
+        );
+        let cuboid = Sdf::Cuboid {
+            half_size: Vec3D::new(1., 2., 0.2),
+        };
+        let twisted = cuboid.clone().twist(PI / 2.);
+        let point = Vec3D::new(0., 1., 0.9);
+        assert!(cuboid.distance(point) > 0. && twisted.distance(point) < 0.);
+        assert!(twisted.distance(Vec3D::new(0.9, 0., 0.)) < 0.);
+    }
+
+    #[test]
+    fn test_alongside_analytic_objects() {
+        let scene = Scene {
+            objects: vec![
+                Box::new(SdfObject::new(
+                    Sdf::Torus {
+                        major_radius: 1.,
+                        minor_radius: 0.25,
+                    },
+                    Vec3D::new(0., 0., -5.),
+                    1.5,
+                    Material::default(),
+                )),
+                Box::new(Sphere::new(
+                    Vec3D::new(0., 0., -5.),
+                    0.5,
+                    Material::default(),
+                )),
+            ],
+            ..Default::default()
+        };
+        let hit = scene
+            .intersect(&Ray::new(Vec3D::new(1., 2., -5.), -Vec3D::Y))
+            .unwrap();
+        assert!(close(hit.position, Vec3D::new(1., 0.25, -5.)));
+        let hit = scene
+            .intersect(&Ray::new(Vec3D::new(0., 2., -5.), -Vec3D::Y))
+            .unwrap();
+        assert!(close(hit.position, Vec3D::new(0., 0.5, -5.)));
+    }
+
+    #[test]
+    fn test_csg_with_marched_object() {
+        let drilled = Csg::difference(
+            Arc::new(SdfObject::new(
+                Sdf::Sphere { radius: 1. },
+                Vec3D::ZERO,
+                1.5,
+                Material::default(),
+            )),
+            Arc::new(Sphere::new(Vec3D::ZERO, 0.5, Material::default())),
+        );
+        let intersections = drilled.intersections(&Ray::new(Vec3D::new(-5., 0., 0.), Vec3D::X));
+        assert_eq!(intersections.len(), 4);
+        assert!(close(intersections[1].position, Vec3D::new(-0.5, 0., 0.)));
+    }
+
+    #[test]
+    fn test_mandelbulb() {
+        let mandelbulb = SdfObject::new(
+            Sdf::Mandelbulb {
+                power: 8.,
+                iterations: 8,
+            },
+            Vec3D::ZERO,
+            1.5,
+            Material::default(),
+        );
+        assert!(mandelbulb.sdf.distance(Vec3D::triple(1.5)) > 0.);
+        assert_eq!(mandelbulb.sdf.distance(Vec3D::ZERO), 0.);
+        let ray = Ray::new(Vec3D::new(0.1, 0.2, 5.), -Vec3D::Z);
+        let intersections = mandelbulb.intersections(&ray);
+        assert!(!intersections.is_empty());
+        let hit = &intersections[0];
+        assert!(hit.position.length() < 1.5);
+        assert!(hit.normal.dot(ray.direction) < 0.);
+    }
+}
@@ -0,0 +1,145 @@
+use std::f32::consts::PI;
+
+use crate::vector::Vec3D;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ShadingModel {
+    Phong,
+    #[default]
+    BlinnPhong,
+}
+
+impl ShadingModel {
+    pub fn specular(self, normal: Vec3D, to_light: Vec3D, to_viewer: Vec3D, exponent: f32) -> f32 {
+        match self {
+            Self::Phong => phong(normal, to_light, to_viewer, exponent),
+            Self::BlinnPhong => blinn_phong(normal, to_light, to_viewer, exponent),
+        }
+    }
+}
+
+pub fn lambert(normal: Vec3D, to_light: Vec3D) -> f32 {
+    0_f32.max(normal.normalise().dot(to_light.normalise()))
+}
+
+// Normalised so that the lobe reflects all incoming energy at normal incidence.
+pub fn phong(normal: Vec3D, to_light: Vec3D, to_viewer: Vec3D, exponent: f32) -> f32 {
+    let normal = normal.normalise();
+    let to_light = to_light.normalise();
+    let to_viewer = to_viewer.normalise();
+    if normal.dot(to_light) <= 0. || normal.dot(to_viewer) <= 0. {
+        return 0.;
+    }
+    let reflected = (-to_light).reflect(normal);
+    (exponent + 2.) / (2. * PI) * 0_f32.max(reflected.dot(to_viewer)).powf(exponent)
+}
+
+pub fn blinn_phong(normal: Vec3D, to_light: Vec3D, to_viewer: Vec3D, exponent: f32) -> f32 {
+    let normal = normal.normalise();
+    let to_light = to_light.normalise();
+    let to_viewer = to_viewer.normalise();
+    if normal.dot(to_light) <= 0. || normal.dot(to_viewer) <= 0. {
+        return 0.;
+    }
+    let halfway = (to_light + to_viewer).normalise();
+    (exponent + 8.) / (8. * PI) * 0_f32.max(normal.dot(halfway)).powf(exponent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn directional_albedo(model: ShadingModel, to_light: Vec3D, exponent: f32) -> f32 {
+        let steps = 512;
+        let step = (PI / 2.) / steps as f32;
+        let azimuth_step = (2. * PI) / steps as f32;
+        let mut total = 0.;
+        for i in 0..steps {
+            let theta = (i as f32 + 0.5) * step;
+            for j in 0..steps {
+                let phi = (j as f32 + 0.5) * azimuth_step;
+                let to_viewer = Vec3D::new(
+                    theta.sin() * phi.cos(),
+                    theta.sin() * phi.sin(),
+                    theta.cos(),
+                );
+                total += model.specular(Vec3D::Z, to_light, to_viewer, exponent)
+                    * theta.cos()
+                    * theta.sin()
+                    * step
+                    * azimuth_step;
+            }
+        }
+        total
+    }
+
+    #[test]
+    fn test_lambert() {
+        assert_eq!(lambert(Vec3D::Z, Vec3D::Z * 3.), 1.);
+        assert_eq!(lambert(Vec3D::Z, Vec3D::X), 0.);
+        assert_eq!(lambert(Vec3D::Z, -Vec3D::Z), 0.);
+        assert!((lambert(Vec3D::Z, Vec3D::new(1., 0., 1.)) - 0.5_f32.sqrt()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_phong_highlight_at_mirror_direction() {
+        let to_light = Vec3D::new(-1., 0., 1.);
+        let mirror = Vec3D::new(1., 0., 1.);
+        let peak = phong(Vec3D::Z, to_light, mirror, 50.);
+        assert!((peak - 52. / (2. * PI)).abs() < 1e-4);
+        for to_viewer in [
+            Vec3D::new(1., 0.1, 1.),
+            Vec3D::new(0.9, 0., 1.),
+            Vec3D::new(1.1, 0., 1.),
+            Vec3D::Z,
+        ] {
+            assert!(phong(Vec3D::Z, to_light, to_viewer, 50.) < peak);
+        }
+    }
+
+    #[test]
+    fn test_blinn_phong_highlight_at_halfway_normal() {
+        let to_light = Vec3D::new(-1., 0., 2.);
+        let to_viewer = Vec3D::new(1., 0., 2.);
+        let peak = blinn_phong(Vec3D::Z, to_light, to_viewer, 50.);
+        assert!((peak - 58. / (8. * PI)).abs() < 1e-4);
+        assert!(blinn_phong(Vec3D::Z, to_light, Vec3D::new(1., 0., 1.), 50.) < peak);
+        assert!(blinn_phong(Vec3D::new(0.1, 0., 1.), to_light, to_viewer, 50.) < peak);
+    }
+
+    #[test]
+    fn test_highlight_follows_light() {
+        let to_light = Vec3D::new(1., 0., 1.);
+        let towards_light = Vec3D::new(-1., 0., 1.);
+        for model in [ShadingModel::Phong, ShadingModel::BlinnPhong] {
+            assert!(
+                model.specular(Vec3D::Z, to_light, towards_light, 20.)
+                    > model.specular(Vec3D::Z, to_light, to_light, 20.)
+            );
+        }
+    }
+
+    #[test]
+    fn test_no_specular_below_horizon() {
+        for model in [ShadingModel::Phong, ShadingModel::BlinnPhong] {
+            assert_eq!(model.specular(Vec3D::Z, -Vec3D::Z, Vec3D::Z, 10.), 0.);
+            assert_eq!(model.specular(Vec3D::Z, Vec3D::Z, -Vec3D::Z, 10.), 0.);
+        }
+    }
+
+    #[test]
+    fn test_phong_energy_normalisation() {
+        for exponent in [1., 10., 50., 200.] {
+            let albedo = directional_albedo(ShadingModel::Phong, Vec3D::Z, exponent);
+            assert!((albedo - 1.).abs() < 0.01, "{exponent}: {albedo}");
+        }
+    }
+
+    #[test]
+    fn test_blinn_phong_energy_normalisation() {
+        for exponent in [10., 50., 200.] {
+            let albedo = directional_albedo(ShadingModel::BlinnPhong, Vec3D::Z, exponent);
+            assert!((albedo - 1.).abs() < 0.1, "{exponent}: {albedo}");
+        }
+    }
+}
@@ -0,0 +1,236 @@
+use std::{
+    f32::consts::{FRAC_PI_2, PI},
+    io,
+};
+
+use crate::{
+    colour::Colour, environment::Environment, image::Image, lighting::DirectionalLight,
+    vector::Vec3D,
+};
+
+pub const SUN_ANGULAR_RADIUS: f32 = 0.004_65;
+pub const SUN_IRRADIANCE: f32 = 120.;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sky {
+    pub sun_elevation: f32,
+    pub sun_azimuth: f32,
+    pub turbidity: f32,
+    pub intensity: f32,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Perez([f32; 5]);
+
+impl Perez {
+    fn new(turbidity: f32, coefficients: [(f32, f32); 5]) -> Self {
+        Self(coefficients.map(|(slope, offset)| slope * turbidity + offset))
+    }
+
+    fn evaluate(&self, cos_theta: f32, gamma: f32) -> f32 {
+        let [a, b, c, d, e] = self.0;
+        (1. + a * (b / cos_theta).exp()) * (1. + c * (d * gamma).exp() + e * gamma.cos().powi(2))
+    }
+}
+
+impl Sky {
+    pub fn new(sun_elevation: f32, sun_azimuth: f32, turbidity: f32) -> Self {
+        Self {
+            sun_elevation,
+            sun_azimuth,
+            turbidity,
+            intensity: 1.,
+        }
+    }
+
+    pub fn sun_direction(&self) -> Vec3D {
+        let (sin_elevation, cos_elevation) = self.sun_elevation.sin_cos();
+        let (sin_azimuth, cos_azimuth) = self.sun_azimuth.sin_cos();
+        Vec3D::new(
+            cos_elevation * sin_azimuth,
+            sin_elevation,
+            -cos_elevation * cos_azimuth,
+        )
+    }
+
+    fn sun_zenith(&self) -> f32 {
+        (FRAC_PI_2 - self.sun_elevation).clamp(0., FRAC_PI_2)
+    }
+
+    fn zenith(&self) -> [f32; 3] {
+        let turbidity = self.turbidity;
+        let theta = self.sun_zenith();
+        let chi = (4. / 9. - turbidity / 120.) * (PI - 2. * theta);
+        let luminance = (4.0453 * turbidity - 4.971) * chi.tan() - 0.2155 * turbidity + 2.4192;
+        let polynomial = |[t2, t1, t0]: [[f32; 4]; 3]| {
+            let cubic = |[a, b, c, d]: [f32; 4]| ((a * theta + b) * theta + c) * theta + d;
+            (cubic(t2) * turbidity + cubic(t1)) * turbidity + cubic(t0)
+        };
+        let x = polynomial([
+            [0.001_66, -0.003_75, 0.002_09, 0.],
+            [-0.029_03, 0.063_77, -0.032_02, 0.003_94],
+            [0.116_93, -0.211_96, 0.060_52, 0.258_86],
+        ]);
+        let y = polynomial([
+            [0.002_75, -0.006_1, 0.003_17, 0.],
+            [-0.042_14, 0.089_7, -0.041_53, 0.005_16],
+            [0.153_46, -0.267_56, 0.066_7, 0.266_88],
+        ]);
+        [x, y, luminance.max(0.)]
+    }
+
+    fn distributions(&self) -> [Perez; 3] {
+        let turbidity = self.turbidity;
+        [
+            Perez::new(
+                turbidity,
+                [
+                    (-0.0193, -0.2592),
+                    (-0.0665, 0.0008),
+                    (-0.0004, 0.2125),
+                    (-0.0641, -0.8989),
+                    (-0.0033, 0.0452),
+                ],
+            ),
+            Perez::new(
+                turbidity,
+                [
+                    (-0.0167, -0.2608),
+                    (-0.095, 0.0092),
+                    (-0.0079, 0.2102),
+                    (-0.0441, -1.6537),
+                    (-0.0109, 0.0529),
+                ],
+            ),
+            Perez::new(
+                turbidity,
+                [
+                    (0.1787, -1.463),
+                    (-0.3554, 0.4275),
+                    (-0.0227, 5.3251),
+                    (0.1206, -2.5771),
+                    (-0.067, 0.3703),
+                ],
+            ),
+        ]
+    }
+
+    pub fn sky_radiance(&self, direction: Vec3D) -> Colour {
+        let direction = direction.normalise();
+        let cos_theta = direction.y.max(0.01);
+        let gamma = direction.dot(self.sun_direction()).clamp(-1., 1.).acos();
+        let sun_zenith = self.sun_zenith();
+        let zenith = self.zenith();
+        let distributions = self.distributions();
+        let [x, y, luminance] = std::array::from_fn(|index| {
+            let perez = distributions[index];
+            zenith[index] * perez.evaluate(cos_theta, gamma) / perez.evaluate(1., sun_zenith)
+        });
+        xyy_to_rgb(x, y, luminance) * self.intensity
+    }
+
+    pub fn sun_transmittance(&self) -> Colour {
+        if self.sun_elevation <= 0. {
+            return Colour::default();
+        }
+        let theta = self.sun_zenith();
+        let mass = 1. / (theta.cos() + 0.15 * (93.885 - theta.to_degrees()).powf(-1.253));
+        let beta = 0.046_08 * self.turbidity - 0.045_86;
+        let [red, green, blue] = [0.68_f32, 0.55, 0.44].map(|wavelength| {
+            let rayleigh = 0.008_735 * wavelength.powf(-4.08);
+            let aerosol = beta * wavelength.powf(-1.3);
+            (-(rayleigh + aerosol) * mass).exp()
+        });
+        Colour::new(red, green, blue)
+    }
+
+    pub fn sun(&self) -> DirectionalLight {
+        DirectionalLight::new(
+            self.sun_direction(),
+            self.sun_transmittance() * (SUN_IRRADIANCE * self.intensity),
+            SUN_ANGULAR_RADIUS,
+        )
+    }
+
+    pub fn value(&self, direction: Vec3D) -> Colour {
+        self.sky_radiance(direction) + self.sun().radiance(direction)
+    }
+
+    pub fn environment(&self, width: usize, height: usize) -> io::Result<Environment> {
+        let mut image = Image::new(width, height, Colour::default());
+        for (x, y, pixel) in &mut image {
+            let longitude = ((x as f32 + 0.5) / width as f32 - 0.5) * 2. * PI;
+            let latitude = (0.5 - (y as f32 + 0.5) / height as f32) * PI;
+            *pixel = self.sky_radiance(Vec3D::new(
+                latitude.cos() * longitude.sin(),
+                latitude.sin(),
+                -latitude.cos() * longitude.cos(),
+            ));
+        }
+        Environment::new(image, 0., 1.)
+    }
+}
+
+fn xyy_to_rgb(x: f32, y: f32, luminance: f32) -> Colour {
+    if y <= 0. {
+        return Colour::default();
+    }
+    let (x, z) = (x / y * luminance, (1. - x - y) / y * luminance);
+    Colour::new(
+        (3.2406 * x - 1.5372 * luminance - 0.4986 * z).max(0.),
+        (-0.9689 * x + 1.8758 * luminance + 0.0415 * z).max(0.),
+        (0.0557 * x - 0.204 * luminance + 1.057 * z).max(0.),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sun_direction() {
+        let sky = Sky::new(0., 0., 3.);
+        assert_eq!(sky.sun_direction(), -Vec3D::Z);
+        let sky = Sky::new(FRAC_PI_2 / 2., FRAC_PI_2, 3.);
+        let direction = sky.sun_direction();
+        assert!((direction.x - direction.y).abs() < 1e-6 && direction.z.abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_sky_brightens_towards_sun() {
+        let sky = Sky::new(0.4, 1., 3.);
+        let sun = sky.sun_direction();
+        let near = (sun + Vec3D::Y * 0.1).normalise();
+        let far = Vec3D::new(-sun.x, sun.y, -sun.z);
+        assert!(sky.sky_radiance(near).luminance() > sky.sky_radiance(far).luminance());
+        let zenith = sky.sky_radiance(Vec3D::Y);
+        assert!((zenith.luminance() - sky.zenith()[2]).abs() < 0.02 * sky.zenith()[2]);
+        assert!(zenith.blue > zenith.red);
+    }
+
+    #[test]
+    fn test_sun_reddens_and_dims() {
+        let high = Sky::new(1.2, 0., 3.).sun_transmittance();
+        let low = Sky::new(0.05, 0., 3.).sun_transmittance();
+        let hazy = Sky::new(1.2, 0., 8.).sun_transmittance();
+        assert!(low.red / low.blue > high.red / high.blue);
+        assert!(hazy.luminance() < high.luminance());
+        assert_eq!(
+            Sky::new(-0.1, 0., 3.).sun_transmittance(),
+            Colour::default()
+        );
+    }
+
+    #[test]
+    fn test_sun_disk_and_environment() {
+        let sky = Sky::new(0.5, -0.7, 2.5);
+        let sun = sky.sun();
+        let disk = sky.value(sky.sun_direction()) - sky.sky_radiance(sky.sun_direction());
+        assert!((disk.luminance() * sun.solid_angle() - sun.irradiance.luminance()).abs() < 1e-2);
+        assert_eq!(sun.radiance(Vec3D::Y), Colour::default());
+        let environment = sky.environment(64, 32).unwrap();
+        let direction = Vec3D::new(0.3, 0.4, 0.2).normalise();
+        let difference = environment.value(direction) - sky.sky_radiance(direction);
+        assert!(difference.luminance().abs() < 0.1 * sky.sky_radiance(direction).luminance());
+    }
+}
@@ -1,12 +1,15 @@
-use std::mem::swap;
+use std::{f32::consts::PI, mem::swap};
 
 use crate::{
+    checkpoint::{Fingerprint, Fingerprinter},
+    constants::EPSILON,
+    material::Material,
     physics::{Intersection, Object},
     ray::Ray,
-    vector::Vec3D, material::Material,
+    vector::Vec3D,
 };
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone)]
 pub struct Sphere {
     pub centre: Vec3D,
     pub radius: f32,
@@ -55,12 +58,18 @@ impl Object for Sphere {
         intersections.dedup();
         intersections
             .iter()
-            .map(|intersection| Intersection {
-                distance: (*intersection - ray.origin).length(),
-                position: *intersection,
-                normal: (*intersection - self.centre).normalise(),
-                object: Box::new(self.clone()),
-                ray: *ray
+            .map(|intersection| {
+                let normal = (*intersection - self.centre).normalise();
+                Intersection {
+                    distance: (*intersection - ray.origin).length(),
+                    position: *intersection,
+                    local_position: *intersection - self.centre,
+                    normal,
+                    tangent: spherical_tangent(normal),
+                    uv: spherical_uv(normal),
+                    object: Box::new(self.clone()),
+                    ray: *ray,
+                }
             })
             .collect::<Vec<Intersection>>()
     }
@@ -78,16 +87,39 @@ impl Object for Sphere {
     }
 }
 
+pub fn spherical_uv(direction: Vec3D) -> (f32, f32) {
+    (
+        0.5 + direction.x.atan2(direction.z) / (2. * PI),
+        0.5 + direction.y.clamp(-1., 1.).asin() / PI,
+    )
+}
+
+pub fn spherical_tangent(normal: Vec3D) -> Vec3D {
+    let tangent = Vec3D::new(normal.z, 0., -normal.x);
+    if tangent.length() < EPSILON {
+        normal.basis().0
+    } else {
+        tangent.normalise()
+    }
+}
+
 impl Default for Sphere {
     fn default() -> Self {
         Self {
             centre: Vec3D::default(),
             radius: 1.,
-            material: Material::default()
+            material: Material::default(),
         }
     }
 }
 
+impl Fingerprint for Sphere {
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        state.debug(&(self.centre, self.radius));
+        self.material.fingerprint(state);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -135,4 +167,44 @@ mod tests {
         assert_eq!(intersections[0].normal, Vec3D::new(0., 0., -1.));
         assert_eq!(intersections[0].distance, 1.);
     }
+
+    #[test]
+    fn test_sphere_intersections_tangent_frame() {
+        let sphere = Sphere::default();
+        for direction in [
+            Vec3D::new(0., 0., -1.),
+            Vec3D::new(0., -1., 0.),
+            Vec3D::new(1., -2., 0.5),
+        ] {
+            let ray = Ray::new(direction * -5., direction);
+            for intersection in sphere.intersections(&ray) {
+                assert!(intersection.tangent.dot(intersection.normal).abs() < EPSILON);
+                assert!((intersection.tangent.length() - 1.).abs() < EPSILON);
+            }
+        }
+        let ray = Ray::new(Vec3D::new(0., 0., 5.), Vec3D::new(0., 0., -1.));
+        let intersections = sphere.intersections(&ray);
+        assert_eq!(intersections[0].tangent, Vec3D::X);
+        assert_eq!(
+            intersections[0].normal.cross(intersections[0].tangent),
+            Vec3D::Y
+        );
+    }
+
+    #[test]
+    fn test_sphere_intersections_uv() {
+        let sphere = Sphere::new(Vec3D::new(0., 0., -5.), 2., Material::default());
+        let ray = Ray::new(Vec3D::ZERO, Vec3D::new(0., 0., -1.));
+        let intersections = sphere.intersections(&ray);
+        assert_eq!(intersections[0].uv, (0.5, 0.5));
+        assert_eq!(intersections[0].local_position, Vec3D::new(0., 0., 2.));
+        assert_eq!(intersections[1].uv.0 % 1., 0.);
+        assert_eq!(intersections[1].uv.1, 0.5);
+        let ray = Ray::new(Vec3D::new(0., 5., -5.), Vec3D::new(0., -1., 0.));
+        let intersections = sphere.intersections(&ray);
+        assert_eq!(intersections[0].uv.1, 1.);
+        assert_eq!(intersections[1].uv.1, 0.);
+        let ray = Ray::new(Vec3D::new(5., 0., -5.), Vec3D::new(-1., 0., 0.));
+        assert_eq!(sphere.intersections(&ray)[0].uv, (0.75, 0.5));
+    }
 }
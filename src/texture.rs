@@ -0,0 +1,272 @@
+use std::{
+    fmt::{self, Debug},
+    io,
+    path::Path,
+    sync::Arc,
+};
+
+use crate::{
+    checkpoint::{Fingerprint, Fingerprinter},
+    colour::Colour,
+    image::Image,
+    vector::Vec3D,
+};
+
+pub trait Texture: Fingerprint + Debug + Send + Sync {
+    fn value(&self, uv: (f32, f32), position: Vec3D) -> Colour;
+}
+
+impl From<Colour> for Arc<dyn Texture> {
+    fn from(value: Colour) -> Self {
+        Arc::new(SolidColour(value))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SolidColour(pub Colour);
+
+impl Texture for SolidColour {
+    fn value(&self, _: (f32, f32), _: Vec3D) -> Colour {
+        self.0
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Checkerboard {
+    pub even: Arc<dyn Texture>,
+    pub odd: Arc<dyn Texture>,
+    pub divisions: (f32, f32),
+}
+
+impl Checkerboard {
+    pub fn new(
+        even: impl Into<Arc<dyn Texture>>,
+        odd: impl Into<Arc<dyn Texture>>,
+        divisions: (f32, f32),
+    ) -> Self {
+        Self {
+            even: even.into(),
+            odd: odd.into(),
+            divisions,
+        }
+    }
+}
+
+impl Texture for Checkerboard {
+    fn value(&self, (u, v): (f32, f32), position: Vec3D) -> Colour {
+        let cell = (u * self.divisions.0).floor() + (v * self.divisions.1).floor();
+        if cell.rem_euclid(2.) < 1. {
+            self.even.value((u, v), position)
+        } else {
+            self.odd.value((u, v), position)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WrapMode {
+    #[default]
+    Repeat,
+    Mirror,
+    Clamp,
+}
+
+impl WrapMode {
+    fn apply(self, index: isize, size: usize) -> usize {
+        let size = size as isize;
+        match self {
+            Self::Repeat => index.rem_euclid(size) as usize,
+            Self::Mirror => {
+                let index = index.rem_euclid(2 * size);
+                (if index < size {
+                    index
+                } else {
+                    2 * size - 1 - index
+                }) as usize
+            }
+            Self::Clamp => index.clamp(0, size - 1) as usize,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ImageTexture {
+    pub image: Image,
+    pub wrap_mode: WrapMode,
+}
+
+impl ImageTexture {
+    pub fn new(image: Image, wrap_mode: WrapMode) -> io::Result<Self> {
+        if image.width == 0 || image.height == 0 || image.pixels.len() != image.width * image.height
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid {}x{} texture", image.width, image.height),
+            ));
+        }
+        Ok(Self { image, wrap_mode })
+    }
+
+    pub fn load(path: impl AsRef<Path>, wrap_mode: WrapMode) -> io::Result<Self> {
+        Self::new(Image::load(path)?, wrap_mode)
+    }
+
+    fn texel(&self, x: isize, y: isize) -> Colour {
+        self.image.pixel(
+            self.wrap_mode.apply(x, self.image.width),
+            self.wrap_mode.apply(y, self.image.height),
+        )
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, (u, v): (f32, f32), _: Vec3D) -> Colour {
+        let x = u * self.image.width as f32 - 0.5;
+        let y = (1. - v) * self.image.height as f32 - 0.5;
+        let (left, top) = (x.floor(), y.floor());
+        let (horizontal, vertical) = (x - left, y - top);
+        let (left, top) = (left as isize, top as isize);
+        let upper =
+            self.texel(left, top) * (1. - horizontal) + self.texel(left + 1, top) * horizontal;
+        let lower = self.texel(left, top + 1) * (1. - horizontal)
+            + self.texel(left + 1, top + 1) * horizontal;
+        upper * (1. - vertical) + lower * vertical
+    }
+}
+
+pub struct ProceduralTexture<F>(pub F)
+where
+    F: Fn((f32, f32), Vec3D) -> Colour + Send + Sync;
+
+impl<F> Debug for ProceduralTexture<F>
+where
+    F: Fn((f32, f32), Vec3D) -> Colour + Send + Sync,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProceduralTexture").finish_non_exhaustive()
+    }
+}
+
+impl<F> Texture for ProceduralTexture<F>
+where
+    F: Fn((f32, f32), Vec3D) -> Colour + Send + Sync,
+{
+    fn value(&self, uv: (f32, f32), position: Vec3D) -> Colour {
+        self.0(uv, position)
+    }
+}
+
+impl Fingerprint for SolidColour {
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        state.debug(&self.0);
+    }
+}
+
+impl Fingerprint for Checkerboard {
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        self.even.fingerprint(state);
+        self.odd.fingerprint(state);
+        state.debug(&self.divisions);
+    }
+}
+
+impl Fingerprint for ImageTexture {
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        self.image.fingerprint(state);
+        state.debug(&self.wrap_mode);
+    }
+}
+
+// Closures cannot be inspected, so a procedural texture is identified by its values on a fixed
+// grid of probe points.
+impl<F> Fingerprint for ProceduralTexture<F>
+where
+    F: Fn((f32, f32), Vec3D) -> Colour + Send + Sync,
+{
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        for index in 0..64 {
+            let (u, v) = ((index % 8) as f32 / 7., (index / 8) as f32 / 7.);
+            let position = Vec3D::new(u - 0.5, v - 0.5, u * v - 0.25) * 10.;
+            state.floats(self.0((u, v), position).as_rgb());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gradient() -> Image {
+        Image {
+            width: 2,
+            height: 2,
+            pixels: vec![
+                Colour::gray(0.),
+                Colour::gray(1.),
+                Colour::gray(0.),
+                Colour::gray(1.),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_solid_colour() {
+        let texture: Arc<dyn Texture> = Colour::new(0.1, 0.2, 0.3).into();
+        assert_eq!(
+            texture.value((0.7, 0.2), Vec3D::ONE),
+            Colour::new(0.1, 0.2, 0.3)
+        );
+    }
+
+    #[test]
+    fn test_checkerboard() {
+        let texture = Checkerboard::new(Colour::gray(1.), Colour::gray(0.), (4., 2.));
+        assert_eq!(texture.value((0.1, 0.1), Vec3D::ZERO), Colour::gray(1.));
+        assert_eq!(texture.value((0.3, 0.1), Vec3D::ZERO), Colour::gray(0.));
+        assert_eq!(texture.value((0.3, 0.6), Vec3D::ZERO), Colour::gray(1.));
+        assert_eq!(texture.value((-0.1, 0.1), Vec3D::ZERO), Colour::gray(0.));
+    }
+
+    #[test]
+    fn test_wrap_modes() {
+        assert_eq!(WrapMode::Repeat.apply(-1, 4), 3);
+        assert_eq!(WrapMode::Repeat.apply(5, 4), 1);
+        assert_eq!(WrapMode::Mirror.apply(-1, 4), 0);
+        assert_eq!(WrapMode::Mirror.apply(4, 4), 3);
+        assert_eq!(WrapMode::Mirror.apply(5, 4), 2);
+        assert_eq!(WrapMode::Clamp.apply(-3, 4), 0);
+        assert_eq!(WrapMode::Clamp.apply(7, 4), 3);
+    }
+
+    #[test]
+    fn test_bilinear_filtering() {
+        let texture = ImageTexture::new(gradient(), WrapMode::Clamp).unwrap();
+        assert_eq!(texture.value((0.25, 0.5), Vec3D::ZERO), Colour::gray(0.));
+        assert_eq!(texture.value((0.5, 0.5), Vec3D::ZERO), Colour::gray(0.5));
+        assert_eq!(texture.value((0.75, 0.25), Vec3D::ZERO), Colour::gray(1.));
+        assert_eq!(texture.value((0.375, 0.9), Vec3D::ZERO), Colour::gray(0.25));
+    }
+
+    #[test]
+    fn test_rejects_empty_images() {
+        let error =
+            ImageTexture::new(Image::new(0, 0, Colour::default()), WrapMode::Repeat).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_image_wrapping() {
+        let repeat = ImageTexture::new(gradient(), WrapMode::Repeat).unwrap();
+        assert_eq!(repeat.value((1.25, 0.5), Vec3D::ZERO), Colour::gray(0.));
+        assert_eq!(repeat.value((0., 0.5), Vec3D::ZERO), Colour::gray(0.5));
+        let clamp = ImageTexture::new(gradient(), WrapMode::Clamp).unwrap();
+        assert_eq!(clamp.value((0., 0.5), Vec3D::ZERO), Colour::gray(0.));
+        assert_eq!(clamp.value((1.5, 0.5), Vec3D::ZERO), Colour::gray(1.));
+    }
+
+    #[test]
+    fn test_procedural_texture() {
+        let texture = ProceduralTexture(|(u, _), position: Vec3D| Colour::gray(u + position.x));
+        assert_eq!(texture.value((0.25, 0.), Vec3D::X), Colour::gray(1.25));
+    }
+}
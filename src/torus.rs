@@ -0,0 +1,199 @@
+use std::f32::consts::PI;
+
+use crate::{
+    checkpoint::{Fingerprint, Fingerprinter},
+    material::Material,
+    math::solve_quartic,
+    physics::{Intersection, Object},
+    ray::Ray,
+    sphere::{spherical_tangent, spherical_uv},
+    vector::Vec3D,
+};
+
+#[derive(Debug, Clone)]
+pub struct Torus {
+    pub centre: Vec3D,
+    pub major_radius: f32,
+    pub minor_radius: f32,
+    pub material: Material,
+}
+
+impl Torus {
+    pub fn new(centre: Vec3D, major_radius: f32, minor_radius: f32, material: Material) -> Self {
+        Self {
+            centre,
+            major_radius,
+            minor_radius,
+            material,
+        }
+    }
+
+    pub fn unit(material: Material) -> Self {
+        Self {
+            material,
+            ..Default::default()
+        }
+    }
+}
+
+impl Object for Torus {
+    fn intersections(&self, ray: &Ray) -> Vec<Intersection> {
+        let origin = ray.origin - self.centre;
+        let [origin_x, origin_y, origin_z] = [origin.x, origin.y, origin.z].map(f64::from);
+        let [direction_x, direction_y, direction_z] =
+            [ray.direction.x, ray.direction.y, ray.direction.z].map(f64::from);
+        let major_squared = f64::from(self.major_radius).powi(2);
+        let minor_squared = f64::from(self.minor_radius).powi(2);
+        let direction_squared =
+            direction_x * direction_x + direction_y * direction_y + direction_z * direction_z;
+        let origin_dot_direction =
+            origin_x * direction_x + origin_y * direction_y + origin_z * direction_z;
+        let k = origin_x * origin_x + origin_y * origin_y + origin_z * origin_z + major_squared
+            - minor_squared;
+        let mut intersections = solve_quartic(
+            direction_squared * direction_squared,
+            4. * direction_squared * origin_dot_direction,
+            2. * direction_squared * k + 4. * origin_dot_direction * origin_dot_direction
+                - 4. * major_squared * (direction_x * direction_x + direction_z * direction_z),
+            4. * origin_dot_direction * k
+                - 8. * major_squared * (origin_x * direction_x + origin_z * direction_z),
+            k * k - 4. * major_squared * (origin_x * origin_x + origin_z * origin_z),
+        )
+        .into_iter()
+        .filter(|distance| *distance > 0.)
+        .map(|distance| ray.origin + ray.direction * distance as f32)
+        .collect::<Vec<Vec3D>>();
+        intersections.dedup();
+        intersections
+            .iter()
+            .map(|intersection| {
+                let local_position = *intersection - self.centre;
+                let around = Vec3D::new(local_position.x, 0., local_position.z).normalise();
+                let tube = local_position - around * self.major_radius;
+                let normal = tube.normalise();
+                Intersection {
+                    distance: (*intersection - ray.origin).length(),
+                    position: *intersection,
+                    local_position,
+                    normal,
+                    tangent: spherical_tangent(around),
+                    uv: (
+                        spherical_uv(around).0,
+                        0.5 + tube.y.atan2(tube.dot(around)) / (2. * PI),
+                    ),
+                    object: Box::new(self.clone()),
+                    ray: *ray,
+                }
+            })
+            .collect::<Vec<Intersection>>()
+    }
+
+    fn extent(&self) -> f32 {
+        2. * (self.major_radius + self.minor_radius)
+    }
+
+    fn centre(&self) -> Vec3D {
+        self.centre
+    }
+
+    fn material(&self) -> Material {
+        self.material.clone()
+    }
+}
+
+impl Default for Torus {
+    fn default() -> Self {
+        Self {
+            centre: Vec3D::default(),
+            major_radius: 1.,
+            minor_radius: 0.25,
+            material: Material::default(),
+        }
+    }
+}
+
+impl Fingerprint for Torus {
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        state.debug(&(self.centre, self.major_radius, self.minor_radius));
+        self.material.fingerprint(state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_torus_intersections() {
+        let torus = Torus::default();
+        let ray = Ray::new(Vec3D::new(-5., 0., 0.), Vec3D::new(1., 0., 0.));
+        let intersections = torus.intersections(&ray);
+        assert_eq!(intersections.len(), 4);
+        assert_eq!(intersections[0].position, Vec3D::new(-1.25, 0., 0.));
+        assert_eq!(intersections[1].position, Vec3D::new(-0.75, 0., 0.));
+        assert_eq!(intersections[2].position, Vec3D::new(0.75, 0., 0.));
+        assert_eq!(intersections[3].position, Vec3D::new(1.25, 0., 0.));
+        assert_eq!(intersections[0].normal, -Vec3D::X);
+        assert_eq!(intersections[1].normal, Vec3D::X);
+        assert_eq!(intersections[2].normal, -Vec3D::X);
+        assert_eq!(intersections[3].normal, Vec3D::X);
+        assert_eq!(intersections[0].distance, 3.75);
+        assert_eq!(intersections[0].uv, (0.25, 0.5));
+    }
+
+    #[test]
+    fn test_torus_intersections_from_above() {
+        let torus = Torus::default();
+        let ray = Ray::new(Vec3D::new(0., 5., 1.), Vec3D::new(0., -1., 0.));
+        let intersections = torus.intersections(&ray);
+        assert_eq!(intersections.len(), 2);
+        assert_eq!(intersections[0].position, Vec3D::new(0., 0.25, 1.));
+        assert_eq!(intersections[0].normal, Vec3D::Y);
+        assert_eq!(intersections[1].normal, -Vec3D::Y);
+        assert_eq!(intersections[0].uv, (0.5, 0.75));
+    }
+
+    #[test]
+    fn test_torus_intersections_tangent() {
+        let torus = Torus::default();
+        let ray = Ray::new(Vec3D::new(1.25, 0., 5.), Vec3D::new(0., 0., -1.));
+        let intersections = torus.intersections(&ray);
+        assert_eq!(intersections.len(), 1);
+        assert_eq!(intersections[0].position, Vec3D::new(1.25, 0., 0.));
+        assert_eq!(intersections[0].normal, Vec3D::X);
+    }
+
+    #[test]
+    fn test_torus_intersections_miss() {
+        let torus = Torus::default();
+        let ray = Ray::new(Vec3D::new(0., 5., 0.), Vec3D::new(0., -1., 0.));
+        assert!(torus.intersections(&ray).is_empty());
+        let ray = Ray::new(Vec3D::new(-5., 0.3, 0.), Vec3D::new(1., 0., 0.));
+        assert!(torus.intersections(&ray).is_empty());
+    }
+
+    #[test]
+    fn test_torus_intersections_inside() {
+        let torus = Torus::default();
+        let ray = Ray::new(Vec3D::new(1., 0., 0.), Vec3D::new(1., 0., 0.));
+        let intersections = torus.intersections(&ray);
+        assert_eq!(intersections.len(), 1);
+        assert_eq!(intersections[0].position, Vec3D::new(1.25, 0., 0.));
+        assert_eq!(intersections[0].distance, 0.25);
+    }
+
+    #[test]
+    fn test_torus_intersections_edge_on() {
+        let torus = Torus::new(Vec3D::new(0., 0., -500.), 2., 0.1, Material::default());
+        let ray = Ray::new(Vec3D::ZERO, Vec3D::new(0.0021, 0., -1.).normalise());
+        let intersections = torus.intersections(&ray);
+        assert_eq!(intersections.len(), 4);
+        for intersection in &intersections {
+            let local = intersection.local_position;
+            let tube = Vec3D::new(local.x, 0., local.z).normalise() * 2.;
+            assert!(((local - tube).length() - 0.1).abs() < 1e-3);
+        }
+        let ray = Ray::new(Vec3D::ZERO, Vec3D::new(0.0041, 0., -1.).normalise());
+        assert_eq!(torus.intersections(&ray).len(), 2);
+    }
+}
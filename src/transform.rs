@@ -0,0 +1,142 @@
+use std::ops::Mul;
+
+use crate::{matrix::Mat4, quaternion::Quaternion, ray::Ray, vector::Vec3D};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    pub matrix: Mat4,
+    pub inverse: Mat4,
+}
+
+impl Transform {
+    pub const IDENTITY: Self = Self {
+        matrix: Mat4::IDENTITY,
+        inverse: Mat4::IDENTITY,
+    };
+
+    pub fn new(translation: Vec3D, rotation: Quaternion, scale: Vec3D) -> Self {
+        Self::translation(translation) * Self::rotation(rotation) * Self::scale(scale)
+    }
+
+    pub fn from_matrix(matrix: Mat4) -> Option<Self> {
+        Some(Self {
+            matrix,
+            inverse: matrix.inverse()?,
+        })
+    }
+
+    pub fn translation(offset: Vec3D) -> Self {
+        Self {
+            matrix: Mat4::translation(offset),
+            inverse: Mat4::translation(-offset),
+        }
+    }
+
+    pub fn rotation(rotation: Quaternion) -> Self {
+        let matrix = Mat4::rotation(rotation);
+        Self {
+            matrix,
+            inverse: matrix.transpose(),
+        }
+    }
+
+    pub fn scale(factors: Vec3D) -> Self {
+        Self {
+            matrix: Mat4::scale(factors),
+            inverse: Mat4::scale(Vec3D::new(1. / factors.x, 1. / factors.y, 1. / factors.z)),
+        }
+    }
+
+    pub fn inverse(self) -> Self {
+        Self {
+            matrix: self.inverse,
+            inverse: self.matrix,
+        }
+    }
+
+    pub fn normal_matrix(self) -> Mat4 {
+        self.inverse.transpose()
+    }
+
+    pub fn point(self, point: Vec3D) -> Vec3D {
+        self.matrix.transform_point(point)
+    }
+
+    pub fn vector(self, vector: Vec3D) -> Vec3D {
+        self.matrix.transform_vector(vector)
+    }
+
+    pub fn normal(self, normal: Vec3D) -> Vec3D {
+        self.normal_matrix().transform_vector(normal).normalise()
+    }
+
+    pub fn ray(self, ray: &Ray) -> Ray {
+        Ray::new(self.point(ray.origin), self.vector(ray.direction))
+    }
+
+    pub fn maximum_scale(self) -> f32 {
+        [Vec3D::X, Vec3D::Y, Vec3D::Z]
+            .map(|axis| self.vector(axis).length())
+            .into_iter()
+            .fold(0., f32::max)
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Mul for Transform {
+    type Output = Self;
+    fn mul(self, rhs: Self) -> Self::Output {
+        Self {
+            matrix: self.matrix * rhs.matrix,
+            inverse: rhs.inverse * self.inverse,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use super::*;
+
+    #[test]
+    fn test_composition_order() {
+        let transform = Transform::new(
+            Vec3D::new(0., 0., 5.),
+            Quaternion::from_axis_angle(Vec3D::Z, PI / 2.),
+            Vec3D::new(2., 1., 1.),
+        );
+        assert_eq!(transform.point(Vec3D::X), Vec3D::new(0., 2., 5.));
+        assert_eq!(transform.vector(Vec3D::X), Vec3D::new(0., 2., 0.));
+        assert_eq!(transform.inverse().point(Vec3D::new(0., 2., 5.)), Vec3D::X);
+    }
+
+    #[test]
+    fn test_inverse() {
+        let transform = Transform::new(
+            Vec3D::new(1., -2., 3.),
+            Quaternion::from_axis_angle(Vec3D::new(1., 1., 0.), 0.8),
+            Vec3D::new(0.5, 2., 3.),
+        );
+        assert_eq!(transform.inverse, transform.matrix.inverse().unwrap());
+        assert_eq!((transform * transform.inverse()).matrix, Mat4::IDENTITY);
+        let from_matrix = Transform::from_matrix(transform.matrix).unwrap();
+        assert_eq!(from_matrix.inverse, transform.inverse);
+    }
+
+    #[test]
+    fn test_normals_under_non_uniform_scale() {
+        let transform = Transform::scale(Vec3D::new(1., 4., 1.));
+        let normal = Vec3D::new(1., 1., 0.).normalise();
+        let tangent = Vec3D::new(-1., 1., 0.);
+        let transformed = transform.normal(normal);
+        assert_eq!(transformed.length(), 1.);
+        assert_eq!(transformed.dot(transform.vector(tangent)), 0.);
+        assert_ne!(transformed, transform.vector(normal).normalise());
+    }
+}
@@ -47,6 +47,16 @@ impl Vec3D {
     pub fn reflect(self, plane_normal: Self) -> Self {
         self - plane_normal * 2. * self.dot(plane_normal)
     }
+
+    pub fn basis(self) -> (Self, Self) {
+        let sign = 1_f32.copysign(self.z);
+        let a = -1. / (sign + self.z);
+        let b = self.x * self.y * a;
+        (
+            Self::new(1. + sign * self.x * self.x * a, sign * b, -sign * self.x),
+            Self::new(b, sign + self.y * self.y * a, -self.y),
+        )
+    }
 }
 
 impl PartialEq for Vec3D {
@@ -237,4 +247,24 @@ mod tests {
             Vec3D::new(-1. / 3., -1. / 3., -4. / 3.)
         );
     }
+
+    #[test]
+    fn test_basis() {
+        for normal in [
+            Vec3D::X,
+            Vec3D::Y,
+            Vec3D::Z,
+            -Vec3D::Z,
+            Vec3D::new(1., 2., 3.).normalise(),
+            Vec3D::new(-3., 1., -0.5).normalise(),
+        ] {
+            let (tangent, bitangent) = normal.basis();
+            assert!((tangent.length() - 1.).abs() < EPSILON);
+            assert!((bitangent.length() - 1.).abs() < EPSILON);
+            assert!(tangent.dot(normal).abs() < EPSILON);
+            assert!(bitangent.dot(normal).abs() < EPSILON);
+            assert!(tangent.dot(bitangent).abs() < EPSILON);
+            assert_eq!(tangent.cross(bitangent), normal);
+        }
+    }
 }
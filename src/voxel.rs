@@ -0,0 +1,486 @@
+use std::{
+    ffi::OsStr,
+    fs::File,
+    io::{self, BufReader, ErrorKind, Read},
+    path::Path,
+    sync::Arc,
+};
+
+use crate::{
+    checkpoint::{Fingerprint, Fingerprinter},
+    colour::Colour,
+    medium::Medium,
+    ray::Ray,
+    sampler::Sampler,
+    vector::Vec3D,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DensityGrid {
+    pub resolution: (usize, usize, usize),
+    pub densities: Vec<f32>,
+    maximum: f32,
+}
+
+impl DensityGrid {
+    pub fn new(resolution: (usize, usize, usize), densities: Vec<f32>) -> io::Result<Self> {
+        let expected = resolution
+            .0
+            .checked_mul(resolution.1)
+            .and_then(|count| count.checked_mul(resolution.2))
+            .ok_or_else(|| {
+                invalid_data(format!(
+                    "density grid resolution {resolution:?} is too large"
+                ))
+            })?;
+        if expected == 0 || densities.len() != expected {
+            return Err(invalid_data(format!(
+                "expected {expected} densities, found {}",
+                densities.len()
+            )));
+        }
+        if densities
+            .iter()
+            .any(|density| !density.is_finite() || *density < 0.)
+        {
+            return Err(invalid_data(
+                "densities must be finite and non-negative".to_string(),
+            ));
+        }
+        let maximum = densities.iter().copied().fold(0., f32::max);
+        Ok(Self {
+            resolution,
+            densities,
+            maximum,
+        })
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let reader = BufReader::new(File::open(path)?);
+        match path
+            .extension()
+            .and_then(OsStr::to_str)
+            .map(str::to_ascii_lowercase)
+            .as_deref()
+        {
+            Some("raw") => Self::read_raw(reader),
+            Some("txt" | "vol") => Self::read_text(reader),
+            _ => Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                format!("unsupported density grid format: {}", path.display()),
+            )),
+        }
+    }
+
+    pub fn read_text(mut reader: impl Read) -> io::Result<Self> {
+        let mut text = String::new();
+        reader.read_to_string(&mut text)?;
+        let mut tokens = text.lines().flat_map(|line| {
+            line.split('#')
+                .next()
+                .unwrap_or_default()
+                .split_whitespace()
+        });
+        let mut dimension = || -> io::Result<usize> {
+            let token = tokens
+                .next()
+                .ok_or_else(|| invalid_data("missing density grid resolution".to_string()))?;
+            token
+                .parse()
+                .map_err(|_| invalid_data(format!("invalid density grid resolution {token}")))
+        };
+        let resolution = (dimension()?, dimension()?, dimension()?);
+        let densities = tokens
+            .map(|token| {
+                token
+                    .parse()
+                    .map_err(|_| invalid_data(format!("invalid density {token}")))
+            })
+            .collect::<io::Result<Vec<f32>>>()?;
+        Self::new(resolution, densities)
+    }
+
+    pub fn read_raw(mut reader: impl Read) -> io::Result<Self> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        if bytes.len() < 12 {
+            return Err(invalid_data("truncated density grid header".to_string()));
+        }
+        let word = |index: usize| <[u8; 4]>::try_from(&bytes[index * 4..index * 4 + 4]).unwrap();
+        let resolution = (
+            u32::from_le_bytes(word(0)) as usize,
+            u32::from_le_bytes(word(1)) as usize,
+            u32::from_le_bytes(word(2)) as usize,
+        );
+        let densities = bytes[12..]
+            .chunks_exact(4)
+            .map(|chunk| f32::from_le_bytes(chunk.try_into().unwrap()))
+            .collect();
+        Self::new(resolution, densities)
+    }
+
+    pub fn maximum(&self) -> f32 {
+        self.maximum
+    }
+
+    pub fn voxel(&self, x: usize, y: usize, z: usize) -> f32 {
+        self.densities[(z * self.resolution.1 + y) * self.resolution.0 + x]
+    }
+
+    pub fn density(&self, local: Vec3D) -> f32 {
+        if !(0. ..=1.).contains(&local.x)
+            || !(0. ..=1.).contains(&local.y)
+            || !(0. ..=1.).contains(&local.z)
+        {
+            return 0.;
+        }
+        let axis = |value: f32, size: usize| {
+            let position = (value * size as f32 - 0.5).clamp(0., (size - 1) as f32);
+            let lower = (position.floor() as usize).min(size.saturating_sub(2));
+            (lower, (lower + 1).min(size - 1), position - lower as f32)
+        };
+        let (x0, x1, tx) = axis(local.x, self.resolution.0);
+        let (y0, y1, ty) = axis(local.y, self.resolution.1);
+        let (z0, z1, tz) = axis(local.z, self.resolution.2);
+        let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+        let plane = |z: usize| {
+            lerp(
+                lerp(self.voxel(x0, y0, z), self.voxel(x1, y0, z), tx),
+                lerp(self.voxel(x0, y1, z), self.voxel(x1, y1, z), tx),
+                ty,
+            )
+        };
+        lerp(plane(z0), plane(z1), tz)
+    }
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Tracking {
+    #[default]
+    Delta,
+    Ratio,
+}
+
+#[derive(Debug, Clone)]
+pub struct GridVolume {
+    pub grid: Arc<DensityGrid>,
+    pub minimum: Vec3D,
+    pub maximum: Vec3D,
+    pub extinction: f32,
+    pub albedo: Colour,
+    pub asymmetry: f32,
+    pub tracking: Tracking,
+}
+
+impl GridVolume {
+    pub fn new(grid: Arc<DensityGrid>, minimum: Vec3D, maximum: Vec3D, extinction: f32) -> Self {
+        Self {
+            grid,
+            minimum,
+            maximum,
+            extinction,
+            albedo: Colour::gray(1.),
+            asymmetry: 0.,
+            tracking: Tracking::default(),
+        }
+    }
+
+    pub fn density(&self, position: Vec3D) -> f32 {
+        let size = self.maximum - self.minimum;
+        let local = position - self.minimum;
+        self.grid.density(Vec3D::new(
+            local.x / size.x,
+            local.y / size.y,
+            local.z / size.z,
+        ))
+    }
+
+    pub fn medium(&self, position: Vec3D) -> Medium {
+        let extinction = self.extinction * self.density(position);
+        Medium::new(
+            (Colour::gray(1.) - self.albedo) * extinction,
+            self.albedo * extinction,
+            self.asymmetry,
+        )
+    }
+
+    fn majorant(&self) -> f32 {
+        self.extinction * self.grid.maximum()
+    }
+
+    fn bounds(&self, ray: &Ray, max_distance: f32) -> Option<(f32, f32)> {
+        let mut near = 0_f32;
+        let mut far = max_distance;
+        for (origin, direction, minimum, maximum) in [
+            (
+                ray.origin.x,
+                ray.direction.x,
+                self.minimum.x,
+                self.maximum.x,
+            ),
+            (
+                ray.origin.y,
+                ray.direction.y,
+                self.minimum.y,
+                self.maximum.y,
+            ),
+            (
+                ray.origin.z,
+                ray.direction.z,
+                self.minimum.z,
+                self.maximum.z,
+            ),
+        ] {
+            if direction == 0. {
+                if origin < minimum || origin > maximum {
+                    return None;
+                }
+                continue;
+            }
+            let first = (minimum - origin) / direction;
+            let second = (maximum - origin) / direction;
+            near = near.max(first.min(second));
+            far = far.min(first.max(second));
+        }
+        (near < far).then_some((near, far))
+    }
+
+    pub fn sample_distance(
+        &self,
+        ray: &Ray,
+        max_distance: f32,
+        sampler: &mut dyn Sampler,
+    ) -> Option<f32> {
+        let majorant = self.majorant();
+        let (mut distance, far) = self.bounds(ray, max_distance)?;
+        if majorant <= 0. {
+            return None;
+        }
+        loop {
+            distance -= (1. - sampler.next_f32()).ln() / majorant;
+            if distance >= far {
+                return None;
+            }
+            let density = self.density(ray.origin + ray.direction * distance);
+            if sampler.next_f32() * self.grid.maximum() < density {
+                return Some(distance);
+            }
+        }
+    }
+
+    pub fn transmittance(&self, ray: &Ray, max_distance: f32, sampler: &mut dyn Sampler) -> f32 {
+        match self.tracking {
+            Tracking::Delta => {
+                if self.sample_distance(ray, max_distance, sampler).is_some() {
+                    0.
+                } else {
+                    1.
+                }
+            }
+            Tracking::Ratio => {
+                let majorant = self.majorant();
+                let Some((mut distance, far)) = self.bounds(ray, max_distance) else {
+                    return 1.;
+                };
+                if majorant <= 0. {
+                    return 1.;
+                }
+                let mut transmittance = 1.;
+                loop {
+                    distance -= (1. - sampler.next_f32()).ln() / majorant;
+                    if distance >= far {
+                        return transmittance;
+                    }
+                    let density = self.density(ray.origin + ray.direction * distance);
+                    transmittance *= 1. - density / self.grid.maximum();
+                }
+            }
+        }
+    }
+}
+
+impl Fingerprint for DensityGrid {
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        state.debug(&self.resolution);
+        state.floats(self.densities.iter().copied());
+    }
+}
+
+impl Fingerprint for GridVolume {
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        self.grid.fingerprint(state);
+        state.debug(&(
+            self.minimum,
+            self.maximum,
+            self.extinction,
+            self.albedo,
+            self.asymmetry,
+            self.tracking,
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{random::Rng, scene::Scene};
+
+    fn uniform(density: f32) -> GridVolume {
+        GridVolume::new(
+            Arc::new(DensityGrid::new((2, 2, 2), vec![density; 8]).unwrap()),
+            Vec3D::triple(-1.),
+            Vec3D::triple(1.),
+            1.,
+        )
+    }
+
+    #[test]
+    fn test_new_validates() {
+        assert!(DensityGrid::new((1, 2, 1), vec![0.5, 1.]).is_ok());
+        assert!(DensityGrid::new((2, 2, 1), vec![0.5, 1.]).is_err());
+        assert!(DensityGrid::new((0, 0, 0), Vec::new()).is_err());
+        assert!(DensityGrid::new((usize::MAX, 2, 1), vec![1.]).is_err());
+        for density in [-1., f32::NAN, f32::INFINITY] {
+            let error = DensityGrid::new((1, 1, 1), vec![density]).unwrap_err();
+            assert_eq!(error.kind(), ErrorKind::InvalidData);
+        }
+    }
+
+    #[test]
+    fn test_read_text() {
+        let text = "# a 2x1x2 grid\n2 1 2\n0 1\n0.5 2 # last row\n";
+        let grid = DensityGrid::read_text(text.as_bytes()).unwrap();
+        assert_eq!(grid.resolution, (2, 1, 2));
+        assert_eq!(grid.voxel(1, 0, 1), 2.);
+        assert_eq!(grid.maximum(), 2.);
+        assert!(DensityGrid::read_text("2 2 2\n1 2 3".as_bytes()).is_err());
+        assert!(DensityGrid::read_text("1 1 1\n-1".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_read_raw() {
+        let mut bytes = Vec::new();
+        for dimension in [1_u32, 2, 1] {
+            bytes.extend(dimension.to_le_bytes());
+        }
+        for density in [0.25_f32, 0.75] {
+            bytes.extend(density.to_le_bytes());
+        }
+        let grid = DensityGrid::read_raw(bytes.as_slice()).unwrap();
+        assert_eq!(grid.resolution, (1, 2, 1));
+        assert_eq!(grid.densities, vec![0.25, 0.75]);
+        assert!(DensityGrid::read_raw(&bytes[..14]).is_err());
+        let mut huge = Vec::new();
+        for dimension in [u32::MAX; 3] {
+            huge.extend(dimension.to_le_bytes());
+        }
+        let error = DensityGrid::read_raw(huge.as_slice()).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_trilinear_density() {
+        let grid = DensityGrid::new((2, 1, 1), vec![0., 1.]).unwrap();
+        assert_eq!(grid.density(Vec3D::new(0.25, 0.5, 0.5)), 0.);
+        assert_eq!(grid.density(Vec3D::new(0.5, 0.5, 0.5)), 0.5);
+        assert_eq!(grid.density(Vec3D::new(0.9, 0.1, 0.3)), 1.);
+        assert_eq!(grid.density(Vec3D::new(1.5, 0.5, 0.5)), 0.);
+    }
+
+    #[test]
+    fn test_tracking_matches_beer_lambert() {
+        let ray = Ray::new(Vec3D::new(-5., 0., 0.), Vec3D::X);
+        let expected = (-0.7_f32 * 2.).exp();
+        let mut volume = uniform(0.7);
+        let mut rng = Rng::new(4, 0);
+        let count = 20000;
+        for tracking in [Tracking::Delta, Tracking::Ratio] {
+            volume.tracking = tracking;
+            let estimate = (0..count)
+                .map(|_| volume.transmittance(&ray, f32::INFINITY, &mut rng))
+                .sum::<f32>()
+                / count as f32;
+            assert!(
+                (estimate - expected).abs() < 0.01,
+                "{tracking:?}: {estimate}"
+            );
+        }
+        assert_eq!(volume.transmittance(&ray, 3., &mut rng), 1.);
+    }
+
+    #[test]
+    fn test_heterogeneous_tracking() {
+        let grid = DensityGrid::new((4, 1, 1), vec![0., 0.5, 1.5, 3.]).unwrap();
+        let volume = GridVolume {
+            tracking: Tracking::Ratio,
+            ..GridVolume::new(Arc::new(grid), Vec3D::triple(-1.), Vec3D::triple(1.), 1.)
+        };
+        let ray = Ray::new(Vec3D::new(-5., 0., 0.), Vec3D::X);
+        let steps = 10000;
+        let optical_depth = (0..steps)
+            .map(|i| {
+                volume.density(Vec3D::new(
+                    -1. + 2. * (i as f32 + 0.5) / steps as f32,
+                    0.,
+                    0.,
+                ))
+            })
+            .sum::<f32>()
+            * 2.
+            / steps as f32;
+        let mut rng = Rng::new(5, 0);
+        let count = 20000;
+        let ratio = (0..count)
+            .map(|_| volume.transmittance(&ray, f32::INFINITY, &mut rng))
+            .sum::<f32>()
+            / count as f32;
+        let delta = (0..count)
+            .filter(|_| {
+                volume
+                    .sample_distance(&ray, f32::INFINITY, &mut rng)
+                    .is_none()
+            })
+            .count() as f32
+            / count as f32;
+        assert!((ratio - (-optical_depth).exp()).abs() < 0.01);
+        assert!((delta - (-optical_depth).exp()).abs() < 0.01);
+        let collisions = (0..2000)
+            .filter_map(|_| volume.sample_distance(&ray, f32::INFINITY, &mut rng))
+            .collect::<Vec<f32>>();
+        assert!(collisions.iter().all(|distance| *distance > 4.25));
+    }
+
+    #[test]
+    fn test_scene_sampling() {
+        let scene = Scene {
+            grid_volumes: vec![uniform(0.5)],
+            ..Default::default()
+        };
+        let ray = Ray::new(Vec3D::new(-5., 0., 0.), Vec3D::X * 2.);
+        let mut rng = Rng::new(6, 0);
+        let count = 20000;
+        let mut escaped = 0;
+        for _ in 0..count {
+            match scene.sample_medium(&ray, f32::INFINITY, &mut rng) {
+                (Some(event), weight) => {
+                    assert!((-1. ..=1.).contains(&event.position.x));
+                    let albedo = weight * event.media[0].scattering;
+                    assert!((albedo - Colour::gray(1.))
+                        .as_rgb()
+                        .iter()
+                        .all(|x| x.abs() < 1e-5));
+                }
+                (None, weight) => {
+                    assert_eq!(weight, Colour::gray(1.));
+                    escaped += 1;
+                }
+            }
+        }
+        assert!((escaped as f32 / count as f32 - (-1_f32).exp()).abs() < 0.01);
+    }
+}